@@ -63,7 +63,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("std channels 8192 unbounded", |bench| {
         bench.iter(|| {
             use std::sync::mpsc::{Receiver, Sender};
-            let (mut prod, mut cons): (Sender<[u8; 8192]>, Receiver<[u8; 8192]>) =
+            let (prod, cons): (Sender<[u8; 8192]>, Receiver<[u8; 8192]>) =
                 std::sync::mpsc::channel();
             let rdata = &data;
 
@@ -82,7 +82,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                 sc.spawn(move |_| {
                     rdata.chunks(8192).for_each(|ch| {
                         let x = cons.recv().unwrap();
-                        assert_eq!(&x[..], &ch[..]);
+                        assert_eq!(&x[..], ch);
                     });
                 });
             })
@@ -92,8 +92,8 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 
     c.bench_function("xbeam channels 8192/65536", |bench| {
         bench.iter(|| {
-            use crossbeam::{bounded, Receiver, Sender};
-            let (mut prod, mut cons): (Sender<[u8; 8192]>, Receiver<[u8; 8192]>) =
+            use crossbeam::channel::{bounded, Receiver, Sender};
+            let (prod, cons): (Sender<[u8; 8192]>, Receiver<[u8; 8192]>) =
                 bounded(65536 / 8192);
             let rdata = &data;
 
@@ -112,7 +112,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                 sc.spawn(move |_| {
                     rdata.chunks(8192).for_each(|ch| {
                         let x = cons.recv().unwrap();
-                        assert_eq!(&x[..], &ch[..]);
+                        assert_eq!(&x[..], ch);
                     });
                 });
             })
@@ -143,7 +143,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                         sc.spawn(move |_| {
                             rdata.chunks(8192).for_each(|ch| {
                                 let x = cons.pop();
-                                assert_eq!(&x[..], &ch[..]);
+                                assert_eq!(&x[..], ch);
                             });
                         });
                     }).unwrap();
@@ -183,7 +183,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                 sc.spawn(|_| {
                     data.chunks(8192).for_each(|ch| loop {
                         if let Some(x) = cons.dequeue() {
-                            assert_eq!(&x[..], &ch[..]);
+                            assert_eq!(&x[..], ch);
                             break;
                         }
                     });
@@ -200,7 +200,7 @@ fn chunky(data: &[u8], chunksz: usize) {
     let (mut prod, mut cons) = buffy.try_split().unwrap();
 
     thread::scope(|sc| {
-        let pjh = sc.spawn(|_| {
+        sc.spawn(|_| {
             data.chunks(chunksz).for_each(|ch| loop {
                 if let Ok(mut wgr) = prod.grant_exact(chunksz) {
                     wgr.copy_from_slice(ch);
@@ -210,7 +210,7 @@ fn chunky(data: &[u8], chunksz: usize) {
             });
         });
 
-        let cjh = sc.spawn(|_| {
+        sc.spawn(|_| {
             data.chunks(chunksz).for_each(|ch| {
                 let mut st = 0;
                 loop {
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use bbqueue::{
+        cobs::{CobsDecoder, CobsProducer},
+        BBBuffer, Error,
+    };
+
+    #[test]
+    fn cobs_sanity() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = CobsProducer::new(prod);
+        let mut cons: CobsDecoder<64, 32> = CobsDecoder::new(cons);
+
+        // Nothing to receive yet
+        assert_eq!(cons.recv(), Err(Error::Empty));
+
+        prod.send(&[]).unwrap();
+        prod.send(&[0]).unwrap();
+        prod.send(&[1, 2, 0, 0, 3]).unwrap();
+
+        assert_eq!(cons.recv().unwrap(), &[]);
+        assert_eq!(cons.recv().unwrap(), &[0]);
+        assert_eq!(cons.recv().unwrap(), &[1, 2, 0, 0, 3]);
+        assert_eq!(cons.recv(), Err(Error::Empty));
+
+        // The raw encoding is what ends up in the queue
+        let mut cons = cons.into_inner();
+        prod.send(&[0x11, 0x00, 0x22]).unwrap();
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[0x02, 0x11, 0x02, 0x22, 0x00]);
+        rgr.release(5);
+    }
+
+    #[test]
+    fn cobs_long_runs() {
+        let bb: BBBuffer<2048> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = CobsProducer::new(prod);
+        let mut cons: CobsDecoder<2048, 600> = CobsDecoder::new(cons);
+
+        for len in [253usize, 254, 255, 508, 509] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 255) as u8 + 1).collect();
+            prod.send(&data).unwrap();
+            assert_eq!(cons.recv().unwrap(), &data[..]);
+        }
+    }
+
+    #[test]
+    fn cobs_wrap() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = CobsProducer::new(prod);
+        let mut cons: CobsDecoder<16, 16> = CobsDecoder::new(cons);
+
+        for i in 0..100u8 {
+            let data = [i, 0, i.wrapping_add(1), 0, 0];
+            prod.send(&data).unwrap();
+            assert_eq!(cons.recv().unwrap(), &data);
+        }
+    }
+
+    #[test]
+    fn cobs_bad_frames_are_discarded() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, cons) = bb.try_split().unwrap();
+        let mut cons: CobsDecoder<64, 4> = CobsDecoder::new(cons);
+
+        // A truncated block, followed by a valid frame
+        let mut wgr = prod.grant_exact(6).unwrap();
+        wgr.copy_from_slice(&[0x05, 0x01, 0x00, 0x02, 0x01, 0x00]);
+        wgr.commit(6);
+        assert_eq!(cons.recv(), Err(Error::InvalidFrame));
+        assert_eq!(cons.recv().unwrap(), &[0x01]);
+
+        // Too large for the scratch buffer
+        let mut prod = CobsProducer::new(prod);
+        prod.send(&[1, 2, 3, 4, 5]).unwrap();
+        prod.send(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(cons.recv(), Err(Error::InsufficientSize));
+        assert_eq!(cons.recv().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn cobs_grant_in_place() {
+        let bb: BBBuffer<4096> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = CobsProducer::new(prod);
+        let mut cons: CobsDecoder<4096, 1024> = CobsDecoder::new(cons);
+
+        let zeros = vec![0u8; 300];
+        let mixed: Vec<u8> = (0..600)
+            .map(|i| if i % 3 == 0 { 0 } else { i as u8 })
+            .collect();
+        let runs: Vec<u8> = (0..1000).map(|i| (i % 255) as u8 + 1).collect();
+
+        for data in [&zeros[..], &mixed[..], &runs[..], &[]] {
+            // Use the maximum size of the grant
+            let mut wgr = prod.grant(data.len()).unwrap();
+            wgr.copy_from_slice(data);
+            wgr.commit(data.len());
+            assert_eq!(cons.recv().unwrap(), data);
+
+            // And a much larger grant than needed
+            let mut wgr = prod.grant(data.len() + 300).unwrap();
+            wgr[..data.len()].copy_from_slice(data);
+            wgr.commit(data.len());
+            assert_eq!(cons.recv().unwrap(), data);
+        }
+
+        // Dropping a grant sends nothing
+        drop(prod.grant(10).unwrap());
+        assert_eq!(cons.recv(), Err(Error::Empty));
+    }
+
+    #[test]
+    fn cobs_recv_raw() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = CobsProducer::new(prod);
+        let mut cons: CobsDecoder<16, 16> = CobsDecoder::new(cons);
+        assert_eq!(cons.recv_raw().unwrap_err(), Error::Empty);
+
+        prod.send(&[0x11, 0x00, 0x22]).unwrap();
+        let rgr = cons.recv_raw().unwrap();
+        assert_eq!(&*rgr, &[0x02, 0x11, 0x02, 0x22, 0x00]);
+        rgr.release(5);
+        assert_eq!(cons.recv_raw().unwrap_err(), Error::Empty);
+    }
+}
@@ -0,0 +1,44 @@
+//! A `critical-section` implementation for the host, which counts the
+//! critical sections taken by each thread. This lets the tests check that
+//! granting, committing, reading and releasing never need one, which is what
+//! a single core target without read-modify-write atomics relies on.
+
+use std::cell::{Cell, RefCell};
+use std::sync::{Mutex, MutexGuard};
+
+struct CountingCriticalSection;
+critical_section::set_impl!(CountingCriticalSection);
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static GUARD: RefCell<Option<MutexGuard<'static, ()>>> = const { RefCell::new(None) };
+    static TAKEN: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The number of critical sections the current thread has entered
+#[cfg(test)]
+pub fn taken() -> usize {
+    TAKEN.with(Cell::get)
+}
+
+unsafe impl critical_section::Impl for CountingCriticalSection {
+    unsafe fn acquire() -> bool {
+        TAKEN.with(|taken| taken.set(taken.get() + 1));
+        GUARD.with(|guard| {
+            let mut guard = guard.borrow_mut();
+            if guard.is_some() {
+                // Nested in a critical section of this thread
+                return true;
+            }
+            *guard = Some(LOCK.lock().unwrap_or_else(|err| err.into_inner()));
+            false
+        })
+    }
+
+    unsafe fn release(nested: bool) {
+        if !nested {
+            GUARD.with(|guard| guard.borrow_mut().take());
+        }
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use bbqueue::BBBuffer;
+    use bbqueue::{
+        framed::{Crc16, Crc32, FrameHeader, Leb128Header},
+        BBBuffer, Error,
+    };
 
     #[test]
     fn frame_wrong_size() {
@@ -160,4 +163,388 @@ mod tests {
 
         assert!(cons.read().is_none());
     }
+
+    #[test]
+    fn frame_variable_sizes_wrap() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        // Keep up to two frames in flight, so the frames wrap around
+        // the end of the buffer at varying positions
+        let mut in_flight: std::collections::VecDeque<Vec<u8>> = Default::default();
+        for i in 0..1000usize {
+            let sz = (i * 7) % 20 + 1;
+
+            if in_flight.len() == 2 {
+                let expected = in_flight.pop_front().unwrap();
+                let rgr = cons.read().unwrap();
+                assert_eq!(&*rgr, &expected[..]);
+                rgr.release();
+            }
+
+            let frame: Vec<u8> = (0..sz).map(|b| (b + i) as u8).collect();
+            let mut wgr = prod.grant(sz).unwrap();
+            wgr.copy_from_slice(&frame);
+            wgr.commit(sz);
+            in_flight.push_back(frame);
+        }
+
+        while let Some(expected) = in_flight.pop_front() {
+            let rgr = cons.read().unwrap();
+            assert_eq!(&*rgr, &expected[..]);
+            rgr.release();
+        }
+        assert!(cons.read().is_none());
+    }
+
+    #[test]
+    fn frame_zero_length() {
+        let bb: BBBuffer<32> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        // Keep up to two frames in flight, so that empty frames land at
+        // every position in the buffer, including right at the end
+        let mut in_flight: std::collections::VecDeque<Vec<u8>> = Default::default();
+        for i in 0..1000usize {
+            let sz = [5, 0, 0, 10][i % 4];
+
+            if in_flight.len() == 2 {
+                let expected = in_flight.pop_front().unwrap();
+                let rgr = cons.read().unwrap();
+                assert_eq!(&*rgr, &expected[..]);
+                rgr.release();
+            }
+
+            let frame: Vec<u8> = (0..sz).map(|b| (b + i) as u8).collect();
+            // Both an exact empty grant, and an empty commit of a larger grant
+            let mut wgr = prod.grant(if i % 8 == 1 { 0 } else { sz + 1 }).unwrap();
+            wgr[..sz].copy_from_slice(&frame);
+            wgr.commit(sz);
+            in_flight.push_back(frame);
+        }
+
+        while let Some(expected) = in_flight.pop_front() {
+            let rgr = cons.read().unwrap();
+            assert_eq!(&*rgr, &expected[..]);
+            rgr.release();
+        }
+        assert!(cons.read().is_none());
+
+        // An empty frame in the very last byte of the buffer
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+        prod.grant(6).unwrap().commit(6);
+        cons.read().unwrap().release();
+        prod.grant(0).unwrap().commit(0);
+        prod.grant(5).unwrap().commit(5);
+
+        let rgr = cons.read().unwrap();
+        assert!(rgr.is_empty());
+        rgr.release();
+        let rgr = cons.read().unwrap();
+        assert_eq!(rgr.len(), 5);
+        rgr.release();
+        assert!(cons.read().is_none());
+    }
+
+    fn fixed_header_roundtrip<H: FrameHeader, const N: usize>(bb: &BBBuffer<N>, max_sz: usize) {
+        let (mut prod, mut cons) = bb.try_split_framed_with::<H>().unwrap();
+
+        // One more than the header can represent
+        assert!(matches!(prod.grant(max_sz + 1), Err(Error::FrameTooLarge)));
+
+        let mut wgr = prod.grant(max_sz).unwrap();
+        for (i, by) in wgr.iter_mut().enumerate() {
+            *by = i as u8;
+        }
+        wgr.commit(max_sz);
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(rgr.len(), max_sz);
+        for (i, by) in rgr.iter().enumerate() {
+            assert_eq!(*by, i as u8);
+        }
+        rgr.release();
+        assert!(cons.read().is_none());
+    }
+
+    #[test]
+    fn frame_fixed_headers() {
+        let bb: BBBuffer<300> = BBBuffer::new();
+        fixed_header_roundtrip::<u8, 300>(&bb, 255);
+
+        let bb: Box<BBBuffer<65600>> = Box::new(BBBuffer::new());
+        fixed_header_roundtrip::<u16, 65600>(&bb, 65535);
+
+        // The header takes four bytes, regardless of the frame size
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed_with::<u32>().unwrap();
+        assert_eq!(prod.grant(61).unwrap_err(), Error::FrameTooLarge);
+        prod.grant(60).unwrap().commit(1);
+        assert_eq!(cons.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn frame_leb128_headers() {
+        let bb: Box<BBBuffer<40000>> = Box::new(BBBuffer::new());
+        let (mut prod, mut cons) = bb.try_split_framed_with::<Leb128Header>().unwrap();
+
+        for &len in [0, 127, 128, 16383, 16384].iter() {
+            // Grant more than needed, so the header must shrink on commit
+            let mut wgr = prod.grant(len + 200).unwrap();
+            for (i, by) in wgr.iter_mut().enumerate() {
+                *by = i as u8;
+            }
+            wgr.commit(len);
+
+            let rgr = cons.read().unwrap();
+            assert_eq!(rgr.len(), len);
+            for (i, by) in rgr.iter().enumerate() {
+                assert_eq!(*by, i as u8);
+            }
+            rgr.release();
+        }
+        assert!(cons.read().is_none());
+    }
+
+    #[test]
+    fn frame_leb128_tight() {
+        let bb: BBBuffer<130> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed_with::<Leb128Header>().unwrap();
+
+        // Two header bytes are reserved, but only one is used
+        let mut wgr = prod.grant(128).unwrap();
+        wgr[0] = 42;
+        wgr.to_commit(1);
+        drop(wgr);
+
+        // Fits exactly, as the first frame only takes two bytes
+        let mut wgr = prod.grant(127).unwrap();
+        wgr[126] = 43;
+        wgr.commit(127);
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[42]);
+        rgr.release();
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(rgr.len(), 127);
+        assert_eq!(rgr[126], 43);
+        rgr.release();
+    }
+
+    #[test]
+    fn frame_consumer_auto_release() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+        cons.set_auto_release(true);
+
+        for i in 0..10u8 {
+            // Only fits if the previous frames were released
+            for j in 0..2 {
+                let mut wgr = prod.grant(20).unwrap();
+                wgr[0] = i + j;
+                wgr.commit(20);
+            }
+            for j in 0..2 {
+                let rgr = cons.read().unwrap();
+                assert_eq!(rgr[0], i + j);
+                // drop
+            }
+        }
+        assert!(cons.read().is_none());
+
+        // Opt out for a single frame
+        prod.grant(20).unwrap().commit(20);
+        let mut rgr = cons.read().unwrap();
+        rgr.auto_release(false);
+        drop(rgr);
+        assert_eq!(cons.read().unwrap().len(), 20);
+        assert!(cons.read().is_none());
+
+        // Explicit release works in manual mode, and dropping keeps the frame
+        cons.set_auto_release(false);
+        prod.grant(20).unwrap().commit(20);
+        drop(cons.read().unwrap());
+        let rgr = cons.read().unwrap();
+        assert_eq!(rgr.len(), 20);
+        rgr.release();
+        assert!(cons.read().is_none());
+    }
+
+    #[test]
+    fn frame_iterator() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        for round in 0..10u8 {
+            for i in 0..5u8 {
+                let mut wgr = prod.grant(8).unwrap();
+                wgr[0] = round;
+                wgr[1] = i;
+                wgr.commit(usize::from(i) + 2);
+            }
+
+            let mut count = 0u8;
+            for frame in cons.frames() {
+                assert_eq!(frame.len(), usize::from(count) + 2);
+                assert_eq!(&frame[..2], &[round, count]);
+                count += 1;
+            }
+            assert_eq!(count, 5);
+            assert!(cons.read().is_none());
+        }
+
+        // Holding on to a frame ends the iteration early
+        prod.grant(1).unwrap().commit(1);
+        prod.grant(1).unwrap().commit(1);
+        let mut frames = cons.frames();
+        let first = frames.next().unwrap();
+        assert!(frames.next().is_none());
+        drop(first);
+        assert_eq!(frames.count(), 1);
+        assert!(cons.read().is_none());
+    }
+
+    #[test]
+    fn frame_peek() {
+        let bb: BBBuffer<32> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        for round in 0..20u8 {
+            for ty in 0..3u8 {
+                let mut wgr = prod.grant(3).unwrap();
+                wgr.copy_from_slice(&[ty, round, ty]);
+                wgr.commit(usize::from(ty) + 1);
+            }
+
+            for ty in 0..3u8 {
+                // Peek and decline, repeatedly
+                for _ in 0..2 {
+                    let frame = cons.peek_frame().unwrap();
+                    assert_eq!(frame, &[ty, round, ty][..usize::from(ty) + 1]);
+                }
+
+                // Peeking is not possible while a frame is held
+                let rgr = cons.read().unwrap();
+                assert!(cons.peek_frame().is_none());
+                assert_eq!(&*rgr, &[ty, round, ty][..usize::from(ty) + 1]);
+                rgr.release();
+            }
+            assert!(cons.peek_frame().is_none());
+        }
+    }
+
+    #[test]
+    fn frame_max_size() {
+        const TOTAL: usize = 5_000;
+        static BB: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split_framed_with_max::<u8>(31).unwrap();
+
+        // Too large frames fail fast, rather than waiting for space
+        assert_eq!(prod.grant(32).unwrap_err(), Error::FrameTooLarge);
+
+        let consumer = std::thread::spawn(move || {
+            let mut received = 0;
+            while received < TOTAL {
+                if let Some(rgr) = cons.read() {
+                    assert!(rgr.len() <= 31);
+                    received += rgr.len();
+                    rgr.release();
+                }
+            }
+        });
+
+        // Every frame within the limit is eventually granted, wherever
+        // the previous frames ended
+        let mut sent = 0;
+        let mut sz = 1;
+        while sent < TOTAL {
+            let sz_now = std::cmp::min(sz, TOTAL - sent);
+            loop {
+                match prod.grant(sz_now) {
+                    Ok(wgr) => {
+                        wgr.commit(sz_now);
+                        break;
+                    }
+                    Err(Error::InsufficientSize) => {}
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+            sent += sz_now;
+            sz = sz % 31 + 1;
+        }
+
+        consumer.join().unwrap();
+    }
+
+    fn crc_corruption<H: FrameHeader>() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed_with::<H>().unwrap();
+
+        for round in 0..100u8 {
+            for i in 0..3u8 {
+                let mut wgr = prod.grant(8).unwrap();
+                wgr[..4].copy_from_slice(&[round, i, round, i]);
+                wgr.commit(4);
+            }
+
+            // Corrupt the second frame in the queue
+            let rgr = cons.read_unchecked().unwrap();
+            rgr.release();
+            let mut rgr = cons.read_unchecked().unwrap();
+            rgr[usize::from(round % 4)] ^= 1 << (round % 8);
+            drop(rgr);
+
+            // The bad frame is detected and discarded, and the stream recovers
+            assert_eq!(cons.try_read().unwrap_err(), Error::ChecksumMismatch);
+            let rgr = cons.try_read().unwrap();
+            assert_eq!(&*rgr, &[round, 2, round, 2]);
+            rgr.release();
+            assert_eq!(cons.try_read().unwrap_err(), Error::Empty);
+        }
+    }
+
+    #[test]
+    fn frame_crc16() {
+        crc_corruption::<Crc16>();
+        crc_corruption::<Crc16<u8>>();
+    }
+
+    #[test]
+    fn frame_crc32() {
+        crc_corruption::<Crc32>();
+        crc_corruption::<Crc32<Leb128Header>>();
+    }
+
+    #[test]
+    fn frame_crc_keep_bad() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed_with::<Crc32<u8>>().unwrap();
+        cons.set_discard_bad_frames(false);
+
+        // The trailer is reserved in addition to the frame and header
+        assert_eq!(prod.grant(60).unwrap_err(), Error::FrameTooLarge);
+        for i in 0..3u8 {
+            let mut wgr = prod.grant(4).unwrap();
+            wgr.copy_from_slice(&[i; 4]);
+            wgr.commit(4);
+        }
+
+        let mut rgr = cons.read_unchecked().unwrap();
+        rgr[0] = 0xFF;
+        drop(rgr);
+
+        // The bad frame stays in the queue, until it is read without checking
+        assert_eq!(cons.try_read().unwrap_err(), Error::ChecksumMismatch);
+        assert!(cons.read().is_none());
+        assert_eq!(cons.frames().count(), 0);
+        let rgr = cons.read_unchecked().unwrap();
+        assert_eq!(&*rgr, &[0xFF, 0, 0, 0]);
+        rgr.release();
+
+        // The following frames are intact
+        let ids: Vec<u8> = cons.frames().map(|frame| frame[0]).collect();
+        assert_eq!(ids, [1, 2]);
+    }
 }
@@ -1,180 +1,2784 @@
 //! NOTE: this crate is really just a shim for testing
 //! the other no-std crate.
 
+// With `--cfg loom`, the atomics of `bbqueue` may only be used by the `loom`
+// tests, which run each scenario inside of `loom::model()`
+#[cfg(loom)]
+mod loom;
+
+#[cfg(feature = "critical-section")]
+mod counting_cs;
+
+#[cfg(not(loom))]
+mod cobs;
+#[cfg(not(loom))]
 mod framed;
+#[cfg(not(loom))]
+mod model;
+#[cfg(not(loom))]
 mod multi_thread;
+#[cfg(not(loom))]
+mod postcard;
+#[cfg(not(loom))]
 mod ring_around_the_senders;
+#[cfg(not(loom))]
 mod single_thread;
+#[cfg(not(loom))]
+mod slip;
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
-    use bbqueue::{BBBuffer, Error as BBQError};
+    use bbqueue::{BBBuffer, Error as BBQError, ReadAll};
+
+    #[test]
+    fn deref_deref_mut() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut wgr = prod.grant_exact(1).unwrap();
+
+        // deref_mut
+        wgr[0] = 123;
+
+        assert_eq!(wgr.len(), 1);
+
+        wgr.commit(1);
+
+        // deref
+        let rgr = cons.read().unwrap();
+
+        assert_eq!(rgr[0], 123);
+
+        rgr.release(1);
+    }
+
+    #[test]
+    fn static_allocator() {
+        // Check we can make multiple static items...
+        static BBQ1: BBBuffer<6> = BBBuffer::new();
+        static BBQ2: BBBuffer<6> = BBBuffer::new();
+        let (mut prod1, mut cons1) = BBQ1.try_split().unwrap();
+        let (mut _prod2, mut cons2) = BBQ2.try_split().unwrap();
+
+        // ... and they aren't the same
+        let mut wgr1 = prod1.grant_exact(3).unwrap();
+        wgr1.copy_from_slice(&[1, 2, 3]);
+        wgr1.commit(3);
+
+        // no data here...
+        assert!(cons2.read().is_err());
+
+        // ...data is here!
+        let rgr1 = cons1.read().unwrap();
+        assert_eq!(&*rgr1, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn release() {
+        // Check we can make multiple static items...
+        static BBQ1: BBBuffer<6> = BBBuffer::new();
+        static BBQ2: BBBuffer<6> = BBBuffer::new();
+        let (prod1, cons1) = BBQ1.try_split().unwrap();
+        let (prod2, cons2) = BBQ2.try_split().unwrap();
+
+        // We cannot release with the wrong prod/cons
+        let (prod2, cons2) = BBQ1.try_release(prod2, cons2).unwrap_err();
+        let (prod1, cons1) = BBQ2.try_release(prod1, cons1).unwrap_err();
+
+        // We cannot release with the wrong consumer...
+        let (prod1, cons2) = BBQ1.try_release(prod1, cons2).unwrap_err();
+
+        // ...or the wrong producer
+        let (prod2, cons1) = BBQ1.try_release(prod2, cons1).unwrap_err();
+
+        // We cannot release with a write grant in progress
+        let mut prod1 = prod1;
+        let wgr1 = prod1.grant_exact(3).unwrap();
+        let (prod1, mut cons1) = BBQ1.try_release(prod1, cons1).unwrap_err();
+
+        // We cannot release with a read grant in progress
+        wgr1.commit(3);
+        let rgr1 = cons1.read().unwrap();
+        let (prod1, cons1) = BBQ1.try_release(prod1, cons1).unwrap_err();
+
+        // But we can when everything is resolved
+        rgr1.release(3);
+        assert!(BBQ1.try_release(prod1, cons1).is_ok());
+        assert!(BBQ2.try_release(prod2, cons2).is_ok());
+
+        // And we can re-split on-demand
+        let _ = BBQ1.try_split().unwrap();
+        let _ = BBQ2.try_split().unwrap();
+    }
+
+    #[test]
+    fn release_split_parts() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(4).unwrap().commit(4);
+
+        // Either part of a split grant blocks a release, so it can not be
+        // released into the next split
+        let (head, tail) = cons.read().unwrap().split_at(2);
+        head.release();
+        let (prod, cons) = bb.try_release(prod, cons).unwrap_err();
+
+        tail.release();
+        assert!(bb.try_release(prod, cons).is_ok());
+
+        let (_prod, mut cons) = bb.try_split().unwrap();
+        assert!(cons.is_empty());
+        assert_eq!(cons.read().unwrap_err(), BBQError::Empty);
+    }
+
+    #[test]
+    fn direct_usage_sanity() {
+        // Initialize
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+
+        // Initial grant, shouldn't roll over
+        let mut x = prod.grant_exact(4).unwrap();
+
+        // Still no data available yet
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+
+        // Add full data from grant
+        x.copy_from_slice(&[1, 2, 3, 4]);
+
+        // Still no data available yet
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+
+        // Commit data
+        x.commit(4);
+
+        ::std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+
+        let a = cons.read().unwrap();
+        assert_eq!(&*a, &[1, 2, 3, 4]);
+
+        // Release the first two bytes
+        a.release(2);
+
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[3, 4]);
+        r.release(0);
+
+        // Grant two more
+        let mut x = prod.grant_exact(2).unwrap();
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[3, 4]);
+        r.release(0);
+
+        // Add more data
+        x.copy_from_slice(&[11, 12]);
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[3, 4]);
+        r.release(0);
+
+        // Commit
+        x.commit(2);
+
+        let a = cons.read().unwrap();
+        assert_eq!(&*a, &[3, 4, 11, 12]);
+
+        a.release(2);
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[11, 12]);
+        r.release(0);
+
+        let mut x = prod.grant_exact(3).unwrap();
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[11, 12]);
+        r.release(0);
+
+        x.copy_from_slice(&[21, 22, 23]);
+
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[11, 12]);
+        r.release(0);
+        x.commit(3);
+
+        let a = cons.read().unwrap();
+
+        // NOTE: The data we just added isn't available yet,
+        // since it has wrapped around
+        assert_eq!(&*a, &[11, 12]);
+
+        a.release(2);
+
+        // And now we can see it
+        let r = cons.read().unwrap();
+        assert_eq!(&*r, &[21, 22, 23]);
+        r.release(0);
+
+        // Ask for something way too big
+        assert!(prod.grant_exact(10).is_err());
+    }
+
+    #[test]
+    fn release_checked() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut wgr = prod.grant_exact(4).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3, 4]);
+        wgr.commit(4);
+
+        // Over-release hands back the grant, and releases nothing
+        let rgr = cons.read().unwrap();
+        let (err, rgr) = rgr.release_checked(5).unwrap_err();
+        assert_eq!(err, BBQError::InsufficientSize);
+        assert_eq!(&*rgr, &[1, 2, 3, 4]);
+
+        // Zero release
+        rgr.release_checked(0).unwrap();
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[1, 2, 3, 4]);
+
+        // Exact release
+        rgr.release_checked(4).unwrap();
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+
+        // Wrapped data is released per region
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[5, 6]);
+        wgr.commit(2);
+        let mut wgr = prod.grant_exact(3).unwrap();
+        wgr.copy_from_slice(&[7, 8, 9]);
+        wgr.commit(3);
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[5, 6]);
+        let (_, rgr) = rgr.release_checked(3).unwrap_err();
+        rgr.release_checked(2).unwrap();
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[7, 8, 9]);
+        rgr.release_checked(3).unwrap();
+    }
+
+    #[test]
+    fn skip() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        // Nothing to skip yet
+        assert_eq!(cons.skip(4), Ok(0));
+
+        let mut wgr = prod.grant_exact(4).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3, 4]);
+        wgr.commit(4);
+        assert_eq!(cons.skip(3), Ok(3));
+
+        // | 7 | 8 | x | 4 | 5 | 6 |, with `last` at 6
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[5, 6]);
+        wgr.commit(2);
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[7, 8]);
+        wgr.commit(2);
+
+        // Skip across the wrap boundary
+        assert_eq!(cons.skip(4), Ok(4));
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[8]);
+
+        // Not allowed with a read grant in progress
+        assert_eq!(cons.skip(1), Err(BBQError::GrantInProgress));
+        rgr.release(0);
+
+        // Skipping more than available stops at the end of the data
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[9, 10]);
+        wgr.commit(2);
+        assert_eq!(cons.skip(10), Ok(3));
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+    }
+
+    #[test]
+    fn read_frame_header_body() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        prod.write_frame(&[0xAA, 3], &[1, 2, 3]).unwrap();
+
+        // Not enough data for the header
+        assert!(cons.read_frame_header(6).is_err());
+
+        // Inspecting the header twice gives the same header
+        let hdr = cons.read_frame_header(2).unwrap();
+        assert_eq!(&*hdr, &[0xAA, 3]);
+        drop(hdr);
+        let hdr = cons.read_frame_header(2).unwrap();
+        assert_eq!(&*hdr, &[0xAA, 3]);
+        let body_len = hdr[1] as usize;
+
+        // Not allowed while the header grant is still active
+        assert_eq!(cons.read_body(body_len), Err(BBQError::GrantInProgress));
+        drop(hdr);
+
+        // Too large a body leaves the header in place
+        assert_eq!(cons.read_body(4), Err(BBQError::InsufficientSize));
+
+        let body = cons.read_body(body_len).unwrap();
+        assert_eq!(&*body, &[1, 2, 3]);
+        body.release(body_len);
+        assert_eq!(cons.read(), Err(BBQError::Empty));
+
+        // | 1 | 2 | x | x | x | x | x | 0xBB | with the header at the end
+        prod.grant_exact(2).unwrap().commit(2);
+        prod.write_frame(&[0xBB], &[]).unwrap();
+        cons.skip(2).unwrap();
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[1, 2]);
+        wgr.commit(2);
+
+        let hdr = cons.read_frame_header(1).unwrap();
+        assert_eq!(&*hdr, &[0xBB]);
+        drop(hdr);
+
+        // The body wraps around to the start of the buffer
+        let body = cons.read_body(2).unwrap();
+        assert_eq!(&*body, &[1, 2]);
+        body.release(2);
+
+        // A released header is not skipped again
+        prod.write_frame(&[7], &[8]).unwrap();
+        let hdr = cons.read_frame_header(1).unwrap();
+        hdr.release(1);
+        let body = cons.read_body(1).unwrap();
+        assert_eq!(&*body, &[8]);
+    }
+
+    #[test]
+    fn bytes_available() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert!(cons.is_empty());
+        assert_eq!(cons.bytes_available(), 0);
+
+        // Granted, but not committed bytes are not available
+        let wgr = prod.grant_exact(4).unwrap();
+        assert_eq!(cons.bytes_available(), 0);
+        wgr.commit(4);
+        assert_eq!(cons.bytes_available(), 4);
+        assert!(!cons.is_empty());
+
+        cons.read().unwrap().release(3);
+        assert_eq!(cons.bytes_available(), 1);
+
+        // | x | x | x | 3 | 4 | 5 |
+        prod.grant_exact(2).unwrap().commit(2);
+        assert_eq!(cons.bytes_available(), 3);
+
+        // | 6 | 7 | x | 3 | 4 | 5 |, wrapped
+        prod.grant_exact(2).unwrap().commit(2);
+        assert_eq!(cons.bytes_available(), 5);
+        let rgr = cons.read().unwrap();
+        assert_eq!(rgr.len(), 3);
+        rgr.release(3);
+        assert_eq!(cons.bytes_available(), 2);
+
+        // Unwrapped again
+        cons.read().unwrap().release(1);
+        assert_eq!(cons.bytes_available(), 1);
+        cons.read().unwrap().release(1);
+        assert_eq!(cons.bytes_available(), 0);
+        assert!(cons.is_empty());
+    }
+
+    #[test]
+    fn cursor() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(prod.cursor(), cons.cursor());
+
+        let mut last = prod.cursor();
+        for i in 0..100 {
+            let sz = (i % 3) + 1;
+            prod.grant_exact(sz).unwrap().commit(sz);
+            assert!(prod.cursor() > last);
+            assert_eq!(prod.cursor() - last, sz);
+            assert_eq!(prod.cursor() - cons.cursor(), sz);
+            last = prod.cursor();
+
+            // Release across both regions when wrapped
+            let rgr = cons.split_read().unwrap();
+            assert_eq!(rgr.combined_len(), sz);
+            rgr.release(sz);
+            assert_eq!(prod.cursor(), cons.cursor());
+        }
+
+        assert_eq!(prod.cursor().position, 199);
+    }
+
+    #[test]
+    fn read_exact() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.read_exact(1).unwrap_err(), (BBQError::Empty, 0));
+
+        let mut wgr = prod.grant_exact(4).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3, 4]);
+        wgr.commit(4);
+        assert_eq!(
+            cons.read_exact(5).unwrap_err(),
+            (BBQError::InsufficientSize, 4)
+        );
+        cons.read_exact(2).unwrap().release(2);
+
+        // | 7 | x | 3 | 4 | 5 | 6 |, with `last` at 6
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[5, 6]);
+        wgr.commit(2);
+        let mut wgr = prod.grant_exact(1).unwrap();
+        wgr.copy_from_slice(&[7]);
+        wgr.commit(1);
+
+        // One byte past `last`: enough data in total, but not contiguous
+        assert_eq!(
+            cons.read_exact(5).unwrap_err(),
+            (BBQError::NotContiguous, 5)
+        );
+        assert_eq!(
+            cons.read_exact(6).unwrap_err(),
+            (BBQError::InsufficientSize, 5)
+        );
+
+        // A grant ending exactly at `last`
+        let rgr = cons.read_exact(4).unwrap();
+        assert_eq!(&*rgr, &[3, 4, 5, 6]);
+        rgr.release(4);
+
+        // The next read starts at the beginning of the buffer
+        let rgr = cons.read_exact(1).unwrap();
+        assert_eq!(&*rgr, &[7]);
+        rgr.release(1);
+        assert_eq!(cons.read_exact(1).unwrap_err(), (BBQError::Empty, 0));
+
+        // The same at a `last` before the end of the buffer:
+        // | 8 | 9 | x | 4 | 5 | x |, with `last` at 5
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(3).unwrap().commit(3);
+        cons.read().unwrap().release(3);
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[4, 5]);
+        wgr.commit(2);
+        let mut wgr = prod.grant_exact(2).unwrap();
+        wgr.copy_from_slice(&[8, 9]);
+        wgr.commit(2);
+        cons.read_exact(1).unwrap().release(1);
+
+        // `last - read` is 1
+        assert_eq!(
+            cons.read_exact(2).unwrap_err(),
+            (BBQError::NotContiguous, 3)
+        );
+        let rgr = cons.read_exact(1).unwrap();
+        assert_eq!(&*rgr, &[5]);
+        rgr.release(1);
+        assert_eq!(&*cons.read_exact(2).unwrap(), &[8, 9]);
+    }
+
+    #[test]
+    fn spin_producer() {
+        use bbqueue::spin::SpinProducer;
+
+        static BB: BBBuffer<16> = BBBuffer::new();
+        let (prod, mut cons) = BB.try_split().unwrap();
+        let mut prod = SpinProducer::new(prod);
+
+        let tx = std::thread::spawn(move || {
+            for i in 0..100u32 {
+                let mut wgr = prod.grant_spin(4).unwrap();
+                wgr.copy_from_slice(&i.to_le_bytes());
+                wgr.commit(4);
+            }
+
+            // A grant in progress is never waited on
+            let _wgr = prod.grant_max_spin(1).unwrap();
+            assert_eq!(prod.grant_spin(1).unwrap_err(), BBQError::GrantInProgress);
+        });
+
+        for i in 0..100u32 {
+            let rgr = loop {
+                if let Ok(rgr) = cons.read_exact(4) {
+                    break rgr;
+                }
+            };
+            assert_eq!(&*rgr, &i.to_le_bytes());
+            rgr.release(4);
+        }
+
+        tx.join().unwrap();
+    }
+
+    #[test]
+    fn spin_producer_impossible_grants() {
+        use bbqueue::spin::SpinProducer;
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+
+        // Without a spin limit, grants that can never succeed must still return
+        let mut prod = SpinProducer::new(prod);
+        assert_eq!(prod.grant_spin(9).unwrap_err(), BBQError::InsufficientSize);
+        assert_eq!(prod.grant_spin(0).unwrap_err(), BBQError::InsufficientSize);
+        assert_eq!(
+            prod.grant_max_spin(0).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 9);
+
+        prod.grant_spin(8).unwrap().commit(8);
+    }
+
+    #[test]
+    fn fill_buf_consume() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut tx = 0u8;
+        let mut rx = 0u8;
+        let mut steps = 0usize;
+
+        while rx < 250 {
+            // Write whatever fits, a few bytes at a time
+            if let Ok(mut wgr) = prod.grant_max_remaining(5) {
+                for by in wgr.iter_mut() {
+                    *by = tx;
+                    tx = tx.wrapping_add(1);
+                }
+                let len = wgr.len();
+                wgr.commit(len);
+            }
+
+            // Idempotent
+            let first = cons.fill_buf().unwrap().to_vec();
+            let buf = cons.fill_buf().unwrap();
+            assert_eq!(&first[..], buf);
+
+            // Consume 1-7 bytes at a time
+            let want = (steps % 7) + 1;
+            let amt = want.min(buf.len());
+            for by in &buf[..amt] {
+                assert_eq!(*by, rx);
+                rx += 1;
+            }
+            cons.consume(amt).unwrap();
+            steps += 1;
+        }
+
+        // Mutually exclusive with read grants
+        let rgr = cons.read().unwrap();
+        assert_eq!(cons.fill_buf(), Err(BBQError::GrantInProgress));
+        assert_eq!(cons.consume(1), Err(BBQError::GrantInProgress));
+        rgr.release(0);
+
+        // Bytes committed after `fill_buf()` are not consumed
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(3).unwrap().commit(3);
+        assert_eq!(cons.fill_buf().unwrap().len(), 3);
+        prod.grant_exact(2).unwrap().commit(2);
+        cons.consume(7).unwrap();
+        assert_eq!(cons.fill_buf().unwrap().len(), 2);
+
+        // Nor is anything after a read grant was released in between
+        cons.read().unwrap().release(1);
+        cons.consume(2).unwrap();
+        assert_eq!(cons.fill_buf().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn fill_buf_consume_threaded() {
+        const ITERS: usize = 100_000;
+        static BB: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        let tx = std::thread::spawn(move || {
+            let mut ctr = 0usize;
+            while ctr < ITERS {
+                if let Ok(mut wgr) = prod.grant_max_remaining(5) {
+                    let len = wgr.len().min(ITERS - ctr);
+                    for by in wgr[..len].iter_mut() {
+                        *by = ctr as u8;
+                        ctr += 1;
+                    }
+                    wgr.commit(len);
+                } else {
+                    std::thread::yield_now();
+                }
+            }
+        });
+
+        let mut ctr = 0usize;
+        let mut steps = 0usize;
+        while ctr < ITERS {
+            let buf = cons.fill_buf().unwrap();
+            if buf.is_empty() {
+                std::thread::yield_now();
+                continue;
+            }
+
+            // Consume 1-7 bytes at a time, asking for more than was seen
+            // whenever the slice is shorter
+            let want = (steps % 7) + 1;
+            let amt = want.min(buf.len());
+            for by in &buf[..amt] {
+                assert_eq!(*by, ctr as u8);
+                ctr += 1;
+            }
+            cons.consume(want).unwrap();
+            steps += 1;
+        }
+
+        tx.join().unwrap();
+        assert_eq!(cons.fill_buf(), Err(BBQError::Disconnected));
+    }
+
+    #[test]
+    fn watermarks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static HIGH: AtomicUsize = AtomicUsize::new(0);
+        static LOW: AtomicUsize = AtomicUsize::new(0);
+
+        fn on_high() {
+            HIGH.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_low() {
+            LOW.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        bb.register_high_watermark(6, on_high);
+        bb.register_low_watermark(2, on_low);
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        prod.grant_exact(5).unwrap().commit(5);
+        assert_eq!(HIGH.load(Ordering::Relaxed), 0);
+
+        // Crossing the high watermark
+        prod.grant_exact(2).unwrap().commit(2);
+        assert_eq!(HIGH.load(Ordering::Relaxed), 1);
+
+        // Staying above it does not call the callback again
+        prod.grant_exact(1).unwrap().commit(1);
+        assert_eq!(HIGH.load(Ordering::Relaxed), 1);
+
+        cons.read().unwrap().release(5);
+        assert_eq!(LOW.load(Ordering::Relaxed), 0);
+
+        // Crossing the low watermark
+        cons.read().unwrap().release(1);
+        assert_eq!(LOW.load(Ordering::Relaxed), 1);
+        cons.read().unwrap().release(2);
+        assert_eq!(LOW.load(Ordering::Relaxed), 1);
+
+        // And back up again, across the wrap
+        prod.grant_exact(4).unwrap().commit(4);
+        prod.grant_exact(2).unwrap().commit(2);
+        assert_eq!(HIGH.load(Ordering::Relaxed), 2);
+
+        // The callbacks stay registered for the next split
+        cons.read().unwrap().release(8);
+        assert!(bb.try_release(prod, cons).is_ok());
+        let (mut prod, _cons) = bb.try_split().unwrap();
+        prod.grant_exact(6).unwrap().commit(6);
+        assert_eq!(HIGH.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn bytes_lost() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, cons) = bb.try_split().unwrap();
+        assert_eq!(cons.bytes_lost(), 0);
+
+        // Grants that fail for lack of space count the requested size
+        prod.grant_exact(6).unwrap().commit(6);
+        assert_eq!(prod.grant_exact(4).unwrap_err(), BBQError::InsufficientSize);
+        assert_eq!(cons.bytes_lost(), 4);
+        assert_eq!(
+            prod.grant_max_with_minimum(5, 3).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 9);
+
+        // Other failures are not counted
+        let grant = prod.grant_exact(1).unwrap();
+        assert_eq!(prod.grant_exact(4).unwrap_err(), BBQError::GrantInProgress);
+        drop(grant);
+        assert_eq!(cons.bytes_lost(), 9);
+
+        // Nor are sizes that could never be granted
+        assert_eq!(
+            prod.grant_max_with_minimum(2, 3).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 9);
+
+        // Nor polling callers, which ask to be called again
+        use std::io::Write;
+        prod.grant_exact(2).unwrap().commit(2);
+        let err = prod.write(&[1, 2, 3]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let mut queue = bbqueue::std_compat::VecDequeAdapter::from_parts(prod, cons);
+        assert_eq!(queue.push_back(1).unwrap_err(), BBQError::InsufficientSize);
+        assert_eq!(
+            queue.push_back_slice(&[1, 2, 3]).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        let (mut prod, mut cons) = queue.into_parts();
+        assert_eq!(cons.bytes_lost(), 9);
+
+        prod.record_lost(3);
+        assert_eq!(cons.bytes_lost(), 12);
+
+        // Retried grants only count once they give up
+        let mut spin = bbqueue::spin::SpinProducer::with_max_spins(prod, 10);
+        assert_eq!(spin.grant_spin(4).unwrap_err(), BBQError::InsufficientSize);
+        assert_eq!(cons.bytes_lost(), 16);
+        let mut prod = spin.into_inner();
+        let timeout = std::time::Duration::from_millis(5);
+        assert_eq!(
+            prod.grant_timeout(4, timeout).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 20);
+
+        // Reading does not reset the count
+        cons.read().unwrap().release(8);
+        assert_eq!(cons.bytes_lost(), 20);
+        prod.record_lost(usize::MAX);
+        assert_eq!(cons.bytes_lost(), 19);
+    }
+
+    #[test]
+    fn drain_iter() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.iter().next(), None);
+
+        let mut pushed = Vec::new();
+        let mut popped = Vec::new();
+        let mut ctr = 0u8;
+
+        for i in 0..200 {
+            let sz = (i % 7) + 1;
+            if let Ok(mut wgr) = prod.grant_exact(sz) {
+                for by in wgr.iter_mut() {
+                    *by = ctr;
+                    pushed.push(ctr);
+                    ctr = ctr.wrapping_add(1);
+                }
+                wgr.commit(sz);
+            }
+
+            // Alternate between partial and complete draining
+            if i % 3 == 0 {
+                popped.extend(cons.iter().take(5));
+            } else {
+                popped.extend(cons.iter());
+                assert!(cons.is_empty());
+            }
+        }
+        popped.extend(cons.iter());
+
+        assert_eq!(pushed, popped);
+
+        // Dropping mid-region leaves a consistent state
+        let mut wgr = prod.grant_exact(4).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3, 4]);
+        wgr.commit(4);
+        let mut iter = cons.iter();
+        assert_eq!(iter.next(), Some(1));
+        drop(iter);
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[2, 3, 4]);
+    }
+
+    #[test]
+    fn split_at_parts() {
+        static BB: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        // Head first
+        prod.write_frame(&[1, 2], &[3, 4]).unwrap();
+        let (head, tail) = cons.read().unwrap().split_at(2);
+        assert_eq!(&*head, &[1, 2]);
+        assert_eq!(&*tail, &[3, 4]);
+        head.release();
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        tail.release();
+        assert_eq!(cons.cursor().position, 4);
+
+        // Tail first
+        prod.write_frame(&[5], &[6, 7]).unwrap();
+        let (head, tail) = cons.read().unwrap().split_at(1);
+        tail.release();
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        head.release();
+        assert_eq!(cons.cursor().position, 7);
+        assert!(cons.is_empty());
+
+        // Dropping the tail only releases the head
+        prod.write_frame(&[8], &[9]).unwrap();
+        let (head, tail) = cons.read().unwrap().split_at(1);
+        drop(tail);
+        head.release();
+        assert_eq!(cons.cursor().position, 8);
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[9]);
+        rgr.release(1);
+
+        // Dropping the head releases nothing
+        prod.write_frame(&[10], &[11]).unwrap();
+        let (head, tail) = cons.read().unwrap().split_at(1);
+        tail.release();
+        drop(head);
+        assert_eq!(cons.cursor().position, 9);
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[10, 11]);
+
+        // Parts can be sent to different threads
+        let (head, tail) = rgr.split_at(10);
+        assert_eq!(tail.len(), 0);
+        std::thread::spawn(move || head.release()).join().unwrap();
+        tail.release();
+        assert_eq!(cons.cursor().position, 11);
+    }
+
+    #[test]
+    fn single_read_grant() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(4).unwrap().commit(4);
+
+        // Every kind of read grant excludes every other kind
+        let rgr = cons.read().unwrap();
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        assert_eq!(cons.split_read(), Err(BBQError::GrantInProgress));
+        assert_eq!(
+            cons.read_exact(1).unwrap_err(),
+            (BBQError::GrantInProgress, 0)
+        );
+        rgr.release(1);
+
+        let rgr = cons.split_read().unwrap();
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        assert_eq!(cons.split_read(), Err(BBQError::GrantInProgress));
+        rgr.release(1);
+
+        let (head, tail) = cons.read().unwrap().split_at(1);
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        head.release();
+        assert_eq!(cons.read(), Err(BBQError::GrantInProgress));
+        tail.release();
+
+        // Producer grants are tracked independently
+        let rgr = cons.read();
+        assert_eq!(rgr, Err(BBQError::Empty));
+        let wgr = prod.grant_exact(1).unwrap();
+        assert_eq!(prod.grant_exact(1), Err(BBQError::GrantInProgress));
+        wgr.commit(1);
+        let rgr = cons.read().unwrap();
+        assert!(prod.grant_exact(1).is_ok());
+        rgr.release(1);
+    }
+
+    #[test]
+    fn flush() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.flush(), Ok(0));
+
+        prod.grant_exact(4).unwrap().commit(4);
+        cons.read().unwrap().release(3);
+
+        // | 6 | 7 | x | 3 | 4 | 5 |, wrapped
+        prod.grant_exact(2).unwrap().commit(2);
+        prod.grant_exact(2).unwrap().commit(2);
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(cons.flush(), Err(BBQError::GrantInProgress));
+        rgr.release(0);
+
+        assert_eq!(cons.flush(), Ok(5));
+        assert!(cons.is_empty());
+        assert_eq!(prod.cursor(), cons.cursor());
+
+        // The queue is usable afterwards
+        let mut wgr = prod.grant_exact(3).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3]);
+        wgr.commit(3);
+        assert_eq!(&*cons.read().unwrap(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn flush_concurrent() {
+        static BB: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        const ITERS: usize = 10_000;
+
+        let tx = std::thread::spawn(move || {
+            let mut sent = 0;
+            for i in 0..ITERS {
+                let sz = (i % 13) + 1;
+                if let Ok(mut wgr) = prod.grant_exact(sz) {
+                    wgr.fill(sz as u8);
+                    wgr.commit(sz);
+                    sent += sz;
+                }
+            }
+            sent
+        });
+
+        let mut received = 0;
+        let mut flushed = 0;
+        while !tx.is_finished() || !cons.is_empty() {
+            // Every frame is either flushed, or read whole
+            if let Ok(rgr) = cons.read() {
+                let sz = rgr[0] as usize;
+                assert!(rgr[..sz].iter().all(|b| *b as usize == sz));
+                received += sz;
+                rgr.release(sz);
+            }
+            flushed += cons.flush().unwrap();
+        }
+
+        let sent = tx.join().unwrap();
+        assert_eq!(received + flushed, sent);
+        assert_eq!(cons.cursor().position as usize, sent);
+    }
+
+    #[test]
+    fn with_read() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.with_read(|_| 0), Err(BBQError::Empty));
+
+        prod.write_frame(&[1, 2], &[3, 4]).unwrap();
+        assert_eq!(cons.with_read(|buf| buf.len() + 10), Ok(4));
+        assert!(cons.is_empty());
+
+        // A panic does not leave the read grant in progress
+        prod.write_frame(&[5], &[]).unwrap();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cons.with_read(|_| panic!("oops")).ok();
+        }));
+        assert!(res.is_err());
+        assert_eq!(cons.with_read(|buf| buf.len()), Ok(1));
+    }
+
+    #[test]
+    fn read_all_into() {
+        let bb: BBBuffer<6> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        let mut scratch = [0u8; 6];
+        assert_eq!(cons.read_all_into(&mut scratch), Err(BBQError::Empty));
+
+        prod.write_frame(&[1, 2, 3], &[4]).unwrap();
+        cons.skip(2).unwrap();
+
+        // | 5 | 6 | x | 3 | 4 |, wrapped
+        prod.write_frame(&[5, 6], &[]).unwrap();
+        assert_eq!(cons.bytes_available(), 4);
+
+        // A scratch buffer smaller than the data
+        assert_eq!(cons.read_all_into(&mut scratch[..3]), Ok(3));
+        assert_eq!(&scratch[..3], &[3, 4, 5]);
+        assert_eq!(cons.read_all_into(&mut scratch), Ok(1));
+        assert_eq!(&scratch[..1], &[6]);
+
+        // | x | x | 7 | 8 | 9 | 10 |, with the data ending exactly at `last`
+        prod.write_frame(&[7, 8, 9, 10], &[]).unwrap();
+        assert_eq!(cons.read_all_into(&mut scratch), Ok(4));
+        assert_eq!(&scratch[..4], &[7, 8, 9, 10]);
+        assert!(cons.is_empty());
+
+        // The next data starts at the beginning of the buffer
+        prod.write_frame(&[11], &[]).unwrap();
+        match cons.read_all(&mut scratch).unwrap() {
+            ReadAll::Grant(grant) => {
+                assert_eq!(&*grant, &[11]);
+                grant.release(1);
+            }
+            ReadAll::Copied(_) => panic!("data should be contiguous"),
+        };
+    }
+
+    #[test]
+    fn read_max() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut grant = prod.grant_exact(48).unwrap();
+        grant.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        grant.commit(48);
+
+        for chunk in 0..2 {
+            let grant = cons.read_max(16).unwrap();
+            assert_eq!(grant.len(), 16);
+            assert_eq!(grant[0], chunk * 16);
+            grant.release(16);
+        }
+
+        // Wrap around, the data now ends exactly at `last`
+        let mut grant = prod.grant_exact(24).unwrap();
+        grant
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, b)| *b = 48 + i as u8);
+        grant.commit(24);
+
+        let mut expected = 32u8;
+        while let Ok(grant) = cons.read_max(16) {
+            assert!(grant.len() <= 16);
+            for byte in grant.iter() {
+                assert_eq!(*byte, expected);
+                expected += 1;
+            }
+            let len = grant.len();
+            grant.release(len);
+        }
+        assert_eq!(expected, 72);
+        assert!(cons.is_empty());
+    }
+
+    #[test]
+    fn producer_disconnect() {
+        static BB: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        let prod_thr = std::thread::spawn(move || {
+            for i in 0..100u8 {
+                loop {
+                    match prod.grant_exact(1) {
+                        Ok(mut gr) => {
+                            gr[0] = i;
+                            gr.commit(1);
+                            break;
+                        }
+                        Err(BBQError::InsufficientSize) => {}
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            }
+            // `prod` is dropped here, mid-stream from the consumer's view
+        });
+
+        let mut expected = 0u8;
+        loop {
+            match cons.read() {
+                Ok(gr) => {
+                    for byte in gr.iter() {
+                        assert_eq!(*byte, expected);
+                        expected += 1;
+                    }
+                    let len = gr.len();
+                    gr.release(len);
+                }
+                Err(BBQError::Empty) => {}
+                Err(BBQError::Disconnected) => break,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+
+        // All data was received before the disconnect was reported
+        assert_eq!(expected, 100);
+        prod_thr.join().unwrap();
+    }
+
+    #[test]
+    fn consumer_disconnect() {
+        static BB: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        let cons_thr = std::thread::spawn(move || {
+            let mut received = 0;
+            while received < 10 {
+                if let Ok(gr) = cons.read() {
+                    let len = gr.len();
+                    gr.release(len);
+                    received += len;
+                }
+            }
+            // `cons` is dropped here, while the producer keeps going
+        });
+
+        loop {
+            match prod.grant_exact(1) {
+                Ok(gr) => gr.commit(1),
+                Err(BBQError::InsufficientSize) => {}
+                Err(BBQError::Disconnected) => break,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+
+        cons_thr.join().unwrap();
+    }
+
+    #[test]
+    fn write_cursor() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut grant = prod.grant_exact(8).unwrap();
+        let mut cursor = grant.cursor();
+        assert_eq!(cursor.remaining(), 8);
+        cursor.write_bytes(&[1, 2]).unwrap();
+        cursor.write_u16_le(0x0403).unwrap();
+        cursor.write_u8(5).unwrap();
+        assert_eq!(cursor.written(), 5);
+
+        // Too large writes do not write anything
+        assert_eq!(cursor.write_u32_le(0), Err(BBQError::InsufficientSize));
+        assert_eq!(cursor.written(), 5);
+        cursor.write_bytes(&[6, 7, 8]).unwrap();
+        assert_eq!(cursor.remaining(), 0);
+        assert_eq!(cursor.write_u8(9), Err(BBQError::InsufficientSize));
+
+        let written = cursor.written();
+        grant.commit(written);
+
+        let mut grant = prod.grant_exact(8).unwrap();
+        let mut cursor = grant.cursor();
+        cursor.write_u64_le(0x100F_0E0D_0C0B_0A09).unwrap();
+        let written = cursor.written();
+        grant.commit(written);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(
+            &*grant,
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+        );
+    }
+
+    #[test]
+    fn write_to() {
+        use std::io::{self, Write};
+
+        // Accepts at most three bytes per write, and fails after `limit` bytes
+        struct ShortWriter {
+            data: Vec<u8>,
+            limit: usize,
+        }
+
+        impl Write for ShortWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.data.len() >= self.limit {
+                    return Err(io::ErrorKind::BrokenPipe.into());
+                }
+                let len = buf.len().min(3).min(self.limit - self.data.len());
+                self.data.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        let mut out = Vec::new();
+        assert_eq!(cons.write_to(&mut out).unwrap(), 0);
+
+        prod.write_frame(&[1, 2, 3, 4, 5], &[6]).unwrap();
+        cons.skip(4).unwrap();
+
+        // | 7 | 8 | 9 | x | 5 | 6 | x | x |, wrapped
+        prod.write_frame(&[7, 8, 9], &[]).unwrap();
+        assert_eq!(cons.write_to(&mut out).unwrap(), 5);
+        assert_eq!(out, &[5, 6, 7, 8, 9]);
+        assert!(cons.is_empty());
+
+        // | x | x | x | 1 | 2 | 3 | 4 | x |
+        prod.write_frame(&[1, 2, 3, 4], &[]).unwrap();
+        let mut short = ShortWriter {
+            data: Vec::new(),
+            limit: 3,
+        };
+        assert_eq!(
+            cons.write_to(&mut short).unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+        assert_eq!(short.data, &[1, 2, 3]);
+
+        // The unwritten byte remains
+        let mut short = ShortWriter {
+            data: Vec::new(),
+            limit: 8,
+        };
+        assert_eq!(cons.write_to(&mut short).unwrap(), 1);
+        assert_eq!(short.data, &[4]);
+    }
+
+    #[test]
+    fn read_cursor() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut grant = prod.grant_exact(16).unwrap();
+        let mut cursor = grant.cursor();
+        cursor.write_u8(1).unwrap();
+        cursor.write_u16_le(0x0302).unwrap();
+        cursor.write_u32_le(0x0706_0504).unwrap();
+        cursor.write_u64_le(0x0F0E_0D0C_0B0A_0908).unwrap();
+        let written = cursor.written();
+        grant.commit(written);
+
+        let grant = cons.read().unwrap();
+        let mut cursor = grant.cursor();
+        assert_eq!(cursor.remaining(), 15);
+        assert_eq!(cursor.read_u8(), Ok(1));
+        assert_eq!(cursor.read_u16_le(), Ok(0x0302));
+        assert_eq!(cursor.read_u32_le(), Ok(0x0706_0504));
+        assert_eq!(cursor.consumed(), 7);
+
+        // Too large reads do not consume anything
+        let mut buf = [0u8; 9];
+        assert_eq!(cursor.read_bytes(&mut buf), Err(BBQError::InsufficientSize));
+        assert_eq!(cursor.consumed(), 7);
+        assert_eq!(cursor.read_u64_le(), Ok(0x0F0E_0D0C_0B0A_0908));
+        assert_eq!(cursor.read_u8(), Err(BBQError::InsufficientSize));
+
+        let consumed = cursor.consumed();
+        grant.release(consumed);
+        assert!(cons.is_empty());
+    }
+
+    #[test]
+    fn read_until() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert_eq!(cons.read_until(b'\n'), Ok(None));
+
+        prod.write_frame(b"ab\ncde", &[]).unwrap();
+        let line = cons.read_until(b'\n').unwrap().unwrap();
+        assert_eq!(&*line, b"ab\n");
+        line.release(3);
+        assert_eq!(cons.read_until(b'\n'), Ok(None));
+
+        // | f | \n | x | c | d | e | \n | x |, split across the wrap.
+        // The delimiter ending exactly at `last` is contiguous.
+        prod.write_frame(b"\n", &[]).unwrap();
+        let mut grant = prod.grant_exact(2).unwrap();
+        grant.copy_from_slice(b"f\n");
+        grant.commit(2);
+
+        let line = cons.read_until(b'\n').unwrap().unwrap();
+        assert_eq!(&*line, b"cde\n");
+        line.release(4);
+
+        // Rolls over to the start of the buffer
+        let line = cons.read_until(b'\n').unwrap().unwrap();
+        assert_eq!(&*line, b"f\n");
+        line.release(2);
+        assert!(cons.is_empty());
+
+        // | l | m | \n | x | j | k | x | x |, with the line split across the wrap
+        prod.write_frame(b"xxjk", &[]).unwrap();
+        cons.skip(2).unwrap();
+        prod.write_frame(b"lm\n", &[]).unwrap();
+        assert_eq!(cons.read_until(b'\n'), Err(BBQError::NotContiguous));
+
+        // Nothing was released
+        assert_eq!(cons.bytes_available(), 5);
+        let grant = cons.split_read().unwrap();
+        assert_eq!(grant.bufs(), (&b"jk"[..], &b"lm\n"[..]));
+        grant.release(5);
+        assert!(cons.is_empty());
+    }
+
+    #[test]
+    fn observer() {
+        static BB: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+        let observer = BB.observer();
+        const TOTAL: u64 = 10_000;
+
+        let prod_thr = std::thread::spawn(move || {
+            let mut sent = 0u64;
+            while sent < TOTAL {
+                let max = (TOTAL - sent).min(7) as usize;
+                if let Ok(grant) = prod.grant_max_remaining(max) {
+                    let len = grant.len();
+                    grant.commit(len);
+                    sent += len as u64;
+                }
+            }
+        });
+
+        let cons_thr = std::thread::spawn(move || {
+            let mut received = 0u64;
+            while received < TOTAL {
+                if let Ok(grant) = cons.read() {
+                    let len = grant.len();
+                    grant.release(len);
+                    received += len as u64;
+                }
+            }
+        });
+
+        let mut last_consumer = 0;
+        while !(prod_thr.is_finished() && cons_thr.is_finished()) {
+            let available = observer.bytes_available();
+            assert!(available <= 64);
+
+            let consumer = observer.consumer_cursor().position;
+            let producer = observer.producer_cursor().position;
+            assert!(consumer >= last_consumer);
+            assert!(producer >= consumer);
+            assert!(producer <= TOTAL);
+            last_consumer = consumer;
+        }
+
+        prod_thr.join().unwrap();
+        cons_thr.join().unwrap();
+        assert_eq!(observer.bytes_available(), 0);
+        assert_eq!(observer.consumer_cursor().position, TOTAL);
+    }
+
+    #[test]
+    fn regions() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut grant = prod.grant_exact(12).unwrap();
+        grant.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        grant.commit(12);
+        cons.skip(8).unwrap();
+
+        // | 12 .. 17 | x .. | 8 .. 11 | x .. |, wrapped
+        let mut grant = prod.grant_exact(6).unwrap();
+        grant
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, b)| *b = 12 + i as u8);
+        grant.commit(6);
+
+        // Build a descriptor list, and "transmit" it
+        let descriptors: Vec<(*const u8, usize)> = cons
+            .regions()
+            .unwrap()
+            .map(|region| (region.as_ptr(), region.len()))
+            .collect();
+        assert_eq!(descriptors.len(), 2);
+
+        let mut transmitted = Vec::new();
+        for (ptr, len) in descriptors.iter() {
+            transmitted.extend_from_slice(unsafe { std::slice::from_raw_parts(*ptr, *len) });
+        }
+        assert_eq!(transmitted, (8..18).collect::<Vec<u8>>());
+
+        // Release both regions in one go
+        cons.release_regions(transmitted.len()).unwrap();
+        assert!(cons.is_empty());
+        assert_eq!(cons.regions().unwrap().count(), 0);
+
+        // The next data continues after the second region
+        prod.write_frame(&[18], &[]).unwrap();
+        let regions: Vec<&[u8]> = cons.regions().unwrap().collect();
+        assert_eq!(regions, vec![&[18][..]]);
+    }
+
+    #[test]
+    fn arc_split() {
+        use bbqueue::arc::try_split_arc;
+        use std::{convert::TryInto, sync::Arc};
+
+        let bb: Arc<BBBuffer<64>> = Arc::new(BBBuffer::new());
+        let (mut prod, mut cons) = try_split_arc(&bb).unwrap();
+        assert!(try_split_arc(&bb).is_err());
+
+        // The threads own the only other references to the buffer
+        drop(bb);
+
+        let prod_thr = std::thread::spawn(move || {
+            for i in 0..1000u32 {
+                loop {
+                    if let Ok(mut grant) = prod.grant_exact(4) {
+                        grant.copy_from_slice(&i.to_le_bytes());
+                        grant.commit(4);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let cons_thr = std::thread::spawn(move || {
+            let mut expected = 0u32;
+            while expected < 1000 {
+                if let Ok(grant) = cons.split_read() {
+                    let (buf1, buf2) = grant.bufs();
+                    let bytes: Vec<u8> = buf1.iter().chain(buf2.iter()).copied().collect();
+                    let frames = bytes.len() / 4;
+                    for chunk in bytes.chunks_exact(4) {
+                        assert_eq!(u32::from_le_bytes(chunk.try_into().unwrap()), expected);
+                        expected += 1;
+                    }
+                    grant.release(frames * 4);
+                }
+            }
+        });
+
+        prod_thr.join().unwrap();
+        cons_thr.join().unwrap();
+    }
+
+    #[test]
+    fn into_split() {
+        use bbqueue::arc::{OwnedConsumer, OwnedProducer};
+
+        fn make() -> (OwnedProducer<8>, OwnedConsumer<8>) {
+            // The buffer only lives on the stack of this function
+            let bb: BBBuffer<8> = BBBuffer::new();
+            bb.into_split().unwrap()
+        }
+
+        let (mut prod, mut cons) = make();
+        let tx = std::thread::spawn(move || {
+            let mut grant = prod.grant_exact(3).unwrap();
+            grant.copy_from_slice(&[1, 2, 3]);
+            grant.commit(3);
+        });
+        tx.join().unwrap();
+
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 2, 3]);
+        grant.release(3);
+
+        // A buffer that was split before can not be split again
+        let bb: BBBuffer<8> = BBBuffer::new();
+        drop(bb.try_split().unwrap());
+        assert!(bb.into_split().is_err());
+    }
+
+    #[test]
+    fn grant_timeout() {
+        use std::time::{Duration, Instant};
+
+        static BB: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+        prod.set_poll_interval(Duration::from_micros(100));
+
+        prod.grant_exact(6).unwrap().commit(6);
+
+        // Nothing is released, so this gives up after the timeout
+        let start = Instant::now();
+        let timeout = Duration::from_millis(20);
+        assert_eq!(
+            prod.grant_timeout(4, timeout).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert!(start.elapsed() >= timeout);
+
+        // Grants larger than the buffer never wait
+        let start = Instant::now();
+        assert_eq!(
+            prod.grant_timeout(9, Duration::from_secs(10)).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // Succeeds once the consumer makes room
+        let rx = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            cons.read().unwrap().release(6);
+            // Keep the consumer open
+            cons
+        });
+        let grant = prod.grant_timeout(4, Duration::from_secs(10)).unwrap();
+        assert_eq!(grant.len(), 4);
+        rx.join().unwrap();
+    }
+
+    #[test]
+    fn ping_pong() {
+        use bbqueue::pingpong::PingPongBuffer;
+
+        const FILLS: u32 = 10_000;
+        static PP: PingPongBuffer<16> = PingPongBuffer::new();
+        let (mut prod, mut cons) = PP.try_split().unwrap();
+
+        // Every fill holds its own number, and some are partial
+        let tx = std::thread::spawn(move || {
+            let mut fill = 0u32;
+            while fill < FILLS {
+                match prod.begin_fill() {
+                    Ok(mut grant) => {
+                        assert_eq!(grant.len(), 16);
+                        let len = 4 + (fill % 13) as usize;
+                        for byte in grant[..len].iter_mut() {
+                            *byte = fill as u8;
+                        }
+                        grant.commit(len);
+                        fill += 1;
+                    }
+                    Err(BBQError::InsufficientSize) => std::thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+            prod
+        });
+
+        let mut fill = 0u32;
+        while fill < FILLS {
+            match cons.begin_drain() {
+                Ok(grant) => {
+                    assert_eq!(grant.len(), 4 + (fill % 13) as usize);
+                    assert!(grant.iter().all(|byte| *byte == fill as u8));
+                    let len = grant.len();
+                    grant.release(len);
+                    fill += 1;
+                }
+                Err(BBQError::Empty) => std::thread::yield_now(),
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+        tx.join().unwrap();
+        assert!(cons.begin_drain().is_err());
+    }
+
+    #[test]
+    fn grants_are_send() {
+        use bbqueue::{GrantR, GrantRPart, GrantW, SplitGrantR};
+
+        fn assert_send<T: Send>() {}
+        assert_send::<GrantW<'static, 8>>();
+        assert_send::<GrantR<'static, 8>>();
+        assert_send::<SplitGrantR<'static, 8>>();
+        assert_send::<GrantRPart<'static, 8>>();
+
+        static BB: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        // Commit and release on other threads than the ones that granted,
+        // as a DMA interrupt would
+        let mut wgr = prod.grant_exact(4).unwrap();
+        wgr.copy_from_slice(&[1, 2, 3, 4]);
+        std::thread::spawn(move || wgr.commit(4)).join().unwrap();
+
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[1, 2, 3, 4]);
+        std::thread::spawn(move || rgr.release(4)).join().unwrap();
+
+        assert_eq!(cons.read().unwrap_err(), BBQError::Empty);
+        assert!(prod.grant_exact(8).is_ok());
+    }
+
+    #[test]
+    fn halves_are_send() {
+        use bbqueue::{Consumer, Observer, Producer};
+
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<BBBuffer<8>>();
+        assert_sync::<BBBuffer<8>>();
+        assert_send::<Producer<'static, 8>>();
+        assert_send::<Consumer<'static, 8>>();
+        assert_send::<Observer<'static, 8>>();
+        assert_sync::<Observer<'static, 8>>();
+
+        // Move each half to its own thread, and push a few megabytes through
+        const TOTAL: usize = 4 << 20;
+        static BB: BBBuffer<1024> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        let tx = std::thread::spawn(move || {
+            let mut sent = 0;
+            while sent < TOTAL {
+                let sz = (TOTAL - sent).min(1 + sent % 300);
+                match prod.grant_exact(sz) {
+                    Ok(mut grant) => {
+                        for byte in grant.iter_mut() {
+                            *byte = sent as u8;
+                            sent += 1;
+                        }
+                        grant.commit(sz);
+                    }
+                    Err(BBQError::InsufficientSize) => std::thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+        });
+
+        let rx = std::thread::spawn(move || {
+            let mut received = 0;
+            while received < TOTAL {
+                match cons.read() {
+                    Ok(grant) => {
+                        for byte in grant.iter() {
+                            assert_eq!(*byte, received as u8);
+                            received += 1;
+                        }
+                        let len = grant.len();
+                        grant.release(len);
+                    }
+                    Err(BBQError::Empty) => std::thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+            cons
+        });
+
+        tx.join().unwrap();
+        let cons = rx.join().unwrap();
+        assert!(cons.is_empty());
+        assert_eq!(BB.total_bytes_read(), TOTAL as u64);
+    }
+
+    #[test]
+    fn sync_producer() {
+        use bbqueue::mutex::SyncProducer;
+        use std::sync::Arc;
+
+        static BB: BBBuffer<32> = BBBuffer::new();
+        let (prod, mut cons) = BB.try_split().unwrap();
+        let prod = Arc::new(SyncProducer::new(prod));
+
+        let threads: Vec<_> = (0..4u8)
+            .map(|id| {
+                let prod = prod.clone();
+                std::thread::spawn(move || {
+                    for seq in 0..100u8 {
+                        loop {
+                            if let Ok(mut grant) = prod.grant_exact(2) {
+                                grant.copy_from_slice(&[id, seq]);
+                                grant.commit(2);
+                                break;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Each pair of bytes is from a single grant, in order per thread
+        let mut next = [0u8; 4];
+        while next.iter().any(|seq| *seq < 100) {
+            if let Ok(grant) = cons.read_exact(2) {
+                let (id, seq) = (usize::from(grant[0]), grant[1]);
+                assert_eq!(next[id], seq);
+                next[id] += 1;
+                grant.release(2);
+            }
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[test]
+    fn cs_producer() {
+        use bbqueue::mutex::CsProducer;
+        use std::sync::Arc;
+
+        static BB: BBBuffer<32> = BBBuffer::new();
+        let (prod, mut cons) = BB.try_split().unwrap();
+        let prod = Arc::new(CsProducer::new(prod));
+
+        // A second grant fails rather than waiting for the first one
+        let grant = prod.grant_exact(2).unwrap();
+        assert_eq!(
+            prod.grant_max_remaining(2).err(),
+            Some(BBQError::GrantInProgress)
+        );
+        drop(grant);
+
+        let threads: Vec<_> = (0..4u8)
+            .map(|id| {
+                let prod = prod.clone();
+                std::thread::spawn(move || {
+                    for seq in 0..100u8 {
+                        loop {
+                            if let Ok(mut grant) = prod.grant_exact(2) {
+                                grant.copy_from_slice(&[id, seq]);
+                                grant.commit(2);
+                                break;
+                            }
+                            std::thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Each pair of bytes is from a single grant, in order per thread
+        let mut next = [0u8; 4];
+        while next.iter().any(|seq| *seq < 100) {
+            if let Ok(grant) = cons.read_exact(2) {
+                let (id, seq) = (usize::from(grant[0]), grant[1]);
+                assert_eq!(next[id], seq);
+                next[id] += 1;
+                grant.release(2);
+            } else {
+                std::thread::yield_now();
+            }
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[test]
+    fn no_critical_sections_after_split() {
+        use crate::counting_cs::taken;
+
+        static BB: BBBuffer<16> = BBBuffer::new();
+        let before = taken();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+        assert_eq!(taken(), before + 1);
+
+        // Wrap around, invert, compact and drop grants, without a single
+        // read-modify-write operation
+        let before = taken();
+        for i in 0..100u8 {
+            let mut wgr = prod.grant_exact(5).unwrap();
+            wgr.copy_from_slice(&[i; 5]);
+            wgr.commit(5);
+            drop(prod.grant_max_remaining(7).unwrap());
+
+            let rgr = cons.read().unwrap();
+            assert_eq!(&*rgr, &[i; 5]);
+            rgr.release(2);
+            drop(cons.read_exact(2).unwrap());
+            cons.split_read().unwrap().release(3);
+
+            assert!(cons.read().is_err());
+            assert!(prod.try_compact());
+        }
+        assert_eq!(taken(), before);
+    }
+
+    #[test]
+    fn close() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        assert!(!cons.is_producer_closed());
+        assert!(!prod.is_consumer_closed());
+
+        prod.write_frame(&[1, 2, 3], &[]).unwrap();
+        cons.close();
+        assert!(prod.is_consumer_closed());
+        assert_eq!(prod.grant_exact(1).unwrap_err(), BBQError::Disconnected);
+        assert_eq!(
+            prod.grant_max_remaining(1).unwrap_err(),
+            BBQError::Disconnected
+        );
+
+        // Data committed before closing can still be read
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 2, 3]);
+        grant.release(3);
+        assert_eq!(cons.read().unwrap_err(), BBQError::Empty);
+
+        prod.close();
+        assert!(cons.is_producer_closed());
+        assert_eq!(cons.read().unwrap_err(), BBQError::Disconnected);
+
+        // Releasing the halves re-opens the buffer
+        assert!(bb.try_release(prod, cons).is_ok());
+        let (mut prod, cons) = bb.try_split().unwrap();
+        assert!(!cons.is_producer_closed());
+        prod.grant_exact(1).unwrap().commit(1);
+    }
+
+    #[test]
+    fn io_read_write() {
+        use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            Read::read(&mut cons, &mut buf).unwrap_err().kind(),
+            ErrorKind::WouldBlock
+        );
+
+        // Only the space that is available is written
+        assert_eq!(prod.write(&[1, 2, 3, 4, 5, 6]).unwrap(), 6);
+        assert_eq!(prod.write(&[7, 8, 9]).unwrap(), 2);
+        assert_eq!(prod.write(&[9]).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        assert_eq!(Read::read(&mut cons, &mut buf[..3]).unwrap(), 3);
+        assert_eq!(&buf[..3], &[1, 2, 3]);
+        assert_eq!(Read::read(&mut cons, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], &[4, 5, 6, 7, 8]);
+
+        // Through buffered wrappers, until the producer is closed
+        {
+            let mut writer = BufWriter::new(&mut prod);
+            writer.write_all(b"hi\nyo\n").unwrap();
+            writer.flush().unwrap();
+        }
+        prod.close();
+
+        let lines: Vec<String> = BufReader::new(&mut cons)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(lines, vec!["hi", "yo"]);
+        assert_eq!(Read::read(&mut cons, &mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn typed_writes() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        let mut grant = prod.grant_exact(15).unwrap();
+        grant
+            .write_u8(1)
+            .write_u16_le(0x0302)
+            .write_u32_le(0x0706_0504)
+            .write_u64_le(0x0F0E_0D0C_0B0A_0908);
+        assert_eq!(grant.written(), 15);
+        grant.commit(15);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(
+            &*grant,
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+    }
+
+    #[test]
+    fn wrap_from_end() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        for round in 0..8u8 {
+            // Fill the buffer up to exactly the end
+            let mut grant = prod.grant_max_remaining(8).unwrap();
+            let len = grant.len();
+            grant.fill(0xFF);
+            grant.commit(len);
+            let grant = cons.read().unwrap();
+            assert_eq!(grant.len(), len);
+            grant.release(len - 1);
+
+            // Wrap around, but only commit part of the grant, so stale bytes
+            // remain behind the committed ones
+            let mut grant = prod.grant_exact(1 + usize::from(round % 6)).unwrap();
+            grant.fill(0xEE);
+            grant[0] = round;
+            grant.commit(1);
+
+            let grant = cons.read().unwrap();
+            assert_eq!(&*grant, &[0xFF]);
+            grant.release(1);
+            let grant = cons.read().unwrap();
+            assert_eq!(&*grant, &[round]);
+            grant.release(1);
+            assert!(cons.read().is_err());
+        }
+    }
+
+    #[test]
+    fn copy_from_slice_partial() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        // A source shorter than the grant leaves the rest untouched
+        let mut grant = prod.grant_exact(4).unwrap();
+        grant.fill(0xEE);
+        assert_eq!(grant.copy_from_slice_partial(&[1, 2]), 2);
+        grant.commit(4);
+
+        // A source longer than the grant is cut short
+        let mut grant = prod.grant_max_remaining(8).unwrap();
+        assert_eq!(grant.len(), 4);
+        assert_eq!(grant.copy_from_slice_partial(&[3, 4, 5, 6, 7, 8]), 4);
+        grant.commit(4);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 2, 0xEE, 0xEE, 3, 4, 5, 6]);
+        grant.release(8);
+
+        // An empty source copies nothing
+        let mut grant = prod.grant_exact(2).unwrap();
+        assert_eq!(grant.copy_from_slice_partial(&[]), 0);
+    }
+
+    #[test]
+    fn grant_split_at_mut() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        // Fill both halves from separate threads, then commit them at once
+        let mut grant = prod.grant_exact(6).unwrap();
+        let (head, tail) = grant.split_at_mut(2);
+        std::thread::scope(|s| {
+            s.spawn(|| head.fill(1));
+            s.spawn(|| tail.fill(2));
+        });
+        grant.commit(6);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 1, 2, 2, 2, 2]);
+        grant.release(6);
+
+        // Either side may be empty
+        let mut grant = prod.grant_exact(4).unwrap();
+        assert_eq!(grant.split_at_mut(0).1.len(), 4);
+        assert_eq!(grant.split_at_mut(4).0.len(), 4);
+    }
+
+    #[test]
+    fn grant_split_at_ref() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        // Partition both grants the same way, on each side of the queue
+        let mut grant = prod.grant_exact(6).unwrap();
+        let (head, tail) = grant.split_at_mut(2);
+        head.copy_from_slice(&[1, 2]);
+        tail.fill(3);
+        grant.commit(6);
+
+        let grant = cons.read().unwrap();
+        let (head, tail) = grant.split_at_ref(2);
+        let (head_sum, tail_sum) = std::thread::scope(|s| {
+            let head = s.spawn(|| head.iter().map(|b| u32::from(*b)).sum::<u32>());
+            let tail = s.spawn(|| tail.iter().map(|b| u32::from(*b)).sum::<u32>());
+            (head.join().unwrap(), tail.join().unwrap())
+        });
+        assert_eq!((head_sum, tail_sum), (3, 12));
+
+        // The whole grant is released at once
+        grant.release(6);
+        assert!(cons.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn grant_split_at_mut_out_of_bounds() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, _cons) = bb.try_split().unwrap();
+        let mut grant = prod.grant_exact(4).unwrap();
+        grant.split_at_mut(5);
+    }
+
+    #[test]
+    fn compact_empty() {
+        let bb: BBBuffer<1024> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        prod.grant_exact(900).unwrap().commit(900);
+        cons.read().unwrap().release(900);
+
+        // The queue is empty, so the whole buffer is available again
+        let mut grant = prod.grant_exact(1024).unwrap();
+        grant[0] = 1;
+        grant[1023] = 2;
+        grant.commit(1024);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(grant.len(), 1024);
+        assert_eq!((grant[0], grant[1023]), (1, 2));
+        grant.release(1000);
+
+        // Not while the queue is in use, or a grant is in progress
+        assert!(!prod.try_compact());
+        let rgr = cons.read().unwrap();
+        rgr.release(24);
+        let wgr = prod.grant_exact(1).unwrap();
+        assert!(!prod.try_compact());
+        drop(wgr);
+
+        // Once the queue is empty again, it can also be moved explicitly
+        assert!(cons.is_empty());
+        assert!(prod.try_compact());
+        assert_eq!(prod.grant_max_remaining(2000).unwrap().len(), 1024);
+
+        // Other grants only move it when their minimum would not fit otherwise
+        prod.grant_exact(900).unwrap().commit(900);
+        cons.read().unwrap().release(900);
+        assert_eq!(prod.grant_max_remaining(2000).unwrap().len(), 124);
+        assert_eq!(prod.grant_max_with_minimum(2000, 512).unwrap().len(), 900);
+        assert_eq!(prod.grant_max_with_minimum(2000, 950).unwrap().len(), 1024);
+        assert_eq!(cons.bytes_lost(), 0);
+    }
+
+    #[test]
+    fn fill_level_ewma() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+
+        assert_eq!(bb.fill_level_ewma(0.5), 0.0);
+
+        // Converges towards a full buffer
+        prod.grant_exact(8).unwrap().commit(8);
+        let mut level = 0.0;
+        for _ in 0..100 {
+            let next = bb.fill_level_ewma(0.1);
+            assert!(next >= level && next <= 1.0);
+            level = next;
+        }
+        assert!(level > 0.99);
+
+        // Then back towards a quarter, and follows immediately with a weight of one
+        cons.read().unwrap().release(6);
+        assert!(bb.fill_level_ewma(0.1) < level);
+        assert_eq!(bb.fill_level_ewma(1.0), 0.25);
+
+        // Weights out of range are clamped
+        assert_eq!(bb.fill_level_ewma(f32::NAN), 0.25);
+        assert_eq!(bb.fill_level_ewma(-1.0), 0.25);
+        cons.read().unwrap().release(2);
+        assert_eq!(bb.fill_level_ewma(2.0), 0.0);
+    }
+
+    #[test]
+    fn poisoned() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(2).unwrap().commit(2);
+
+        // Panic halfway through writing a grant, which is meant to be committed
+        // when dropped
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let mut grant = prod.grant_exact(4).unwrap();
+            grant.to_commit(4);
+            grant[0] = 0xFF;
+            panic!("interrupted while writing");
+        }));
+        assert!(res.is_err());
+
+        // Neither side sees the partially written data
+        assert!(bb.is_poisoned());
+        assert_eq!(cons.read().unwrap_err(), BBQError::Poisoned);
+        assert_eq!(cons.split_read().unwrap_err(), BBQError::Poisoned);
+        assert_eq!(prod.grant_exact(1).unwrap_err(), BBQError::Poisoned);
+        assert_eq!(prod.grant_max_remaining(1).unwrap_err(), BBQError::Poisoned);
+
+        // Until the poison is cleared by someone who knows better
+        bb.clear_poison();
+        let grant = cons.read().unwrap();
+        assert_eq!(grant.len(), 6);
+        grant.release(6);
+
+        // Releasing a split grant while panicking poisons as well
+        prod.grant_exact(2).unwrap().commit(2);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let mut grant = cons.split_read().unwrap();
+            grant.to_release(1);
+            panic!("interrupted while reading");
+        }));
+        assert!(res.is_err());
+        assert_eq!(prod.grant_exact(1).unwrap_err(), BBQError::Poisoned);
+
+        // Splitting again starts out clean
+        assert!(bb.try_release(prod, cons).is_ok());
+        assert!(!bb.is_poisoned());
+
+        // Grants dropped by an unrelated panic, without committing or
+        // releasing anything, leave the queue as it was
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(2).unwrap().commit(2);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let _wgr = prod.grant_exact(4).unwrap();
+            let _rgr = cons.read().unwrap();
+            panic!("unrelated");
+        }));
+        assert!(res.is_err());
+        assert!(!bb.is_poisoned());
+        cons.read().unwrap().release(2);
+        prod.grant_exact(1).unwrap().commit(1);
+    }
+
+    #[test]
+    fn leaked_grants() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(2).unwrap().commit(2);
+
+        // Leaked grants are never committed or released, so both halves
+        // stay busy, without poisoning the queue
+        core::mem::forget(prod.grant_exact(2).unwrap());
+        core::mem::forget(cons.read().unwrap());
+        assert!(!bb.is_poisoned());
+        assert_eq!(prod.grant_exact(1).unwrap_err(), BBQError::GrantInProgress);
+        assert_eq!(cons.read().unwrap_err(), BBQError::GrantInProgress);
+
+        // Which clearing the poison does not change
+        bb.clear_poison();
+        assert_eq!(
+            prod.grant_max_remaining(1).unwrap_err(),
+            BBQError::GrantInProgress
+        );
+        assert_eq!(cons.split_read().unwrap_err(), BBQError::GrantInProgress);
+
+        // And the buffer can not be released either
+        let (prod, cons) = match bb.try_release(prod, cons) {
+            Ok(()) => panic!("released with leaked grants"),
+            Err(halves) => halves,
+        };
+        assert!(bb.try_release(prod, cons).is_err());
+    }
 
     #[test]
-    fn deref_deref_mut() {
-        let bb: BBBuffer<6> = BBBuffer::new();
+    fn over_release() {
+        let bb: BBBuffer<8> = BBBuffer::new();
         let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        let mut wgr = prod.grant_exact(1).unwrap();
+        prod.grant_exact(6).unwrap().commit(6);
+        cons.read().unwrap().release(3);
+
+        // Wrap around, with `last` at six
+        let mut grant = prod.grant_exact(3).unwrap();
+        grant.copy_from_slice(&[7, 8, 9]);
+        grant.commit(3);
+
+        // Releasing too much saturates exactly at `last`
+        let grant = cons.read().unwrap();
+        assert_eq!(grant.len(), 3);
+        assert!(grant.release_checked(4).is_err());
+        let grant = cons.read().unwrap();
+        grant.release(usize::MAX);
+        assert_eq!(cons.bytes_available(), 3);
+
+        // So the next read wraps around to the start
+        let mut grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[7, 8, 9]);
+        grant.to_release(usize::MAX);
+        drop(grant);
+        assert!(cons.is_empty());
+
+        // And the queue remains usable
+        for i in 0..20u8 {
+            let mut grant = prod.grant_exact(5).unwrap();
+            grant.fill(i);
+            grant.commit(5);
+
+            let (head, tail) = cons.read().unwrap().split_at(2);
+            assert!(head.iter().chain(tail.iter()).all(|byte| *byte == i));
+            head.release();
+            tail.release();
+            assert!(cons.is_empty());
+        }
+    }
 
-        // deref_mut
-        wgr[0] = 123;
+    #[test]
+    fn stale_release() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        assert_eq!(wgr.len(), 1);
+        let mut grant = prod.grant_exact(4).unwrap();
+        grant.copy_from_slice(&[1, 2, 3, 4]);
+        grant.commit(4);
+
+        // Forge a copy of a grant, which is stale once the original has been
+        // released. Its bytes are never accessed.
+        let grant = cons.read().unwrap();
+        let stale = unsafe { core::ptr::read(&grant) };
+        grant.release(2);
+
+        // Checking the release reports the stale grant, and changes nothing
+        let grant = cons.read().unwrap();
+        let (err, stale) = stale.release_checked(2).unwrap_err();
+        assert_eq!(err, BBQError::StaleGrant);
+        assert!(!bb.is_poisoned());
+        assert_eq!(cons.read().unwrap_err(), BBQError::GrantInProgress);
+
+        // Releasing the stale grant while another one is in progress must
+        // not release any bytes, but ends the read and poisons the queue
+        stale.release(2);
+        assert!(bb.is_poisoned());
+        drop(grant);
+        assert_eq!(cons.read().unwrap_err(), BBQError::Poisoned);
+
+        // Once cleared, the unreleased bytes can be read again
+        bb.clear_poison();
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[3, 4]);
+        grant.release(2);
+        assert_eq!(cons.read().unwrap_err(), BBQError::Empty);
+
+        // And the queue remains usable
+        for i in 0..20u8 {
+            let mut grant = prod.grant_exact(5).unwrap();
+            grant.fill(i);
+            grant.commit(5);
+
+            let grant = cons.read().unwrap();
+            assert!(grant.iter().all(|byte| *byte == i));
+            grant.release(5);
+        }
+        assert!(cons.is_empty());
+    }
 
-        wgr.commit(1);
+    #[test]
+    fn stale_after_release() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        prod.grant_exact(4).unwrap().commit(4);
+
+        // Forge copies of a grant of each kind, which outlive the split.
+        // Their bytes are never accessed.
+        let grant = cons.read().unwrap();
+        let stale_r = unsafe { core::ptr::read(&grant) };
+        grant.release(4);
+        let grant = prod.grant_exact(2).unwrap();
+        let stale_w = unsafe { core::ptr::read(&grant) };
+        grant.commit(0);
+        assert!(bb.try_release(prod, cons).is_ok());
 
-        // deref
-        let rgr = cons.read().unwrap();
+        // Split again, and hold a grant at the same position as the stale
+        // read grant
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        let mut grant = prod.grant_exact(3).unwrap();
+        grant.copy_from_slice(&[7, 8, 9]);
+        grant.commit(3);
+        let grant = cons.read().unwrap();
+
+        // The stale grants are rejected, and dropping them changes nothing
+        let (err, stale_w) = stale_w.commit_checked(2).unwrap_err();
+        assert_eq!(err, BBQError::StaleGrant);
+        let (err, stale_r) = stale_r.release_checked(2).unwrap_err();
+        assert_eq!(err, BBQError::StaleGrant);
+        drop(stale_w);
+        drop(stale_r);
+        assert!(!bb.is_poisoned());
+        assert_eq!(cons.read().unwrap_err(), BBQError::GrantInProgress);
+        assert_eq!(cons.bytes_available(), 3);
+
+        // So the current grants carry on as usual
+        assert_eq!(&*grant, &[7, 8, 9]);
+        grant.release(3);
+        prod.grant_exact(8).unwrap().commit(8);
+        assert_eq!(cons.read().unwrap().len(), 8);
+    }
 
-        assert_eq!(rgr[0], 123);
+    #[test]
+    fn zero_commit_wrap() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        rgr.release(1);
+        prod.grant_exact(6).unwrap().commit(6);
+        cons.read().unwrap().release(6);
+
+        // This grant wraps around to the start, but nothing is committed
+        prod.grant_exact(4).unwrap().commit(0);
+        assert!(cons.read().is_err());
+
+        // So the two bytes at the end were not skipped
+        let mut grant = prod.grant_max_remaining(8).unwrap();
+        assert_eq!(grant.len(), 2);
+        grant.copy_from_slice(&[1, 2]);
+        grant.commit(2);
+
+        // Dropping a wrapping grant is the same as committing nothing
+        drop(prod.grant_exact(5).unwrap());
+        let mut grant = prod.grant_exact(5).unwrap();
+        grant.copy_from_slice(&[3, 4, 5, 6, 7]);
+        grant.commit(5);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 2]);
+        grant.release(2);
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[3, 4, 5, 6, 7]);
+        grant.release(5);
+        assert!(cons.is_empty());
     }
 
     #[test]
-    fn static_allocator() {
-        // Check we can make multiple static items...
-        static BBQ1: BBBuffer<6> = BBBuffer::new();
-        static BBQ2: BBBuffer<6> = BBBuffer::new();
-        let (mut prod1, mut cons1) = BBQ1.try_split().unwrap();
-        let (mut _prod2, mut cons2) = BBQ2.try_split().unwrap();
+    fn zero_commit_wrap_stream() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        // ... and they aren't the same
-        let mut wgr1 = prod1.grant_exact(3).unwrap();
-        wgr1.copy_from_slice(&[1, 2, 3]);
-        wgr1.commit(3);
+        let mut sent = 0u8;
+        let mut received = 0u8;
+        for i in 0..200usize {
+            // Attempt a grant that often has to wrap, and then abandon it
+            if let Ok(grant) = prod.grant_exact(1 + i % 7) {
+                grant.commit(0);
+            }
 
-        // no data here...
-        assert!(cons2.read().is_err());
+            if let Ok(mut grant) = prod.grant_max_remaining(1 + i % 3) {
+                for byte in grant.iter_mut() {
+                    *byte = sent;
+                    sent = sent.wrapping_add(1);
+                }
+                let len = grant.len();
+                grant.commit(len);
+            }
 
-        // ...data is here!
-        let rgr1 = cons1.read().unwrap();
-        assert_eq!(&*rgr1, &[1, 2, 3]);
+            // Only drain every other iteration, so the positions keep moving
+            if i % 2 == 1 {
+                while let Ok(grant) = cons.read() {
+                    for byte in grant.iter() {
+                        assert_eq!(*byte, received);
+                        received = received.wrapping_add(1);
+                    }
+                    let len = grant.len();
+                    grant.release(len);
+                }
+                assert_eq!(received, sent);
+            }
+        }
     }
 
     #[test]
-    fn release() {
-        // Check we can make multiple static items...
-        static BBQ1: BBBuffer<6> = BBBuffer::new();
-        static BBQ2: BBBuffer<6> = BBBuffer::new();
-        let (prod1, cons1) = BBQ1.try_split().unwrap();
-        let (prod2, cons2) = BBQ2.try_split().unwrap();
+    fn grant_max_remaining_zero() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, cons) = bb.try_split().unwrap();
 
-        // We cannot release with the wrong prod/cons
-        let (prod2, cons2) = BBQ1.try_release(prod2, cons2).unwrap_err();
-        let (prod1, cons1) = BBQ2.try_release(prod1, cons1).unwrap_err();
+        // As in 0.5, asking for nothing grants nothing while there is room
+        let grant = prod.grant_max_remaining(0).unwrap();
+        assert!(grant.is_empty());
+        grant.commit(0);
+        assert_eq!(
+            prod.grant_max_with_minimum(0, 1).unwrap_err(),
+            BBQError::InsufficientSize
+        );
 
-        // We cannot release with the wrong consumer...
-        let (prod1, cons2) = BBQ1.try_release(prod1, cons2).unwrap_err();
+        // But not once the buffer is full
+        prod.grant_exact(8).unwrap().commit(8);
+        assert_eq!(
+            prod.grant_max_remaining(0).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 0);
+    }
 
-        // ...or the wrong producer
-        let (prod2, cons1) = BBQ1.try_release(prod2, cons1).unwrap_err();
+    #[test]
+    fn grant_max_with_minimum() {
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        // We cannot release with a write grant in progress
-        let mut prod1 = prod1;
-        let wgr1 = prod1.grant_exact(3).unwrap();
-        let (prod1, mut cons1) = BBQ1.try_release(prod1, cons1).unwrap_err();
+        assert_eq!(
+            prod.grant_max_with_minimum(2, 3).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        prod.grant_exact(6).unwrap().commit(6);
 
-        // We cannot release with a read grant in progress
-        wgr1.commit(3);
-        let rgr1 = cons1.read().unwrap();
-        let (prod1, cons1) = BBQ1.try_release(prod1, cons1).unwrap_err();
+        // Two bytes at the end, none at the start
+        assert_eq!(
+            prod.grant_max_with_minimum(4, 3).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(prod.grant_max_with_minimum(4, 2).unwrap().len(), 2);
+        cons.read().unwrap().release(5);
 
-        // But we can when everything is resolved
-        rgr1.release(3);
-        assert!(BBQ1.try_release(prod1, cons1).is_ok());
-        assert!(BBQ2.try_release(prod2, cons2).is_ok());
+        // Two bytes at the end, five at the start
+        let mut grant = prod.grant_max_with_minimum(8, 3).unwrap();
+        assert_eq!(grant.len(), 5);
+        grant.copy_from_slice(&[1, 2, 3, 4, 5]);
+        grant.commit(5);
 
-        // And we can re-split on-demand
-        let _ = BBQ1.try_split().unwrap();
-        let _ = BBQ2.try_split().unwrap();
+        // Inverted, with no room left
+        assert_eq!(
+            prod.grant_max_with_minimum(1, 1).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+
+        let grant = cons.read().unwrap();
+        assert_eq!(grant.len(), 1);
+        grant.release(1);
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[1, 2, 3, 4, 5]);
+        grant.release(5);
+        assert!(cons.is_empty());
+    }
+
+    /// Put a fresh buffer into the state with `read` and `write` at the given
+    /// positions, returning the committed bytes, in order
+    fn set_positions<'a, const N: usize>(
+        prod: &mut bbqueue::Producer<'a, N>,
+        cons: &mut bbqueue::Consumer<'a, N>,
+        read: usize,
+        write: usize,
+        inverted: bool,
+    ) -> Vec<u8> {
+        let mut next = 0u8;
+        let mut commit = |prod: &mut bbqueue::Producer<'a, N>, sz: usize| {
+            let mut grant = prod.grant_exact(sz).unwrap();
+            for byte in grant.iter_mut() {
+                next = next.wrapping_add(1);
+                *byte = next;
+            }
+            grant.commit(sz);
+        };
+
+        if inverted {
+            // Fill the buffer, and wrap around up to `write`
+            commit(prod, N);
+            cons.read().unwrap().release(read);
+            commit(prod, write);
+        } else {
+            if write > 0 {
+                commit(prod, write);
+            }
+            if read > 0 {
+                cons.read().unwrap().release(read);
+            }
+        }
+
+        let grant = cons.split_read().map(|grant| {
+            let (first, second) = grant.bufs();
+            first.iter().chain(second).copied().collect()
+        });
+        grant.unwrap_or_default()
+    }
+
+    /// Check the grants of every state of a `BBBuffer<N>` against the free
+    /// space found by brute force, byte by byte
+    fn check_grant_bounds<const N: usize>() {
+        let mut states = vec![];
+        for write in 0..=N {
+            for read in 0..=write {
+                states.push((read, write, false));
+            }
+        }
+        for read in 1..=N {
+            for write in 1..=read {
+                states.push((read, write, true));
+            }
+        }
+
+        for &(read, write, inverted) in states.iter() {
+            // Which positions hold committed bytes
+            let used: Vec<bool> = (0..N)
+                .map(|pos| match inverted {
+                    false => pos >= read && pos < write,
+                    true => pos >= read || pos < write,
+                })
+                .collect();
+            let free_from = |start: usize| used[start..].iter().take_while(|u| !**u).count();
+
+            // A grant only wraps around to the start when `write` is at
+            // the end, or when an exact grant does not fit before it
+            let here = free_from(write);
+            let wrapped = if inverted { 0 } else { free_from(0) };
+            let remaining = if inverted || write < N { here } else { wrapped };
+
+            for sz in 1..=N + 1 {
+                let state = format!(
+                    "N={} read={} write={} inverted={} sz={}",
+                    N, read, write, inverted, sz
+                );
+
+                let bb: BBBuffer<N> = BBBuffer::new();
+                let (mut prod, mut cons) = bb.try_split().unwrap();
+                let mut expected = set_positions(&mut prod, &mut cons, read, write, inverted);
+                assert_eq!(
+                    expected.len(),
+                    used.iter().filter(|u| **u).count(),
+                    "{}",
+                    state
+                );
+
+                let max = prod.grant_max_remaining(sz).map(|grant| grant.len());
+                let max_expected = match remaining {
+                    0 => Err(BBQError::InsufficientSize),
+                    remaining => Ok(remaining.min(sz)),
+                };
+                assert_eq!(max, max_expected, "grant_max_remaining, {}", state);
+
+                let grant = prod.grant_exact(sz);
+                let fits = sz <= here || sz <= wrapped;
+                assert_eq!(grant.is_ok(), fits, "grant_exact, {}", state);
+
+                // The grant must not overwrite any committed byte
+                if let Ok(mut grant) = grant {
+                    grant.copy_from_slice(&vec![0xEE; sz]);
+                    grant.commit(sz);
+                    expected.resize(expected.len() + sz, 0xEE);
+
+                    let grant = cons.split_read().unwrap();
+                    let (first, second) = grant.bufs();
+                    let actual: Vec<u8> = first.iter().chain(second).copied().collect();
+                    assert_eq!(actual, expected, "contents, {}", state);
+                }
+            }
+        }
     }
 
     #[test]
-    fn direct_usage_sanity() {
-        // Initialize
-        let bb: BBBuffer<6> = BBBuffer::new();
+    fn grant_bounds() {
+        check_grant_bounds::<1>();
+        check_grant_bounds::<2>();
+        check_grant_bounds::<3>();
+        check_grant_bounds::<4>();
+        check_grant_bounds::<5>();
+        check_grant_bounds::<6>();
+        check_grant_bounds::<7>();
+        check_grant_bounds::<8>();
+        check_grant_bounds::<16>();
+
+        // Inverted, with six free bytes between `write` and `read`
+        let bb: BBBuffer<16> = BBBuffer::new();
         let (mut prod, mut cons) = bb.try_split().unwrap();
-        assert_eq!(cons.read(), Err(BBQError::InsufficientSize));
+        set_positions(&mut prod, &mut cons, 10, 4, true);
+        assert_eq!(prod.grant_max_remaining(16).unwrap().len(), 6);
+    }
 
-        // Initial grant, shouldn't roll over
-        let mut x = prod.grant_exact(4).unwrap();
+    #[test]
+    fn total_bytes() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        // Still no data available yet
-        assert_eq!(cons.read(), Err(BBQError::InsufficientSize));
+        let mut total = 0u64;
+        for i in 1..1000usize {
+            let grant = prod.grant_max_remaining(i % 10 + 1).unwrap();
+            let sz = grant.len();
+            grant.commit(sz);
+            assert_eq!(bb.total_bytes_written(), total + sz as u64);
+
+            // Drain, possibly in two parts across the wrap
+            while let Ok(grant) = cons.read() {
+                let len = grant.len();
+                grant.release(len);
+            }
+            total += sz as u64;
+            assert_eq!(bb.total_bytes_read(), total);
+        }
+        assert_eq!(bb.observer().producer_cursor().position, total);
 
-        // Add full data from grant
-        x.copy_from_slice(&[1, 2, 3, 4]);
+        // The counters restart with the next split
+        assert_eq!(
+            prod.grant_exact(17).unwrap_err(),
+            BBQError::InsufficientSize
+        );
+        assert_eq!(cons.bytes_lost(), 17);
+        bb.fill_level_ewma(1.0);
+        assert!(bb.try_release(prod, cons).is_ok());
+        let (prod, cons) = bb.try_split().unwrap();
+        assert_eq!((bb.total_bytes_written(), bb.total_bytes_read()), (0, 0));
+        assert_eq!(prod.cursor() - cons.cursor(), 0);
+        assert_eq!(cons.bytes_lost(), 0);
+        assert_eq!(bb.fill_level_ewma(0.0), 0.0);
+    }
 
-        // Still no data available yet
-        assert_eq!(cons.read(), Err(BBQError::InsufficientSize));
+    #[test]
+    fn subscribe() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        use std::task::Wake;
+
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
 
-        // Commit data
-        x.commit(4);
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
+        let wakes = Arc::new(Counter(AtomicUsize::new(0)));
+        let count = || wakes.0.load(Ordering::Relaxed);
+
+        // Nothing subscribed
+        prod.grant_exact(2).unwrap().commit(2);
+        cons.read().unwrap().release(2);
+
+        cons.subscribe(5, wakes.clone().into());
+        prod.grant_exact(4).unwrap().commit(4);
+        assert_eq!(count(), 0);
+        prod.grant_exact(4).unwrap().commit(4);
+        assert_eq!(count(), 1);
+
+        // Only woken once
+        prod.grant_exact(1).unwrap().commit(1);
+        assert_eq!(count(), 1);
+
+        // Already available
+        cons.subscribe(9, wakes.clone().into());
+        assert_eq!(count(), 2);
+
+        // Subscribing again replaces the previous subscription
+        cons.read().unwrap().release(9);
+        cons.subscribe(1, wakes.clone().into());
+        cons.subscribe(3, wakes.clone().into());
+        prod.grant_exact(2).unwrap().commit(2);
+        assert_eq!(count(), 2);
+        prod.grant_exact(1).unwrap().commit(1);
+        assert_eq!(count(), 3);
+    }
 
-        ::std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    #[test]
+    fn crc32_commit() {
+        let bb: BBBuffer<32> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        let a = cons.read().unwrap();
-        assert_eq!(&*a, &[1, 2, 3, 4]);
+        // The standard check value
+        let mut grant = prod.grant_exact(13).unwrap();
+        grant[..9].copy_from_slice(b"123456789");
+        grant.commit_with_crc32(9);
 
-        // Release the first two bytes
-        a.release(2);
+        let grant = cons.read().unwrap();
+        assert_eq!(&grant[9..], &0xCBF4_3926u32.to_le_bytes());
+        assert_eq!(grant.release_and_verify_crc32(9), Ok(()));
 
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[3, 4]);
-        r.release(0);
+        // Corrupted payload
+        let mut grant = prod.grant_exact(10).unwrap();
+        grant[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        grant.commit_with_crc32(6);
+        prod.grant_exact(4).unwrap().commit_with_crc32(0);
 
-        // Grant two more
-        let mut x = prod.grant_exact(2).unwrap();
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[3, 4]);
-        r.release(0);
+        let mut grant = cons.read().unwrap();
+        grant[2] ^= 0x10;
+        assert_eq!(grant.len(), 14);
+        assert_eq!(
+            grant.release_and_verify_crc32(6),
+            Err(BBQError::ChecksumMismatch)
+        );
 
-        // Add more data
-        x.copy_from_slice(&[11, 12]);
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[3, 4]);
-        r.release(0);
+        // The stream continues after the bad payload
+        for len in [1, usize::MAX] {
+            let grant = cons.read().unwrap();
+            assert_eq!(
+                grant.release_and_verify_crc32(len),
+                Err(BBQError::InsufficientSize)
+            );
+        }
+        let grant = cons.read().unwrap();
+        assert_eq!(grant.release_and_verify_crc32(0), Ok(()));
+        assert!(cons.is_empty());
+    }
 
-        // Commit
-        x.commit(2);
+    #[test]
+    fn crc32_read() {
+        let bb: BBBuffer<32> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        let a = cons.read().unwrap();
-        assert_eq!(&*a, &[3, 4, 11, 12]);
+        for (i, len) in [5usize, 0, 8].iter().enumerate() {
+            let mut grant = prod.grant_exact(len + 4).unwrap();
+            grant[..*len].fill(i as u8);
+            grant.commit_with_crc32(*len);
+        }
 
-        a.release(2);
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[11, 12]);
-        r.release(0);
+        // Not enough bytes, including lengths that overflow with the checksum
+        for len in [30, usize::MAX - 3, usize::MAX] {
+            assert_eq!(
+                cons.read_and_verify_crc32(len).unwrap_err(),
+                BBQError::InsufficientSize
+            );
+        }
 
-        let mut x = prod.grant_exact(3).unwrap();
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[11, 12]);
-        r.release(0);
+        // Releasing the whole payload also releases the checksum
+        let grant = cons.read_and_verify_crc32(5).unwrap();
+        assert_eq!(&*grant, &[0; 5]);
+        grant.release(5);
+
+        // An empty payload, released by dropping
+        let mut grant = cons.read_and_verify_crc32(0).unwrap();
+        assert!(grant.is_empty());
+        grant.to_release(0);
+        drop(grant);
+
+        // Corrupt the last payload, which is discarded
+        let mut grant = cons.read().unwrap();
+        grant[3] = 0xAA;
+        drop(grant);
+        assert_eq!(
+            cons.read_and_verify_crc32(8).unwrap_err(),
+            BBQError::ChecksumMismatch
+        );
+        assert!(cons.is_empty());
+
+        // Split grants release the checksum with the tail
+        let mut grant = prod.grant_exact(10).unwrap();
+        grant[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        grant.commit_with_crc32(6);
+        let (head, tail) = cons.read_and_verify_crc32(6).unwrap().split_at(2);
+        assert_eq!((&*head, &*tail), (&[1, 2][..], &[3, 4, 5, 6][..]));
+        head.release();
+        tail.release();
+        assert!(cons.is_empty());
+    }
 
-        x.copy_from_slice(&[21, 22, 23]);
+    #[test]
+    fn typed_big_endian() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[11, 12]);
-        r.release(0);
-        x.commit(3);
+        let mut grant = prod.grant_exact(14).unwrap();
+        grant
+            .write_u16_le(0x0102)
+            .write_u32_be(0x0304_0506)
+            .write_u64_be(0x0708_090A_0B0C_0D0E);
+        grant.commit(14);
+
+        let mut grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[2, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+        assert_eq!(grant.read_u16_be(), Ok(0x0201));
+        assert_eq!(grant.read_u32_be(), Ok(0x0304_0506));
+        assert_eq!(grant.read_u64_be(), Ok(0x0708_090A_0B0C_0D0E));
+        assert_eq!(grant.read_u16_be(), Err(BBQError::InsufficientSize));
+        grant.release(14);
+
+        let mut grant = prod.grant_exact(2).unwrap();
+        let mut cursor = grant.cursor();
+        cursor.write_u16_be(0x0A0B).unwrap();
+        assert_eq!(cursor.write_u32_be(0), Err(BBQError::InsufficientSize));
+        grant.commit(2);
+
+        let grant = cons.read().unwrap();
+        let mut cursor = grant.cursor();
+        assert_eq!(cursor.read_u16_be(), Ok(0x0A0B));
+    }
 
-        let a = cons.read().unwrap();
+    #[test]
+    #[should_panic]
+    fn typed_writes_overflow() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, _cons) = bb.try_split().unwrap();
 
-        // NOTE: The data we just added isn't available yet,
-        // since it has wrapped around
-        assert_eq!(&*a, &[11, 12]);
+        let mut grant = prod.grant_exact(3).unwrap();
+        grant.write_u8(1).write_u16_le(2).write_u8(3);
+    }
 
-        a.release(2);
+    #[test]
+    fn typed_reads() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split().unwrap();
 
-        // And now we can see it
-        let r = cons.read().unwrap();
-        assert_eq!(&*r, &[21, 22, 23]);
-        r.release(0);
+        let mut grant = prod.grant_exact(15).unwrap();
+        grant
+            .write_u8(1)
+            .write_u16_le(0x0302)
+            .write_u32_le(0x0706_0504)
+            .write_u64_le(0x0F0E_0D0C_0B0A_0908);
+        grant.commit(15);
+
+        let mut grant = cons.read().unwrap();
+        assert_eq!(grant.read_u8(), Ok(1));
+        assert_eq!(grant.read_u16_le(), Ok(0x0302));
+        assert_eq!(grant.read_u32_le(), Ok(0x0706_0504));
+        assert_eq!(grant.consumed(), 7);
+
+        // Overflowing reads do not advance
+        assert_eq!(
+            grant.read_bytes(&mut [0; 9]),
+            Err(BBQError::InsufficientSize)
+        );
+        assert_eq!(grant.read_u64_le(), Ok(0x0F0E_0D0C_0B0A_0908));
+        assert_eq!(grant.read_u8(), Err(BBQError::InsufficientSize));
+        assert_eq!(grant.consumed(), 15);
 
-        // Ask for something way too big
-        assert!(prod.grant_exact(10).is_err());
+        let consumed = grant.consumed();
+        grant.release(consumed);
+        assert!(cons.is_empty());
     }
 
     #[test]
@@ -268,8 +2872,8 @@ mod tests {
         rgrant.release();
         // 1 frame in queue
 
-        // No more room!
-        assert!(prod.grant(10).is_err());
+        // No more room, only 11 bytes are free!
+        assert!(prod.grant(11).is_err());
 
         let rgrant = cons.read().unwrap();
         assert_eq!(rgrant.len(), 10);
@@ -288,8 +2892,8 @@ mod tests {
         wgrant.commit(10);
         // 1 frame in queue
 
-        // No more room!
-        assert!(prod.grant(10).is_err());
+        // No more room, only 11 bytes are free!
+        assert!(prod.grant(11).is_err());
 
         let rgrant = cons.read().unwrap();
         assert_eq!(rgrant.len(), 10);
@@ -445,7 +3049,7 @@ mod tests {
             } else if second.len() == 1 {
                 assert_eq!(second[0], j);
             } else {
-                assert!(false, "wrong len");
+                panic!("wrong len");
             }
 
             #[cfg(feature = "extra-verbose")]
@@ -457,4 +3061,352 @@ mod tests {
             println!("FINSH: {:?}", bb);
         }
     }
+
+    #[test]
+    fn uninit_grant() {
+        use core::mem::MaybeUninit;
+
+        let bb: BBBuffer<6> = BBBuffer::new();
+        // Only initialized bytes are committed below
+        let (mut prod, mut cons) = unsafe { bb.try_split_uninit() }.unwrap();
+
+        let mut grant = prod.grant_exact(4).unwrap();
+        for (i, byte) in grant.as_uninit_mut().iter_mut().enumerate() {
+            *byte = MaybeUninit::new(i as u8);
+        }
+        // All bytes were written above
+        let buf = unsafe { grant.assume_init_slice() };
+        assert_eq!(buf, &[0, 1, 2, 3]);
+        grant.commit(4);
+
+        let grant = cons.read().unwrap();
+        assert_eq!(&*grant, &[0, 1, 2, 3]);
+    }
+
+    #[cfg(feature = "cortex-m")]
+    #[test]
+    fn wfe_helpers() {
+        static BB: BBBuffer<8> = BBBuffer::new();
+        let (mut prod, mut cons) = BB.try_split().unwrap();
+
+        assert_eq!(prod.grant_wfe(9).unwrap_err(), BBQError::InsufficientSize);
+
+        let consumer = std::thread::spawn(move || {
+            let mut next = 0u8;
+            loop {
+                match cons.read_wfe() {
+                    Ok(rgr) => {
+                        for byte in rgr.iter() {
+                            assert_eq!(*byte, next);
+                            next = next.wrapping_add(1);
+                        }
+                        let len = rgr.len();
+                        rgr.release(len);
+                    }
+                    Err(BBQError::Disconnected) => return next,
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+        });
+
+        // Each grant waits for the consumer to make room
+        let mut next = 0u8;
+        for _ in 0..1000 {
+            let mut wgr = prod.grant_wfe(3).unwrap();
+            for byte in wgr.iter_mut() {
+                *byte = next;
+                next = next.wrapping_add(1);
+            }
+            wgr.commit(3);
+        }
+        drop(prod);
+
+        assert_eq!(consumer.join().unwrap(), next);
+    }
+
+    #[test]
+    fn vec_deque_adapter() {
+        use bbqueue::std_compat::VecDequeAdapter;
+        use std::collections::VecDeque;
+
+        let bb: BBBuffer<8> = BBBuffer::new();
+        let mut queue = VecDequeAdapter::new(&bb).unwrap();
+        let mut model = VecDeque::new();
+
+        // Wrap around the end of the buffer many times
+        for i in 0..200u8 {
+            queue.push_back(i).unwrap();
+            model.push_back(i);
+            if i % 3 == 0 {
+                queue.push_back_slice(&[i, i]).unwrap();
+                model.extend([i, i]);
+            }
+            assert_eq!(queue.len(), model.len());
+
+            while queue.len() > 2 {
+                assert_eq!(queue.pop_front(), model.pop_front());
+            }
+        }
+
+        while let Some(byte) = model.pop_front() {
+            assert_eq!(queue.pop_front(), Some(byte));
+        }
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(
+            queue.push_back_slice(&[0; 9]),
+            Err(BBQError::InsufficientSize)
+        );
+    }
+
+    #[test]
+    fn fuzz_ops() {
+        // A fixed xorshift sequence, so that failures are reproducible
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut data = [0u8; 512];
+
+        for _ in 0..2_000 {
+            for byte in data.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state as u8;
+            }
+            bbqueue::fuzz::run(&data);
+        }
+    }
+
+    #[test]
+    fn error_ord_hash() {
+        use std::collections::HashMap;
+
+        let mut errors = vec![
+            BBQError::Poisoned,
+            BBQError::InsufficientSize,
+            BBQError::GrantInProgress,
+            BBQError::InsufficientSize,
+        ];
+
+        // Counted by variant
+        let mut counts = HashMap::new();
+        for err in errors.iter() {
+            *counts.entry(*err).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&BBQError::InsufficientSize], 2);
+        assert_eq!(counts.len(), 3);
+
+        // Sorted in the order of the variants
+        errors.sort();
+        assert_eq!(
+            errors,
+            [
+                BBQError::InsufficientSize,
+                BBQError::InsufficientSize,
+                BBQError::GrantInProgress,
+                BBQError::Poisoned,
+            ]
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let errors = [
+            (
+                BBQError::InsufficientSize,
+                "not enough space or data in the queue",
+            ),
+            (BBQError::GrantInProgress, "a grant is already in progress"),
+            (BBQError::AlreadySplit, "the queue has already been split"),
+            (
+                BBQError::InvalidFrame,
+                "the data in the queue could not be decoded",
+            ),
+            (
+                BBQError::NotContiguous,
+                "the requested bytes wrap around the end of the queue",
+            ),
+            (
+                BBQError::Disconnected,
+                "the other half of the queue has been closed",
+            ),
+            (
+                BBQError::FrameTooLarge,
+                "the frame is larger than the maximum frame size",
+            ),
+            (
+                BBQError::ChecksumMismatch,
+                "the checksum of the data does not match",
+            ),
+            (BBQError::Poisoned, "the queue was poisoned by a panic"),
+            (BBQError::Empty, "there is nothing to read"),
+            (
+                BBQError::StaleGrant,
+                "the grant no longer matches the queue",
+            ),
+        ];
+
+        for (err, msg) in errors.iter() {
+            assert_eq!(err.to_string(), *msg);
+
+            // Usable as a boxed error, e.g. with `?` in a function
+            // returning `Box<dyn Error>`
+            let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(*err);
+            assert_eq!(boxed.to_string(), *msg);
+            assert_eq!(boxed.downcast_ref::<BBQError>(), Some(err));
+        }
+    }
+
+    #[test]
+    fn error_into_io_error() {
+        use std::io::{Error as IoError, ErrorKind};
+
+        let kinds = [
+            (BBQError::InsufficientSize, ErrorKind::WouldBlock),
+            (BBQError::GrantInProgress, ErrorKind::Other),
+            (BBQError::AlreadySplit, ErrorKind::Other),
+            (BBQError::InvalidFrame, ErrorKind::InvalidData),
+            (BBQError::NotContiguous, ErrorKind::Other),
+            (BBQError::Disconnected, ErrorKind::BrokenPipe),
+            (BBQError::FrameTooLarge, ErrorKind::InvalidInput),
+            (BBQError::ChecksumMismatch, ErrorKind::InvalidData),
+            (BBQError::Poisoned, ErrorKind::Other),
+            (BBQError::Empty, ErrorKind::WouldBlock),
+            (BBQError::StaleGrant, ErrorKind::Other),
+        ];
+
+        for (err, kind) in kinds.iter() {
+            let io_err = IoError::from(*err);
+            assert_eq!(io_err.kind(), *kind);
+            assert_eq!(io_err.to_string(), err.to_string());
+
+            // The original error can be recovered
+            let inner = io_err.into_inner().unwrap();
+            assert_eq!(inner.downcast_ref::<BBQError>(), Some(err));
+        }
+    }
+
+    #[test]
+    fn scripted_ops() {
+        use bbqueue::fuzz::{apply_ops, Op};
+
+        // Wrap around, and read both parts at once
+        let bb: BBBuffer<8> = BBBuffer::new();
+        apply_ops(
+            &bb,
+            &[
+                Op::Grant(6),
+                Op::Commit(6),
+                Op::Read,
+                Op::Release(4),
+                Op::Grant(3),
+                Op::Commit(3),
+                Op::SplitRead,
+                Op::Release(5),
+                Op::Read,
+            ],
+        )
+        .unwrap();
+
+        // Steps without a grant, and sizes that can never be granted, are
+        // tolerated
+        let bb: BBBuffer<4> = BBBuffer::new();
+        apply_ops(
+            &bb,
+            &[
+                Op::Commit(1),
+                Op::Release(1),
+                Op::Grant(200),
+                Op::GrantMax(255),
+                Op::Grant(0),
+                Op::Read,
+                Op::Drop,
+                Op::SplitRead,
+            ],
+        )
+        .unwrap();
+        assert!(apply_ops(&bb, &[]).is_err());
+
+        // A fixed xorshift sequence, so that failures are reproducible
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+        for _ in 0..500 {
+            let ops: Vec<Op> = (0..200)
+                .map(|_| {
+                    let arg = next() % 11;
+                    match next() % 7 {
+                        0 => Op::Grant(arg),
+                        1 => Op::GrantMax(arg),
+                        2 => Op::Commit(arg),
+                        3 => Op::Read,
+                        4 => Op::Release(arg),
+                        5 => Op::Drop,
+                        _ => Op::SplitRead,
+                    }
+                })
+                .collect();
+
+            apply_ops(&BBBuffer::<3>::new(), &ops).unwrap();
+            apply_ops(&BBBuffer::<8>::new(), &ops).unwrap();
+        }
+    }
+
+    /// Fill a `BBBuffer<N>` completely, starting at every position, and in
+    /// chunks of every size, then drain it in chunks of every size
+    fn fill_completely<const N: usize>() {
+        for start in 0..N {
+            for fill in 1..=N {
+                for drain in 1..=N {
+                    let bb: BBBuffer<N> = BBBuffer::new();
+                    let (mut prod, mut cons) = bb.try_split().unwrap();
+
+                    // Move both positions to `start`
+                    if start > 0 {
+                        prod.grant_exact(start).unwrap().commit(start);
+                        cons.read().unwrap().release(start);
+                    }
+
+                    let mut next = 0u8;
+                    while let Ok(mut wgr) = prod.grant_max_remaining(fill) {
+                        for byte in wgr.iter_mut() {
+                            *byte = next;
+                            next += 1;
+                        }
+                        let len = wgr.len();
+                        wgr.commit(len);
+                    }
+                    assert_eq!(usize::from(next), N);
+                    assert_eq!(cons.bytes_available(), N);
+                    assert_eq!(prod.grant_exact(1).unwrap_err(), BBQError::InsufficientSize);
+
+                    let mut expected = 0u8;
+                    while let Ok(rgr) = cons.read() {
+                        let len = rgr.len().min(drain);
+                        for byte in &rgr[..len] {
+                            assert_eq!(*byte, expected);
+                            expected += 1;
+                        }
+                        rgr.release(len);
+                    }
+                    assert_eq!(expected, next);
+                    assert!(cons.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn full_capacity() {
+        fill_completely::<2>();
+        fill_completely::<3>();
+        fill_completely::<4>();
+        fill_completely::<5>();
+        fill_completely::<6>();
+        fill_completely::<7>();
+        fill_completely::<8>();
+    }
 }
@@ -0,0 +1,284 @@
+//! Model checked tests, run with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release -p bbqtest loom
+//! ```
+//!
+//! Each scenario is small enough for `loom` to explore every interleaving of
+//! the `Producer` and `Consumer`. Set `LOOM_MAX_PREEMPTIONS=2` to bound larger
+//! experiments.
+
+#[cfg(test)]
+mod tests {
+    use bbqueue::{BBBuffer, Error};
+    use loom::thread;
+
+    /// The number of bytes sent through the queue, enough to wrap around
+    /// the end of a `BBBuffer<4>` and invert
+    const SENT: u8 = 6;
+
+    /// Create a buffer that can be shared with a `loom` thread. Each
+    /// execution leaks one buffer, which only holds a few bytes.
+    fn leaked<const N: usize>() -> &'static BBBuffer<N> {
+        Box::leak(Box::new(BBBuffer::new()))
+    }
+
+    #[test]
+    fn loom_grant_exact() {
+        loom::model(|| {
+            let bb = leaked::<4>();
+            let (mut prod, mut cons) = bb.try_split().unwrap();
+
+            let producer = thread::spawn(move || {
+                let mut next = 0;
+                while next < SENT {
+                    match prod.grant_exact(2) {
+                        Ok(mut wgr) => {
+                            wgr[0] = next;
+                            wgr[1] = next + 1;
+                            wgr.commit(2);
+                            next += 2;
+                        }
+                        Err(Error::InsufficientSize) => thread::yield_now(),
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            });
+
+            let mut next = 0;
+            while next < SENT {
+                match cons.read() {
+                    Ok(rgr) => {
+                        for byte in rgr.iter() {
+                            assert_eq!(*byte, next);
+                            next += 1;
+                        }
+                        let len = rgr.len();
+                        rgr.release(len);
+                    }
+                    Err(Error::Empty) => thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn loom_grant_max_split_read() {
+        loom::model(|| {
+            let bb = leaked::<4>();
+            let (mut prod, mut cons) = bb.try_split().unwrap();
+
+            let producer = thread::spawn(move || {
+                let mut next = 0;
+                while next < SENT {
+                    match prod.grant_max_remaining(usize::from(SENT - next)) {
+                        Ok(mut wgr) => {
+                            for byte in wgr.iter_mut() {
+                                *byte = next;
+                                next += 1;
+                            }
+                            let len = wgr.len();
+                            wgr.commit(len);
+                        }
+                        Err(Error::InsufficientSize) => thread::yield_now(),
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            });
+
+            // Only release one byte at a time, so that reads race with the
+            // producer at every position of the buffer
+            let mut next = 0;
+            while next < SENT {
+                match cons.split_read() {
+                    Ok(rgr) => {
+                        let (first, second) = rgr.bufs();
+                        assert_eq!(first.iter().chain(second).next(), Some(&next));
+                        rgr.release(1);
+                        next += 1;
+                    }
+                    Err(Error::Empty) => thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn loom_compact() {
+        loom::model(|| {
+            let bb = leaked::<4>();
+            let (mut prod, mut cons) = bb.try_split().unwrap();
+
+            // Grants of three bytes often only fit once the empty queue is
+            // moved back to the start of the buffer
+            let producer = thread::spawn(move || {
+                let mut next = 0;
+                while next < SENT {
+                    match prod.grant_exact(3) {
+                        Ok(mut wgr) => {
+                            for byte in wgr.iter_mut() {
+                                *byte = next;
+                                next += 1;
+                            }
+                            wgr.commit(3);
+                        }
+                        Err(Error::InsufficientSize) => thread::yield_now(),
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            });
+
+            let mut next = 0;
+            while next < SENT {
+                match cons.read() {
+                    Ok(rgr) => {
+                        assert_eq!(rgr[0], next);
+                        rgr.release(1);
+                        next += 1;
+                    }
+                    Err(Error::Empty) => thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn loom_try_compact() {
+        loom::model(|| {
+            let bb = leaked::<4>();
+            let (mut prod, mut cons) = bb.try_split().unwrap();
+
+            // Move the queue away from the start of the buffer
+            let mut wgr = prod.grant_exact(3).unwrap();
+            wgr.copy_from_slice(&[0, 1, 2]);
+            wgr.commit(3);
+
+            // Compact while the consumer releases, or reads the empty queue,
+            // and fill the whole buffer right away
+            let producer = thread::spawn(move || {
+                let filled = prod.try_compact();
+                if filled {
+                    let mut wgr = prod.grant_exact(4).unwrap();
+                    wgr.copy_from_slice(&[3, 4, 5, 6]);
+                    wgr.commit(4);
+                }
+                (prod, filled)
+            });
+
+            let rgr = cons.read().unwrap();
+            assert_eq!(&*rgr, &[0, 1, 2]);
+            rgr.release(3);
+            let read = match cons.read() {
+                Ok(rgr) => {
+                    assert_eq!(&*rgr, &[3, 4, 5, 6]);
+                    rgr.release(4);
+                    true
+                }
+                Err(Error::Empty) => false,
+                Err(e) => panic!("{:?}", e),
+            };
+
+            // Otherwise, the whole buffer can be granted now
+            let (mut prod, filled) = producer.join().unwrap();
+            if !filled {
+                assert!(prod.try_compact());
+                let mut wgr = prod.grant_exact(4).unwrap();
+                wgr.copy_from_slice(&[3, 4, 5, 6]);
+                wgr.commit(4);
+            }
+            if !read {
+                let rgr = cons.read().unwrap();
+                assert_eq!(&*rgr, &[3, 4, 5, 6]);
+                rgr.release(4);
+            }
+            assert!(cons.is_empty());
+        });
+    }
+
+    #[test]
+    fn loom_ping_pong() {
+        use bbqueue::pingpong::PingPongBuffer;
+
+        loom::model(|| {
+            let pp: &'static PingPongBuffer<2> = Box::leak(Box::new(PingPongBuffer::new()));
+            let (mut prod, mut cons) = pp.try_split().unwrap();
+
+            // Enough fills for both buffers to be swapped back and forth
+            let producer = thread::spawn(move || {
+                let mut next = 0;
+                while next < 3 {
+                    match prod.begin_fill() {
+                        Ok(mut wgr) => {
+                            wgr[0] = next;
+                            wgr.commit(1);
+                            next += 1;
+                        }
+                        Err(Error::InsufficientSize) => thread::yield_now(),
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            });
+
+            let mut next = 0;
+            while next < 3 {
+                match cons.begin_drain() {
+                    Ok(rgr) => {
+                        assert_eq!(&*rgr, &[next]);
+                        rgr.release(1);
+                        next += 1;
+                    }
+                    Err(Error::Empty) => thread::yield_now(),
+                    Err(e) => panic!("{:?}", e),
+                }
+            }
+
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn loom_framed() {
+        loom::model(|| {
+            let bb = leaked::<8>();
+            let (mut prod, mut cons) = bb.try_split_framed_with::<u8>().unwrap();
+
+            let producer = thread::spawn(move || {
+                let mut next = 0;
+                while next < 3 {
+                    match prod.grant(2) {
+                        Ok(mut wgr) => {
+                            wgr[0] = next;
+                            wgr.commit(1);
+                            next += 1;
+                        }
+                        Err(Error::InsufficientSize) => thread::yield_now(),
+                        Err(e) => panic!("{:?}", e),
+                    }
+                }
+            });
+
+            let mut next = 0;
+            while next < 3 {
+                match cons.read() {
+                    Some(frame) => {
+                        assert_eq!(&*frame, &[next]);
+                        frame.release();
+                        next += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+
+            producer.join().unwrap();
+        });
+    }
+}
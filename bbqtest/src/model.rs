@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use bbqueue::{test_utils::Differential, BBBuffer};
+    use proptest::prelude::*;
+
+    /// The largest size of a tested buffer. Sizes up to this are small enough
+    /// to wrap around often
+    const MAX_N: usize = 16;
+
+    #[derive(Debug, Clone, Copy)]
+    enum Op {
+        GrantExact(usize),
+        GrantMax(usize),
+        Commit(usize),
+        Read,
+        Release(usize),
+        /// Drop both grants, without committing or releasing
+        Drop,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        // Sizes include zero, and sizes that can never be granted
+        prop_oneof![
+            (0..MAX_N + 2).prop_map(Op::GrantExact),
+            (0..MAX_N + 2).prop_map(Op::GrantMax),
+            (0..MAX_N + 2).prop_map(Op::Commit),
+            Just(Op::Read),
+            (0..MAX_N + 2).prop_map(Op::Release),
+            Just(Op::Drop),
+        ]
+    }
+
+    /// Run `ops` on both a `BBBuffer<N>` and the reference model, which
+    /// panics as soon as they disagree
+    fn check<const N: usize>(ops: &[Op]) {
+        let bb: BBBuffer<N> = BBBuffer::new();
+        let mut queue = Differential::new(&bb).unwrap();
+
+        for op in ops {
+            // Scale the sizes to the buffer, keeping those just too large
+            let scale = |sz: usize| sz % (N + 2);
+            match *op {
+                Op::GrantExact(sz) => {
+                    let _ = queue.grant_exact(scale(sz));
+                }
+                Op::GrantMax(sz) => {
+                    let _ = queue.grant_max_remaining(scale(sz));
+                }
+                Op::Commit(used) => queue.commit(scale(used)),
+                Op::Read => {
+                    let _ = queue.read();
+                }
+                Op::Release(used) => queue.release(scale(used)),
+                Op::Drop => queue.drop_grants(),
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2_000))]
+
+        #[test]
+        fn model_matches(ops in prop::collection::vec(op(), 0..200)) {
+            check::<1>(&ops);
+            check::<2>(&ops);
+            check::<3>(&ops);
+            check::<4>(&ops);
+            check::<5>(&ops);
+            check::<7>(&ops);
+            check::<8>(&ops);
+            check::<MAX_N>(&ops);
+        }
+    }
+}
@@ -52,8 +52,8 @@ mod tests {
         let (mut tx, mut rx) = BB.try_split().unwrap();
 
         let mut last_tx = Instant::now();
-        let mut last_rx = last_tx.clone();
-        let start_time = last_tx.clone();
+        let mut last_rx = last_tx;
+        let start_time = last_tx;
 
         let tx_thr = spawn(move || {
             let mut txd_ct = 0;
@@ -96,14 +96,14 @@ mod tests {
             let mut rxd_ct = 0;
             let mut rxd_ivl = 0;
 
-            for (_idx, i) in data_rx.drain(..).enumerate() {
+            for i in data_rx.drain(..) {
                 'inner: loop {
                     if last_rx.elapsed() > TIMEOUT_NODATA {
                         panic!("rx timeout, iter {}", i);
                     }
                     let gr = match rx.read() {
                         Ok(gr) => gr,
-                        Err(Error::InsufficientSize) => continue 'inner,
+                        Err(Error::Empty) => continue 'inner,
                         Err(_) => panic!(),
                     };
 
@@ -144,8 +144,8 @@ mod tests {
         let (mut tx, mut rx) = BB.try_split().unwrap();
 
         let mut last_tx = Instant::now();
-        let mut last_rx = last_tx.clone();
-        let start_time = last_tx.clone();
+        let mut last_rx = last_tx;
+        let start_time = last_tx;
 
         let tx_thr = spawn(move || {
             let mut txd_ct = 0;
@@ -156,23 +156,20 @@ mod tests {
                     if last_tx.elapsed() > TIMEOUT_NODATA {
                         panic!("tx timeout, iter {}", i);
                     }
-                    match tx.grant_exact(1) {
-                        Ok(mut gr) => {
-                            gr[0] = (i & 0xFF) as u8;
-                            gr.commit(1);
-
-                            // Update tracking
-                            last_tx = Instant::now();
-                            txd_ct += 1;
-                            if (txd_ct / RPT_IVAL) > txd_ivl {
-                                txd_ivl = txd_ct / RPT_IVAL;
-                                #[cfg(feature = "verbose")]
-                                println!("{:?} - sctx: {}", start_time.elapsed(), txd_ct);
-                            }
-
-                            break 'inner;
+                    if let Ok(mut gr) = tx.grant_exact(1) {
+                        gr[0] = (i & 0xFF) as u8;
+                        gr.commit(1);
+
+                        // Update tracking
+                        last_tx = Instant::now();
+                        txd_ct += 1;
+                        if (txd_ct / RPT_IVAL) > txd_ivl {
+                            txd_ivl = txd_ct / RPT_IVAL;
+                            #[cfg(feature = "verbose")]
+                            println!("{:?} - sctx: {}", start_time.elapsed(), txd_ct);
                         }
-                        Err(_) => {}
+
+                        break 'inner;
                     }
                 }
             }
@@ -191,7 +188,7 @@ mod tests {
 
                 let gr = match rx.read() {
                     Ok(gr) => gr,
-                    Err(Error::InsufficientSize) => continue,
+                    Err(Error::Empty) => continue,
                     Err(_) => panic!(),
                 };
 
@@ -250,8 +247,8 @@ mod tests {
         println!("SCGM: Starting Test...");
 
         let mut last_tx = Instant::now();
-        let mut last_rx = last_tx.clone();
-        let start_time = last_tx.clone();
+        let mut last_rx = last_tx;
+        let start_time = last_tx;
 
         let tx_thr = spawn(move || {
             let mut txd_ct = 0;
@@ -264,29 +261,26 @@ mod tests {
                     if last_tx.elapsed() > TIMEOUT_NODATA {
                         panic!("tx timeout");
                     }
-                    match tx.grant_max_remaining(
+                    if let Ok(mut gr) = tx.grant_max_remaining(
                         trng.gen_range((QUEUE_SIZE / 3)..((2 * QUEUE_SIZE) / 3)),
                     ) {
-                        Ok(mut gr) => {
-                            let sz = ::std::cmp::min(data_tx.len(), gr.len());
-                            for i in 0..sz {
-                                gr[i] = data_tx.pop().unwrap();
-                            }
-
-                            // Update tracking
-                            last_tx = Instant::now();
-                            txd_ct += sz;
-                            if (txd_ct / RPT_IVAL) > txd_ivl {
-                                txd_ivl = txd_ct / RPT_IVAL;
-                                #[cfg(feature = "verbose")]
-                                println!("{:?} - scgmtx: {}", start_time.elapsed(), txd_ct);
-                            }
+                        let sz = ::std::cmp::min(data_tx.len(), gr.len());
+                        for i in 0..sz {
+                            gr[i] = data_tx.pop().unwrap();
+                        }
 
-                            let len = gr.len();
-                            gr.commit(len);
-                            break 'inner;
+                        // Update tracking
+                        last_tx = Instant::now();
+                        txd_ct += sz;
+                        if (txd_ct / RPT_IVAL) > txd_ivl {
+                            txd_ivl = txd_ct / RPT_IVAL;
+                            #[cfg(feature = "verbose")]
+                            println!("{:?} - scgmtx: {}", start_time.elapsed(), txd_ct);
                         }
-                        Err(_) => {}
+
+                        let len = gr.len();
+                        gr.commit(len);
+                        break 'inner;
                     }
                 }
             }
@@ -303,7 +297,7 @@ mod tests {
                     }
                     let gr = match rx.read() {
                         Ok(gr) => gr,
-                        Err(Error::InsufficientSize) => continue 'inner,
+                        Err(Error::Empty) => continue 'inner,
                         Err(_) => panic!(),
                     };
 
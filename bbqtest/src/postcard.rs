@@ -0,0 +1,65 @@
+#[cfg(all(test, feature = "postcard"))]
+mod tests {
+    use bbqueue::{BBBuffer, Error};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Message {
+        Ping,
+        Text(String),
+        Reading { id: u8, value: i32 },
+    }
+
+    #[test]
+    fn postcard_roundtrip() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        let messages = [
+            Message::Ping,
+            Message::Text("hello".into()),
+            Message::Reading { id: 3, value: -1 },
+        ];
+
+        for _ in 0..10 {
+            for msg in messages.iter() {
+                prod.send(msg).unwrap();
+            }
+            for msg in messages.iter() {
+                assert_eq!(&cons.recv::<Message>().unwrap(), msg);
+            }
+            assert_eq!(cons.recv::<Message>(), Err(Error::Empty));
+        }
+
+        // Plain values and strings
+        prod.send("text").unwrap();
+        prod.send(&0x1234_5678u32).unwrap();
+        assert_eq!(cons.recv::<String>().unwrap(), "text");
+        assert_eq!(cons.recv::<u32>().unwrap(), 0x1234_5678);
+    }
+
+    #[test]
+    fn postcard_errors() {
+        let bb: BBBuffer<32> = BBBuffer::new();
+        let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+
+        // Too large for the buffer, nothing is committed
+        let long = Message::Text("x".repeat(40));
+        assert_eq!(prod.send(&long), Err(Error::FrameTooLarge));
+
+        // Too large for the remaining space
+        let medium = Message::Text("x".repeat(14));
+        prod.send(&medium).unwrap();
+        assert_eq!(prod.send(&medium), Err(Error::InsufficientSize));
+        assert_eq!(cons.recv::<Message>().unwrap(), medium);
+        assert_eq!(cons.recv::<Message>(), Err(Error::Empty));
+
+        // A frame that does not decode is discarded
+        let mut wgr = prod.grant(1).unwrap();
+        wgr[0] = 0xFF;
+        wgr.commit(1);
+        prod.send(&Message::Ping).unwrap();
+        assert_eq!(cons.recv::<String>(), Err(Error::InvalidFrame));
+        assert_eq!(cons.recv::<Message>().unwrap(), Message::Ping);
+    }
+}
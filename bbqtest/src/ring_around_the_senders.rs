@@ -116,7 +116,7 @@ mod tests {
                         if let Potato::Tx(tx) = new {
                             count -= 1;
 
-                            if (count % 100) == 0 {
+                            if count.is_multiple_of(100) {
                                 println!("count left: {}", count);
                             }
 
@@ -134,17 +134,9 @@ mod tests {
                 }
                 let (new_me, send) = me.work();
 
-                let we_done = if let Potato::Done = &send {
-                    true
-                } else {
-                    false
-                };
+                let we_done = matches!(send, Potato::Done);
 
-                let nop = if let Potato::Idle = &send {
-                    true
-                } else {
-                    false
-                };
+                let nop = matches!(send, Potato::Idle);
 
                 if !nop {
                     tx_1_2.send(send).unwrap();
@@ -178,17 +170,9 @@ mod tests {
                     }
                     let (new_me, send) = me.work();
 
-                    let we_done = if let Potato::Done = &send {
-                        true
-                    } else {
-                        false
-                    };
+                    let we_done = matches!(send, Potato::Done);
 
-                    let nop = if let Potato::Idle = &send {
-                        true
-                    } else {
-                        false
-                    };
+                    let nop = matches!(send, Potato::Idle);
 
                     if !nop {
                         tx.send(send).ok();
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use bbqueue::{
+        slip::{SlipDecoder, SlipProducer},
+        BBBuffer, Error,
+    };
+
+    #[test]
+    fn slip_sanity() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = SlipProducer::new(prod);
+        let mut cons: SlipDecoder<64, 32> = SlipDecoder::new(cons);
+
+        // Nothing to receive yet
+        assert_eq!(cons.recv(), Err(Error::Empty));
+
+        // Empty messages are skipped
+        prod.send(&[]).unwrap();
+        prod.send(&[0xC0]).unwrap();
+        prod.send(&[0xDB, 0xDC, 0xDD]).unwrap();
+        prod.send(&[1, 0xC0, 0xC0, 0xDB, 2]).unwrap();
+
+        assert_eq!(cons.recv().unwrap(), &[0xC0]);
+        assert_eq!(cons.recv().unwrap(), &[0xDB, 0xDC, 0xDD]);
+        assert_eq!(cons.recv().unwrap(), &[1, 0xC0, 0xC0, 0xDB, 2]);
+        assert_eq!(cons.recv(), Err(Error::Empty));
+
+        // The raw encoding is what ends up in the queue
+        let mut cons = cons.into_inner();
+        prod.send(&[0x11, 0xC0, 0x22, 0xDB]).unwrap();
+        let rgr = cons.read().unwrap();
+        assert_eq!(&*rgr, &[0xC0, 0x11, 0xDB, 0xDC, 0x22, 0xDB, 0xDD, 0xC0]);
+        rgr.release(8);
+    }
+
+    #[test]
+    fn slip_worst_case() {
+        let bb: BBBuffer<4096> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = SlipProducer::new(prod);
+        let mut cons: SlipDecoder<4096, 600> = SlipDecoder::new(cons);
+
+        let ends = vec![0xC0u8; 500];
+        let mixed: Vec<u8> = (0..600).map(|i| [0xC0, 0xDB, i as u8][i % 3]).collect();
+
+        for _ in 0..4 {
+            for data in [&ends[..], &mixed[..]] {
+                prod.send(data).unwrap();
+                assert_eq!(cons.recv().unwrap(), data);
+
+                // A larger grant than needed
+                let mut wgr = prod.grant(data.len() + 50).unwrap();
+                wgr[..data.len()].copy_from_slice(data);
+                wgr.commit(data.len());
+                assert_eq!(cons.recv().unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn slip_resync() {
+        let bb: BBBuffer<64> = BBBuffer::new();
+        let (mut prod, cons) = bb.try_split().unwrap();
+        let mut cons: SlipDecoder<64, 4> = SlipDecoder::new(cons);
+
+        // Line noise, an invalid escape sequence, a message too large for
+        // the scratch buffer, and a valid message
+        let mut wgr = prod.grant_exact(20).unwrap();
+        wgr.copy_from_slice(&[
+            0x55, 0xAA, 0xC0, 0xC0, 0x01, 0xDB, 0x02, 0xC0, 1, 2, 3, 4, 5, 0xC0, 0xC0, 9, 0xDB,
+            0xDC, 8, 0xC0,
+        ]);
+        wgr.commit(20);
+
+        assert_eq!(cons.recv().unwrap(), &[0x55, 0xAA]);
+        assert_eq!(cons.recv(), Err(Error::InvalidFrame));
+        assert_eq!(cons.recv(), Err(Error::InsufficientSize));
+        assert_eq!(cons.recv().unwrap(), &[9, 0xC0, 8]);
+        assert_eq!(cons.recv(), Err(Error::Empty));
+
+        // A truncated message is not consumed until it is terminated
+        let mut wgr = prod.grant_exact(3).unwrap();
+        wgr.copy_from_slice(&[0x07, 0xDB, 0xDD]);
+        wgr.commit(3);
+        assert_eq!(cons.recv(), Err(Error::Empty));
+    }
+
+    #[test]
+    fn slip_recv_raw() {
+        let bb: BBBuffer<16> = BBBuffer::new();
+        let (prod, cons) = bb.try_split().unwrap();
+        let mut prod = SlipProducer::new(prod);
+        let mut cons: SlipDecoder<16, 16> = SlipDecoder::new(cons);
+        assert_eq!(cons.recv_raw().unwrap_err(), Error::Empty);
+
+        prod.send(&[0x11, 0xC0]).unwrap();
+        let rgr = cons.recv_raw().unwrap();
+        assert_eq!(&*rgr, &[0xC0, 0x11, 0xDB, 0xDC, 0xC0]);
+        rgr.release(5);
+        assert_eq!(cons.recv_raw().unwrap_err(), Error::Empty);
+    }
+}
@@ -0,0 +1,48 @@
+//! A build test for targets without native atomics, such as
+//! `riscv32i-unknown-none-elf`, using the `portable-atomic` feature
+//!
+//! Build with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg portable_atomic_unsafe_assume_single_core" \
+//!     cargo build --manifest-path ci/no-atomic/Cargo.toml --target riscv32i-unknown-none-elf
+//! ```
+
+#![no_std]
+
+use bbqueue::BBBuffer;
+
+static BB: BBBuffer<64> = BBBuffer::new();
+
+/// Pass `rounds` frames of increasing size through the queue, returning
+/// whether all of them were received intact
+pub fn roundtrip(rounds: usize) -> bool {
+    let (mut prod, mut cons) = match BB.try_split() {
+        Ok(halves) => halves,
+        Err(_) => return false,
+    };
+
+    for round in 0..rounds {
+        let sz = round % 16 + 1;
+
+        let mut wgr = match prod.grant_exact(sz) {
+            Ok(wgr) => wgr,
+            Err(_) => return false,
+        };
+        for byte in wgr.iter_mut() {
+            *byte = round as u8;
+        }
+        wgr.commit(sz);
+
+        let rgr = match cons.read() {
+            Ok(rgr) => rgr,
+            Err(_) => return false,
+        };
+        if rgr.len() != sz || rgr.iter().any(|byte| *byte != round as u8) {
+            return false;
+        }
+        rgr.release(sz);
+    }
+
+    BB.try_release(prod, cons).is_ok()
+}
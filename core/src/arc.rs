@@ -0,0 +1,126 @@
+//! A reference counted flavor of BBQueue, for `std` environments
+//!
+//! The `Producer` and `Consumer` borrow the `BBBuffer` they were split from,
+//! which requires the buffer to outlive both halves, e.g. by placing it in a
+//! `static`. When that is not convenient, the buffer can instead be placed
+//! in an `Arc`, and split into an `ArcProducer` and `ArcConsumer`, which each
+//! keep the buffer alive for as long as they exist.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use bbqueue::{arc::try_split_arc, BBBuffer};
+//! use std::{sync::Arc, thread::spawn};
+//!
+//! let bb: Arc<BBBuffer<64>> = Arc::new(BBBuffer::new());
+//! let (mut prod, mut cons) = try_split_arc(&bb).unwrap();
+//!
+//! spawn(move || {
+//!     let mut grant = prod.grant_exact(4).unwrap();
+//!     grant.copy_from_slice(&[1, 2, 3, 4]);
+//!     grant.commit(4);
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! let grant = cons.read().unwrap();
+//! assert_eq!(&*grant, &[1, 2, 3, 4]);
+//! ```
+//!
+//! A buffer that is owned by value, e.g. one created on the stack, can also
+//! be moved into an `Arc` and split in one step with `BBBuffer::into_split()`:
+//!
+//! ```rust
+//! use bbqueue::BBBuffer;
+//!
+//! let bb: BBBuffer<64> = BBBuffer::new();
+//! let (mut prod, mut cons) = bb.into_split().unwrap();
+//!
+//! prod.grant_exact(1).unwrap().commit(1);
+//! assert_eq!(cons.read().unwrap().len(), 1);
+//! ```
+//!
+//! Only the basic grant methods are available on the `Arc` halves, as grants
+//! must never outlive the half they were obtained from.
+
+use crate::{BBBuffer, Consumer, GrantR, GrantW, Producer, Result, SplitGrantR};
+use std::sync::Arc;
+
+/// Split a reference counted `BBBuffer` into an `ArcProducer` and `ArcConsumer`
+///
+/// As with `BBBuffer::try_split()`, this can only be done once, and an error
+/// is returned if the buffer has already been split.
+pub fn try_split_arc<const N: usize>(
+    bbq: &Arc<BBBuffer<N>>,
+) -> Result<(ArcProducer<N>, ArcConsumer<N>)> {
+    // This is sound, as both halves hold a clone of the `Arc`, and drop
+    // their `Producer` or `Consumer` before it. Grants are only handed out
+    // with the lifetime of the half they were obtained from.
+    let buffer: &'static BBBuffer<N> = unsafe { &*Arc::as_ptr(bbq) };
+    let (producer, consumer) = buffer.try_split()?;
+
+    Ok((
+        ArcProducer {
+            producer,
+            _bbq: bbq.clone(),
+        },
+        ArcConsumer {
+            consumer,
+            _bbq: bbq.clone(),
+        },
+    ))
+}
+
+impl<const N: usize> BBBuffer<N> {
+    /// Move the buffer into an `Arc`, and split it into an `OwnedProducer` and
+    /// `OwnedConsumer`, which do not borrow from the caller
+    ///
+    /// An error is returned if the buffer was split before, and never released.
+    pub fn into_split(self) -> Result<(OwnedProducer<N>, OwnedConsumer<N>)> {
+        try_split_arc(&Arc::new(self))
+    }
+}
+
+/// The `Producer` returned by `BBBuffer::into_split()`
+pub type OwnedProducer<const N: usize> = ArcProducer<N>;
+
+/// The `Consumer` returned by `BBBuffer::into_split()`
+pub type OwnedConsumer<const N: usize> = ArcConsumer<N>;
+
+/// A `Producer` which keeps its `BBBuffer` alive
+pub struct ArcProducer<const N: usize> {
+    // NOTE: Must be declared before `_bbq`, so it is dropped first
+    producer: Producer<'static, N>,
+    _bbq: Arc<BBBuffer<N>>,
+}
+
+impl<const N: usize> ArcProducer<N> {
+    /// See `Producer::grant_exact()`
+    pub fn grant_exact(&mut self, sz: usize) -> Result<GrantW<'_, N>> {
+        self.producer.grant_exact(sz)
+    }
+
+    /// See `Producer::grant_max_remaining()`
+    pub fn grant_max_remaining(&mut self, sz: usize) -> Result<GrantW<'_, N>> {
+        self.producer.grant_max_remaining(sz)
+    }
+}
+
+/// A `Consumer` which keeps its `BBBuffer` alive
+pub struct ArcConsumer<const N: usize> {
+    // NOTE: Must be declared before `_bbq`, so it is dropped first
+    consumer: Consumer<'static, N>,
+    _bbq: Arc<BBBuffer<N>>,
+}
+
+impl<const N: usize> ArcConsumer<N> {
+    /// See `Consumer::read()`
+    pub fn read(&mut self) -> Result<GrantR<'_, N>> {
+        self.consumer.read()
+    }
+
+    /// See `Consumer::split_read()`
+    pub fn split_read(&mut self) -> Result<SplitGrantR<'_, N>> {
+        self.consumer.split_read()
+    }
+}
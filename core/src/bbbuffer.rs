@@ -1,31 +1,74 @@
+#[cfg(feature = "subscribe")]
+use crate::sync::{fence, UnsafeCell};
+#[cfg(feature = "stats")]
+use crate::Cursor;
 use crate::{
-    framed::{FrameConsumer, FrameProducer},
-    Error, Result,
+    crc::crc32,
+    event,
+    framed::{FrameConsumer, FrameHeader, FrameProducer},
+    sync::{AtomicBool, AtomicUsize},
+    Error, ReadCursor, Result, WriteCursor,
 };
 use core::{
-    cell::UnsafeCell,
     cmp::min,
+    fmt,
     marker::PhantomData,
     mem::{forget, transmute, MaybeUninit},
     ops::{Deref, DerefMut},
     ptr::NonNull,
     result::Result as CoreResult,
-    slice::from_raw_parts_mut,
-    sync::atomic::{
-        AtomicBool, AtomicUsize,
-        Ordering::{AcqRel, Acquire, Release},
-    },
+    slice::{from_raw_parts, from_raw_parts_mut},
+    sync::atomic::Ordering::{AcqRel, Acquire, Release},
 };
+#[cfg(feature = "subscribe")]
+use core::{sync::atomic::Ordering::SeqCst, task::Waker};
+#[cfg(any(feature = "watermarks", feature = "embedded_hal_1"))]
+use {crate::sync::AtomicPtr, core::ptr::null_mut};
+
+/// The highest bit of `write`, `read` and `reserve` is a lap flag, which is
+/// flipped each time the index wraps around to the start of the buffer.
+///
+/// The `Producer` is at most one lap ahead of the `Consumer`, so the buffer is
+/// inverted exactly when the flags of `write` and `read` differ. This keeps a
+/// full buffer, where `write` and `read` are at the same position, distinct
+/// from an empty one, so all `N` bytes of the buffer can be used.
+const LAP: usize = !(usize::MAX >> 1);
+
+/// The position in the buffer of an index, without its lap flag
+#[inline(always)]
+const fn position(index: usize) -> usize {
+    index & !LAP
+}
+
+/// Is the `Producer` a lap ahead of the `Consumer`?
+#[inline(always)]
+const fn is_inverted(write: usize, read: usize) -> bool {
+    (write ^ read) & LAP != 0
+}
+
 #[derive(Debug)]
 /// A backing structure for a BBQueue. Can be used to create either
 /// a BBQueue or a split Producer/Consumer pair
+///
+/// A `BBBuffer` is `Send` and `Sync`, so it can be placed in a `static`, and
+/// split through a shared reference. The `Producer` and `Consumer` are
+/// `Send`, so each can be moved to its own thread, but neither is `Sync`:
+///
+/// ```rust,compile_fail
+/// use bbqueue::{BBBuffer, Producer};
+///
+/// fn assert_sync<T: Sync>() {}
+///
+/// // ERROR: `Producer` can not be shared between threads
+/// assert_sync::<Producer<'static, 8>>();
+/// ```
 pub struct BBBuffer<const N: usize> {
-    buf: UnsafeCell<MaybeUninit<[u8; N]>>,
+    buf: core::cell::UnsafeCell<MaybeUninit<[u8; N]>>,
 
-    /// Where the next byte will be written
+    /// Where the next byte will be written, with the lap flag of the writer
     write: AtomicUsize,
 
-    /// Where the next byte will be read from
+    /// Where the next byte will be read from, with the lap flag of the reader
     read: AtomicUsize,
 
     /// Used in the inverted case to mark the end of the
@@ -38,7 +81,8 @@ pub struct BBBuffer<const N: usize> {
 
     /// Used by the Writer to remember what bytes are currently
     /// allowed to be written to, but are not yet ready to be
-    /// read from
+    /// read from. Carries the lap flag `write` will have once
+    /// the grant is committed
     reserve: AtomicUsize,
 
     /// Is there an active read grant?
@@ -49,8 +93,73 @@ pub struct BBBuffer<const N: usize> {
 
     /// Have we already split?
     already_split: AtomicBool,
+
+    /// Total number of bytes committed since the buffer was split
+    #[cfg(feature = "stats")]
+    write_total: Total,
+
+    /// Total number of bytes released since the buffer was split
+    #[cfg(feature = "stats")]
+    read_total: Total,
+
+    /// Number of bytes the `Producer` dropped for lack of space, counted by
+    /// failed grants and `Producer::record_lost()`
+    #[cfg(feature = "stats")]
+    bytes_lost: AtomicUsize,
+
+    /// Threshold for the high watermark callback
+    #[cfg(feature = "watermarks")]
+    high_watermark: AtomicUsize,
+
+    /// The high watermark callback, a `fn()`, or null if not registered
+    #[cfg(feature = "watermarks")]
+    high_watermark_cb: AtomicPtr<()>,
+
+    /// Threshold for the low watermark callback
+    #[cfg(feature = "watermarks")]
+    low_watermark: AtomicUsize,
+
+    /// The low watermark callback, a `fn()`, or null if not registered
+    #[cfg(feature = "watermarks")]
+    low_watermark_cb: AtomicPtr<()>,
+
+    /// The smoothed fill level of `fill_level_ewma()`, as a fixed point
+    /// fraction of `FILL_ONE`
+    #[cfg(feature = "stats")]
+    fill_ewma: AtomicUsize,
+
+    /// Tracks the parts of a read grant split with `GrantR::split_at()`.
+    /// See `GrantRPart` for the encoding.
+    #[cfg(feature = "split_grants")]
+    split_state: AtomicUsize,
+
+    /// Has the `Producer` been closed or dropped?
+    producer_closed: AtomicBool,
+
+    /// Has the `Consumer` been closed or dropped?
+    consumer_closed: AtomicBool,
+
+    /// Was a grant committed or released while panicking?
+    poisoned: AtomicBool,
+
+    /// Counts the calls to `try_release()`. Every grant records it, so that a
+    /// grant of an earlier split can never commit or release into this one.
+    generation: AtomicUsize,
+
+    /// The waker registered with `Consumer::subscribe()`
+    #[cfg(feature = "subscribe")]
+    subscriber: Subscriber,
+
+    /// The pin registered with `Producer::set_data_ready_pin()`
+    #[cfg(feature = "embedded_hal_1")]
+    data_ready: DataReady,
 }
 
+// SAFETY: The bytes of the buffer are only accessed through the `Producer`,
+// the `Consumer` and their grants, of which there is at most one of each, and
+// the indices and flags that hand bytes from one to the other are atomics.
+// Splitting is guarded by `already_split`, so a shared reference, such as one
+// to a `static`, can not be used to create a second pair of halves.
 unsafe impl<const A: usize> Sync for BBBuffer<A> {}
 
 impl<'a, const N: usize> BBBuffer<N> {
@@ -63,8 +172,8 @@ impl<'a, const N: usize> BBBuffer<N> {
     /// is placed at `static` scope within the `.bss` region, the explicit initialization
     /// will be elided (as it is already performed as part of memory initialization)
     ///
-    /// NOTE:  If the `thumbv6` feature is selected, this function takes a short critical section
-    /// while splitting.
+    /// NOTE:  If the `thumbv6` or `critical-section` feature is selected, this function takes a
+    /// short critical section while splitting.
     ///
     /// ```rust
     /// # // bbqueue test shim!
@@ -86,6 +195,27 @@ impl<'a, const N: usize> BBBuffer<N> {
     /// # }
     /// ```
     pub fn try_split(&'a self) -> Result<(Producer<'a, N>, Consumer<'a, N>)> {
+        // This is sound, as the buffer is zeroed
+        unsafe { self.split(true) }
+    }
+
+    /// Attempt to split the `BBBuffer` into `Consumer` and `Producer` halves,
+    /// without zeroing the underlying buffer first, as `try_split()` does.
+    ///
+    /// Together with placing a static `BBBuffer` in a section that is not
+    /// cleared at startup, this avoids initializing the buffer at all, which
+    /// may be slow for large buffers on some systems.
+    ///
+    /// # Safety
+    ///
+    /// Until every byte of the buffer has been written once, the contents of
+    /// write grants must only be accessed through `GrantW::as_uninit_mut()`,
+    /// and only initialized bytes may be committed.
+    pub unsafe fn try_split_uninit(&'a self) -> Result<(Producer<'a, N>, Consumer<'a, N>)> {
+        self.split(false)
+    }
+
+    unsafe fn split(&'a self, zero: bool) -> Result<(Producer<'a, N>, Consumer<'a, N>)> {
         if atomic::swap(&self.already_split, true, AcqRel) {
             return Err(Error::AlreadySplit);
         }
@@ -94,8 +224,10 @@ impl<'a, const N: usize> BBBuffer<N> {
             // Explicitly zero the data to avoid undefined behavior.
             // This is required, because we hand out references to the buffers,
             // which mean that creating them as references is technically UB for now
-            let mu_ptr = self.buf.get();
-            (*mu_ptr).as_mut_ptr().write_bytes(0u8, 1);
+            if zero {
+                let mu_ptr = self.buf.get();
+                (*mu_ptr).as_mut_ptr().write_bytes(0u8, 1);
+            }
 
             let nn1 = NonNull::new_unchecked(self as *const _ as *mut _);
             let nn2 = NonNull::new_unchecked(self as *const _ as *mut _);
@@ -104,10 +236,14 @@ impl<'a, const N: usize> BBBuffer<N> {
                 Producer {
                     bbq: nn1,
                     pd: PhantomData,
+                    #[cfg(feature = "std")]
+                    poll_interval: std::time::Duration::from_millis(1),
                 },
                 Consumer {
                     bbq: nn2,
                     pd: PhantomData,
+                    pending_header: None,
+                    filled: None,
                 },
             ))
         }
@@ -123,17 +259,99 @@ impl<'a, const N: usize> BBBuffer<N> {
     /// is placed at `static` scope within the `.bss` region, the explicit initialization
     /// will be elided (as it is already performed as part of memory initialization)
     ///
-    /// NOTE:  If the `thumbv6` feature is selected, this function takes a short critical
-    /// section while splitting.
+    /// NOTE:  If the `thumbv6` or `critical-section` feature is selected, this function takes a
+    /// short critical section while splitting.
     pub fn try_split_framed(&'a self) -> Result<(FrameProducer<'a, N>, FrameConsumer<'a, N>)> {
+        self.try_split_framed_with()
+    }
+
+    /// Attempt to split the `BBBuffer` into `FrameConsumer` and `FrameProducer` halves,
+    /// using the frame header `H`, such as a fixed size `u16` header. If buffer has
+    /// already been split, an error will be returned.
+    ///
+    /// See `try_split_framed()` for details, and the `framed` module for the
+    /// available frame headers.
+    pub fn try_split_framed_with<H: FrameHeader>(
+        &'a self,
+    ) -> Result<(FrameProducer<'a, N, H>, FrameConsumer<'a, N, H>)> {
+        // Larger frames would never fit in the buffer, including their header
+        let max_frame_size = N.saturating_sub(H::header_len(N).unwrap_or(0) + H::TRAILER_LEN);
+        self.try_split_framed_with_max(max_frame_size)
+    }
+
+    /// Attempt to split the `BBBuffer` into `FrameConsumer` and `FrameProducer` halves,
+    /// using the frame header `H`, and allowing frames of up to `max_frame_size` bytes,
+    /// not including the header. If buffer has already been split, an error will be
+    /// returned.
+    ///
+    /// Requesting a larger frame from the `FrameProducer` returns
+    /// `Error::FrameTooLarge`, rather than `Error::InsufficientSize`, which
+    /// signals that the request should be retried later.
+    ///
+    /// By default, the maximum frame size only excludes frames that can never
+    /// fit in the buffer. However, as frames never wrap around the end of
+    /// the buffer, a frame that takes up more than half of the buffer may
+    /// still never be granted, depending on where the previous frames ended.
+    /// If a frame and its header take up at most half of the buffer, it is
+    /// guaranteed to be granted once the `FrameConsumer` has released all
+    /// previous frames.
+    ///
+    /// See `try_split_framed()` for details.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// let bb: BBBuffer<64> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split_framed_with_max::<u8>(16).unwrap();
+    ///
+    /// assert_eq!(prod.grant(17).unwrap_err(), Error::FrameTooLarge);
+    /// prod.grant(16).unwrap().commit(16);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn try_split_framed_with_max<H: FrameHeader>(
+        &'a self,
+        max_frame_size: usize,
+    ) -> Result<(FrameProducer<'a, N, H>, FrameConsumer<'a, N, H>)> {
         let (producer, consumer) = self.try_split()?;
-        Ok((FrameProducer { producer }, FrameConsumer { consumer }))
+        Ok((
+            FrameProducer {
+                producer,
+                max_frame_size,
+                pd: PhantomData,
+            },
+            FrameConsumer {
+                consumer,
+                auto_release: false,
+                discard_bad_frames: true,
+                pd: PhantomData,
+            },
+        ))
     }
 
     /// Attempt to release the Producer and Consumer
     ///
     /// This re-initializes the buffer so it may be split in a different mode at a later
     /// time. There must be no read or write grants active, or an error will be returned.
+    /// With the `split_grants` feature, this includes both parts of a grant divided
+    /// with `GrantR::split_at()`, so a grant of one split can never be committed or
+    /// released into the next one. A grant leaked with `core::mem::forget()` stays
+    /// active forever, see `is_poisoned()`. A grant that outlives the split anyway,
+    /// such as a copy forged with unsafe code, is stale: committing, releasing or
+    /// dropping it has no effect on the next split, and `GrantW::commit_checked()`
+    /// and `GrantR::release_checked()` return `Error::StaleGrant` for it.
+    ///
+    /// The counters of the `stats` feature, such as `total_bytes_written()` and
+    /// `Consumer::bytes_lost()`, are reset along with the queue. Watermark
+    /// callbacks are registered with the `BBBuffer` rather than its halves, so
+    /// they stay registered for the next split.
     ///
     /// The `Producer` and `Consumer` must be from THIS `BBBuffer`, or an error will
     /// be returned.
@@ -190,11 +408,7 @@ impl<'a, const N: usize> BBBuffer<N> {
         }
 
         // Drop the producer and consumer halves
-        // Clippy's hint are ignored because this is done intentionnaly to prevent any use after
-        // the release fo the lock.
-        #[allow(clippy::drop_non_drop)]
         drop(prod);
-        #[allow(clippy::drop_non_drop)]
         drop(cons);
 
         // Re-initialize the buffer (not totally needed, but nice to do)
@@ -202,6 +416,23 @@ impl<'a, const N: usize> BBBuffer<N> {
         self.read.store(0, Release);
         self.reserve.store(0, Release);
         self.last.store(0, Release);
+        self.producer_closed.store(false, Release);
+        self.consumer_closed.store(false, Release);
+        self.poisoned.store(false, Release);
+        // Both halves are gone, so nothing else writes this
+        let generation = self.generation.load(Acquire);
+        self.generation.store(generation.wrapping_add(1), Release);
+        #[cfg(feature = "subscribe")]
+        self.subscriber.clear();
+        #[cfg(feature = "stats")]
+        {
+            self.write_total.clear();
+            self.read_total.clear();
+            self.bytes_lost.store(0, Release);
+            self.fill_ewma.store(0, Release);
+        }
+        #[cfg(feature = "embedded_hal_1")]
+        self.data_ready.clear();
 
         // Mark the buffer as ready to be split
         self.already_split.store(false, Release);
@@ -216,75 +447,216 @@ impl<'a, const N: usize> BBBuffer<N> {
     ///
     /// The `FrameProducer` and `FrameConsumer` must be from THIS `BBBuffer`, or an error
     /// will be returned.
-    pub fn try_release_framed(
+    pub fn try_release_framed<H>(
         &'a self,
-        prod: FrameProducer<'a, N>,
-        cons: FrameConsumer<'a, N>,
-    ) -> CoreResult<(), (FrameProducer<'a, N>, FrameConsumer<'a, N>)> {
+        prod: FrameProducer<'a, N, H>,
+        cons: FrameConsumer<'a, N, H>,
+    ) -> CoreResult<(), (FrameProducer<'a, N, H>, FrameConsumer<'a, N, H>)> {
+        let cons_auto_release = cons.auto_release;
+        let cons_discard_bad_frames = cons.discard_bad_frames;
+        let max_frame_size = prod.max_frame_size;
         self.try_release(prod.producer, cons.consumer)
             .map_err(|(producer, consumer)| {
                 // Restore the wrapper types
-                (FrameProducer { producer }, FrameConsumer { consumer })
+                (
+                    FrameProducer {
+                        producer,
+                        max_frame_size,
+                        pd: PhantomData,
+                    },
+                    FrameConsumer {
+                        consumer,
+                        auto_release: cons_auto_release,
+                        discard_bad_frames: cons_discard_bad_frames,
+                        pd: PhantomData,
+                    },
+                )
             })
     }
-}
 
-impl<const A: usize> BBBuffer<A> {
-    /// Create a new constant inner portion of a `BBBuffer`.
+    /// Obtain a read-only handle to the queue, for monitoring
     ///
-    /// NOTE: This is only necessary to use when creating a `BBBuffer` at static
-    /// scope, and is generally never used directly. This process is necessary to
-    /// work around current limitations in `const fn`, and will be replaced in
-    /// the future.
+    /// Any number of observers may be created, whether or not the buffer has
+    /// been split. See `Observer` for details.
     ///
-    /// ```rust,no_run
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
     /// use bbqueue::BBBuffer;
     ///
-    /// static BUF: BBBuffer<6> = BBBuffer::new();
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// let observer = buffer.observer();
+    ///
+    /// prod.write_frame(&[1, 2, 3], &[]).unwrap();
+    /// cons.skip(1).unwrap();
     ///
-    /// fn main() {
-    ///    let (prod, cons) = BUF.try_split().unwrap();
-    /// }
+    /// assert_eq!(observer.bytes_available(), 2);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
     /// ```
-    pub const fn new() -> Self {
-        Self {
-            // This will not be initialized until we split the buffer
-            buf: UnsafeCell::new(MaybeUninit::uninit()),
-
-            // Owned by the writer
-            write: AtomicUsize::new(0),
-
-            // Owned by the reader
-            read: AtomicUsize::new(0),
-
-            // Cooperatively owned
-            //
-            // NOTE: This should generally be initialized as size_of::<self.buf>(), however
-            // this would prevent the structure from being entirely zero-initialized,
-            // and can cause the .data section to be much larger than necessary. By
-            // forcing the `last` pointer to be zero initially, we place the structure
-            // in an "inverted" condition, which will be resolved on the first commited
-            // bytes that are written to the structure.
-            //
-            // When read == last == write, no bytes will be allowed to be read (good), but
-            // write grants can be given out (also good).
-            last: AtomicUsize::new(0),
-
-            // Owned by the Writer, "private"
-            reserve: AtomicUsize::new(0),
+    pub fn observer(&'a self) -> Observer<'a, N> {
+        Observer { bbq: self }
+    }
 
-            // Owned by the Reader, "private"
-            read_in_progress: AtomicBool::new(false),
+    /// The total number of bytes committed by the `Producer` since the
+    /// buffer was split
+    ///
+    /// This is a 64-bit counter on all targets, so will not wrap around in
+    /// practice. It is reset by `try_release()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// cons.read().unwrap().release(3);
+    ///
+    /// assert_eq!(buffer.total_bytes_written(), 4);
+    /// assert_eq!(buffer.total_bytes_read(), 3);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn total_bytes_written(&self) -> u64 {
+        self.write_total.load()
+    }
 
-            // Owned by the Writer, "private"
-            write_in_progress: AtomicBool::new(false),
+    /// The total number of bytes released by the `Consumer` since the
+    /// buffer was split, see `total_bytes_written()`
+    #[cfg(feature = "stats")]
+    pub fn total_bytes_read(&self) -> u64 {
+        self.read_total.load()
+    }
+}
 
-            // We haven't split at the start
-            already_split: AtomicBool::new(false),
+impl<const A: usize> BBBuffer<A> {
+    const_fn! {
+        /// Create a new constant inner portion of a `BBBuffer`.
+        ///
+        /// NOTE: This is only necessary to use when creating a `BBBuffer` at static
+        /// scope, and is generally never used directly. This process is necessary to
+        /// work around current limitations in `const fn`, and will be replaced in
+        /// the future.
+        ///
+        /// The storage is not initialized until the buffer is split, so a static
+        /// `BBBuffer` may be placed in a section that is not cleared at startup, such
+        /// as with `#[link_section = ".uninit.BUF"]` on `cortex-m-rt`. See
+        /// `try_split_uninit()` to also skip zeroing the storage when splitting.
+        ///
+        /// ```rust,no_run
+        /// use bbqueue::BBBuffer;
+        ///
+        /// static BUF: BBBuffer<6> = BBBuffer::new();
+        ///
+        /// fn main() {
+        ///    let (prod, cons) = BUF.try_split().unwrap();
+        /// }
+        /// ```
+        pub fn new() -> Self {
+            Self {
+                // This will not be initialized until we split the buffer
+                buf: core::cell::UnsafeCell::new(MaybeUninit::uninit()),
+
+                // Owned by the writer
+                write: AtomicUsize::new(0),
+
+                // Owned by the reader
+                read: AtomicUsize::new(0),
+
+                // Cooperatively owned
+                //
+                // NOTE: This should generally be initialized as size_of::<self.buf>(), however
+                // this would prevent the structure from being entirely zero-initialized,
+                // and can cause the .data section to be much larger than necessary. The
+                // `last` pointer is only believed while inverted, and the first commited
+                // bytes that are written to the structure move it to the end of the buffer.
+                last: AtomicUsize::new(0),
+
+                // Owned by the Writer, "private"
+                reserve: AtomicUsize::new(0),
+
+                // Owned by the Reader, "private"
+                read_in_progress: AtomicBool::new(false),
+
+                // Owned by the Writer, "private"
+                write_in_progress: AtomicBool::new(false),
+
+                // We haven't split at the start
+                already_split: AtomicBool::new(false),
+
+                // Owned by the Writer
+                #[cfg(feature = "stats")]
+                write_total: Total::new(),
+
+                // Owned by the Reader
+                #[cfg(feature = "stats")]
+                read_total: Total::new(),
+
+                // Owned by the Writer
+                #[cfg(feature = "stats")]
+                bytes_lost: AtomicUsize::new(0),
+
+                // No watermarks are registered at the start
+                #[cfg(feature = "watermarks")]
+                high_watermark: AtomicUsize::new(0),
+                #[cfg(feature = "watermarks")]
+                high_watermark_cb: AtomicPtr::new(null_mut()),
+                #[cfg(feature = "watermarks")]
+                low_watermark: AtomicUsize::new(0),
+                #[cfg(feature = "watermarks")]
+                low_watermark_cb: AtomicPtr::new(null_mut()),
+
+                // Updated by whoever samples it
+                #[cfg(feature = "stats")]
+                fill_ewma: AtomicUsize::new(0),
+
+                // Owned by the Reader
+                #[cfg(feature = "split_grants")]
+                split_state: AtomicUsize::new(0),
+
+                // Set by `close()`, or the `Drop` impls of the `Producer` and `Consumer`
+                producer_closed: AtomicBool::new(false),
+                consumer_closed: AtomicBool::new(false),
+
+                // Set by grants dropped while panicking
+                poisoned: AtomicBool::new(false),
+                generation: AtomicUsize::new(0),
+
+                // Registered by the Reader, woken by the Writer
+                #[cfg(feature = "subscribe")]
+                subscriber: Subscriber::new(),
+
+                // Registered by the Writer, driven by both
+                #[cfg(feature = "embedded_hal_1")]
+                data_ready: DataReady::new(),
+            }
         }
     }
 }
 
+#[cfg(not(loom))]
+impl<const A: usize> Default for BBBuffer<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// `Producer` is the primary interface for pushing data into a `BBBuffer`.
 /// There are various methods for obtaining a grant to write to the buffer, with
 /// different potential tradeoffs. As all grants are required to be a contiguous
@@ -296,15 +668,15 @@ impl<const A: usize> BBBuffer<A> {
 /// * `grant_exact(N)`
 ///   * User will receive a grant `sz == N` (or receive an error)
 ///   * This may cause a wraparound if a grant of size N is not available
-///       at the end of the ring.
-///   * If this grant caused a wraparound, the bytes that were "skipped" at the
-///       end of the ring will not be available until the reader reaches them,
-///       regardless of whether the grant commited any data or not.
+///     at the end of the ring.
+///   * If this grant caused a wraparound, and commited any data, the bytes that
+///     were "skipped" at the end of the ring will not be available until the
+///     reader reaches them.
 ///   * Maximum possible waste due to skipping: `N - 1` bytes
 /// * `grant_max_remaining(N)`
 ///   * User will receive a grant `0 < sz <= N` (or receive an error)
 ///   * This will only cause a wrap to the beginning of the ring if exactly
-///       zero bytes are available at the end of the ring.
+///     zero bytes are available at the end of the ring.
 ///   * Maximum possible waste due to skipping: 0 bytes
 ///
 /// See [this github issue](https://github.com/jamesmunns/bbqueue/issues/38) for a
@@ -312,8 +684,16 @@ impl<const A: usize> BBBuffer<A> {
 pub struct Producer<'a, const N: usize> {
     bbq: NonNull<BBBuffer<N>>,
     pd: PhantomData<&'a ()>,
+
+    /// How long `grant_timeout()` sleeps between attempts
+    #[cfg(feature = "std")]
+    poll_interval: std::time::Duration,
 }
 
+// SAFETY: The `Producer` is the only handle that writes to the buffer, and it
+// does so through atomics shared with the `Consumer`, so it may be moved to
+// another thread, or an interrupt handler. It is not `Sync`, as obtaining a
+// grant must not race with another grant of the same `Producer`.
 unsafe impl<'a, const N: usize> Send for Producer<'a, N> {}
 
 impl<'a, const N: usize> Producer<'a, N> {
@@ -325,6 +705,12 @@ impl<'a, const N: usize> Producer<'a, N> {
     /// requested space is not available at the end of the buffer, but
     /// is available at the beginning
     ///
+    /// If either half has been closed or dropped, `Error::Disconnected` is
+    /// returned, as nothing written would ever be read.
+    ///
+    /// With the `stats` feature, if not enough space is available, `sz` is
+    /// added to `Consumer::bytes_lost()`.
+    ///
     /// ```rust
     /// # // bbqueue test shim!
     /// # fn bbqtest() {
@@ -332,7 +718,7 @@ impl<'a, const N: usize> Producer<'a, N> {
     ///
     /// // Create and split a new buffer of 6 elements
     /// let buffer: BBBuffer<6> = BBBuffer::new();
-    /// let (mut prod, cons) = buffer.try_split().unwrap();
+    /// let (mut prod, _cons) = buffer.try_split().unwrap();
     ///
     /// // Successfully obtain and commit a grant of four bytes
     /// let mut grant = prod.grant_exact(4).unwrap();
@@ -350,21 +736,41 @@ impl<'a, const N: usize> Producer<'a, N> {
     /// # }
     /// ```
     pub fn grant_exact(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
+        let res = self.grant_exact_inner(sz);
+        self.record_failure(sz, res)
+    }
+
+    /// `grant_exact()`, without counting a failure in `bytes_lost`, for
+    /// callers which retry
+    pub(crate) fn grant_exact_inner(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
         let inner = unsafe { &self.bbq.as_ref() };
 
-        if atomic::swap(&inner.write_in_progress, true, AcqRel) {
+        inner.debug_sanity_check();
+
+        if inner.consumer_closed.load(Acquire) || inner.producer_closed.load(Acquire) {
+            return Err(Error::Disconnected);
+        }
+        if inner.poisoned.load(Acquire) {
+            return Err(Error::Poisoned);
+        }
+
+        // Only the `Producer` sets this flag, so it is checked and set with a
+        // load and a store, see `atomic`
+        if inner.write_in_progress.load(Acquire) {
             return Err(Error::GrantInProgress);
         }
+        inner.write_in_progress.store(true, Release);
 
         // Writer component. Must never write to `read`,
         // be careful writing to `load`
-        let write = inner.write.load(Acquire);
-        let read = inner.read.load(Acquire);
+        let write_idx = inner.write.load(Acquire);
+        let read_idx = inner.read.load(Acquire);
+        let (write, read) = (position(write_idx), position(read_idx));
         let max = N;
-        let already_inverted = write < read;
+        let already_inverted = is_inverted(write_idx, read_idx);
 
         let start = if already_inverted {
-            if (write + sz) < read {
+            if (write + sz) <= read {
                 // Inverted, room is still available
                 write
             } else {
@@ -378,14 +784,16 @@ impl<'a, const N: usize> Producer<'a, N> {
                 // Non inverted condition
                 write
             } else {
-                // Not inverted, but need to go inverted
-
-                // NOTE: We check sz < read, NOT <=, because
-                // write must never == read in an inverted condition, since
-                // we will then not be able to tell if we are inverted or not
-                if sz < read {
+                // Not inverted, but need to go inverted. The lap flag
+                // tells a full buffer apart from an empty one, so the
+                // grant may reach all the way up to read
+                if sz <= read {
                     // Invertible situation
                     0
+                } else if sz <= max && inner.compact() {
+                    // The queue was empty, and has been moved back to
+                    // the start of the buffer
+                    0
                 } else {
                     // Not invertible, no space
                     inner.write_in_progress.store(false, Release);
@@ -394,8 +802,15 @@ impl<'a, const N: usize> Producer<'a, N> {
             }
         };
 
-        // Safe write, only viewed by this task
-        inner.reserve.store(start + sz, Release);
+        // Safe write, only viewed by this task. Wrapping around to the
+        // start of the buffer begins the next lap
+        let lap = if start < write {
+            (write_idx & LAP) ^ LAP
+        } else {
+            write_idx & LAP
+        };
+        inner.reserve.store(lap | (start + sz), Release);
+        inner.debug_sanity_check();
 
         // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
         // are all `#[repr(Transparent)]
@@ -406,6 +821,8 @@ impl<'a, const N: usize> Producer<'a, N> {
             buf: grant_slice,
             bbq: self.bbq,
             to_commit: 0,
+            write_pos: 0,
+            generation: inner.generation.load(Acquire),
         })
     }
 
@@ -414,7 +831,12 @@ impl<'a, const N: usize> Producer<'a, N> {
     /// wrapping, but some space (0 < available < sz) is available without
     /// wrapping, then a grant will be given for the remaining size at the
     /// end of the buffer. If no space is available for writing, an error
-    /// will be returned.
+    /// will be returned. Requesting zero bytes returns an empty grant, unless
+    /// the buffer is full.
+    ///
+    /// An empty queue is only moved back to the start of the buffer if no
+    /// space at all remains at its end, so this may grant less than the whole
+    /// buffer even then. Use `try_compact()` first to avoid this.
     ///
     /// ```
     /// # // bbqueue test shim!
@@ -447,103 +869,218 @@ impl<'a, const N: usize> Producer<'a, N> {
     /// # bbqtest();
     /// # }
     /// ```
-    pub fn grant_max_remaining(&mut self, mut sz: usize) -> Result<GrantW<'a, N>> {
+    pub fn grant_max_remaining(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
+        // Unlike `grant_max_with_minimum(sz, 1)`, an `sz` of zero is allowed
+        let res = self.grant_max_with_minimum_inner(sz, 1);
+        self.record_failure(sz, res)
+    }
+
+    /// Record that `sz` bytes were dropped for another reason than a failed
+    /// grant, which is already counted, so that the `Consumer` can tell with
+    /// `Consumer::bytes_lost()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    ///
+    /// // A message which does not fit is truncated
+    /// let msg = [1, 2, 3, 4];
+    /// let mut grant = prod.grant_max_remaining(msg.len()).unwrap();
+    /// let len = grant.len();
+    /// grant.copy_from_slice(&msg[..len]);
+    /// grant.commit(len);
+    /// prod.record_lost(msg.len() - len);
+    /// assert_eq!(cons.bytes_lost(), 2);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn record_lost(&mut self, sz: usize) {
         let inner = unsafe { &self.bbq.as_ref() };
 
-        if atomic::swap(&inner.write_in_progress, true, AcqRel) {
-            return Err(Error::GrantInProgress);
-        }
+        // Only the `Producer` writes the counter, so it is updated with a
+        // load and a store, see `atomic`
+        let lost = inner.bytes_lost.load(Acquire);
+        inner.bytes_lost.store(lost.wrapping_add(sz), Release);
+    }
 
-        // Writer component. Must never write to `read`,
-        // be careful writing to `load`
-        let write = inner.write.load(Acquire);
-        let read = inner.read.load(Acquire);
-        let max = N;
+    /// Count the `sz` bytes of a grant that failed for lack of space as lost
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn record_failure(
+        &mut self,
+        sz: usize,
+        res: Result<GrantW<'a, N>>,
+    ) -> Result<GrantW<'a, N>> {
+        #[cfg(feature = "stats")]
+        if let Err(Error::InsufficientSize) = res {
+            self.record_lost(sz);
+        }
+        res
+    }
 
-        let already_inverted = write < read;
+    /// Request a grant of exactly `sz` bytes, as with `grant_exact()`, waiting
+    /// in a low power state with `WFE` until enough space has been released.
+    ///
+    /// The `Consumer` signals with `SEV` whenever it releases data, or is
+    /// closed. On targets other than ARM, this waits by spinning instead.
+    ///
+    /// Any error other than `Error::InsufficientSize` is returned immediately,
+    /// as is `Error::InsufficientSize` if `sz` is larger than the buffer.
+    #[cfg(feature = "cortex-m")]
+    pub fn grant_wfe(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
+        if sz > N {
+            return self.grant_exact(sz);
+        }
+        loop {
+            match self.grant_exact_inner(sz) {
+                Err(Error::InsufficientSize) => event::wait(),
+                res => return res,
+            }
+        }
+    }
 
-        let start = if already_inverted {
-            // In inverted case, read is always > write
-            let remain = read - write - 1;
+    /// Request a writable, contiguous section of memory of exactly `sz` bytes,
+    /// as with `grant_exact()`, sleeping until enough space has been released,
+    /// or `timeout` has elapsed.
+    ///
+    /// The time between attempts is set with `set_poll_interval()`, and is one
+    /// millisecond by default. On timeout, `Error::InsufficientSize` is
+    /// returned, and `sz` is added to `Consumer::bytes_lost()` once. Any other
+    /// error is returned immediately, as is `Error::InsufficientSize` if `sz`
+    /// is larger than the buffer.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    /// use std::time::Duration;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    ///
+    /// // Nothing is released, so this gives up
+    /// let timeout = Duration::from_millis(10);
+    /// assert_eq!(prod.grant_timeout(4, timeout).unwrap_err(), Error::InsufficientSize);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn grant_timeout(
+        &mut self,
+        sz: usize,
+        timeout: std::time::Duration,
+    ) -> Result<GrantW<'a, N>> {
+        let start = std::time::Instant::now();
+        loop {
+            match self.grant_exact_inner(sz) {
+                Err(Error::InsufficientSize) if sz <= N => {}
+                res => return self.record_failure(sz, res),
+            }
 
-            if remain != 0 {
-                sz = min(remain, sz);
-                write
-            } else {
-                // Inverted, no room is available
-                inner.write_in_progress.store(false, Release);
-                return Err(Error::InsufficientSize);
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return self.record_failure(sz, Err(Error::InsufficientSize));
             }
-        } else {
-            #[allow(clippy::collapsible_if)]
-            if write != max {
-                // Some (or all) room remaining in un-inverted case
-                sz = min(max - write, sz);
-                write
+            if self.poll_interval.is_zero() {
+                std::thread::yield_now();
             } else {
-                // Not inverted, but need to go inverted
-
-                // NOTE: We check read > 1, NOT read >= 1, because
-                // write must never == read in an inverted condition, since
-                // we will then not be able to tell if we are inverted or not
-                if read > 1 {
-                    sz = min(read - 1, sz);
-                    0
-                } else {
-                    // Not invertible, no space
-                    inner.write_in_progress.store(false, Release);
-                    return Err(Error::InsufficientSize);
-                }
+                std::thread::sleep(min(self.poll_interval, timeout - elapsed));
             }
-        };
-
-        // Safe write, only viewed by this task
-        inner.reserve.store(start + sz, Release);
-
-        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
-        // are all `#[repr(Transparent)]
-        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
-        let grant_slice = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(start), sz) };
-
-        Ok(GrantW {
-            buf: grant_slice,
-            bbq: self.bbq,
-            to_commit: 0,
-        })
+        }
     }
-}
-
-/// `Consumer` is the primary interface for reading data from a `BBBuffer`.
-pub struct Consumer<'a, const N: usize> {
-    bbq: NonNull<BBBuffer<N>>,
-    pd: PhantomData<&'a ()>,
-}
 
-unsafe impl<'a, const N: usize> Send for Consumer<'a, N> {}
+    /// Set how long `grant_timeout()` sleeps between attempts. With a zero
+    /// interval, it yields to other threads instead.
+    #[cfg(feature = "std")]
+    pub fn set_poll_interval(&mut self, interval: std::time::Duration) {
+        self.poll_interval = interval;
+    }
 
-impl<'a, const N: usize> Consumer<'a, N> {
-    /// Obtains a contiguous slice of committed bytes. This slice may not
-    /// contain ALL available bytes, if the writer has wrapped around. The
-    /// remaining bytes will be available after all readable bytes are
-    /// released
+    /// Drive `pin` high while committed bytes are waiting to be read, and low
+    /// once all of them have been released, to signal a consumer waiting for
+    /// data, such as another chip, or an interrupt handler.
+    ///
+    /// The pin is driven to the current level right away, and then after
+    /// every commit and release, by whichever side made the change. Errors
+    /// returned by the pin are ignored. Registering another pin replaces
+    /// this one, which is left at its last level.
+    ///
+    /// The pin is borrowed for as long as the buffer, so a `static` buffer
+    /// needs a `&'static mut` pin, such as one created with
+    /// `cortex_m::singleton!`. It must be `Send`, as the `Consumer` may drive
+    /// it from another thread.
+    ///
+    /// If the `Consumer` is driving the previous pin at the same time, the new
+    /// pin is handed back, and can be registered again once that release has
+    /// finished. Waiting for it instead could never finish when called from an
+    /// interrupt that preempted the `Consumer`.
+    ///
+    /// NOTE: Every commit and release updates the pin with a read-modify-write
+    /// operation, even while no pin is registered. With the `thumbv6` or
+    /// `critical-section` feature, this is a short critical section.
     ///
     /// ```rust
     /// # // bbqueue test shim!
     /// # fn bbqtest() {
     /// use bbqueue::BBBuffer;
+    /// use core::{
+    ///     convert::Infallible,
+    ///     sync::atomic::{AtomicBool, Ordering::SeqCst},
+    /// };
+    /// use embedded_hal::digital::{ErrorType, OutputPin};
     ///
-    /// // Create and split a new buffer of 6 elements
+    /// static LEVEL: AtomicBool = AtomicBool::new(false);
+    ///
+    /// struct DataReady;
+    ///
+    /// impl ErrorType for DataReady {
+    ///     type Error = Infallible;
+    /// }
+    ///
+    /// impl OutputPin for DataReady {
+    ///     fn set_low(&mut self) -> Result<(), Infallible> {
+    ///         LEVEL.store(false, SeqCst);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn set_high(&mut self) -> Result<(), Infallible> {
+    ///         LEVEL.store(true, SeqCst);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut pin = DataReady;
     /// let buffer: BBBuffer<6> = BBBuffer::new();
     /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// assert!(prod.set_data_ready_pin(&mut pin).is_ok());
     ///
-    /// // Successfully obtain and commit a grant of four bytes
-    /// let mut grant = prod.grant_max_remaining(4).unwrap();
-    /// assert_eq!(grant.buf().len(), 4);
-    /// grant.commit(4);
+    /// prod.grant_exact(2).unwrap().commit(2);
+    /// assert!(LEVEL.load(SeqCst));
     ///
-    /// // Obtain a read grant
-    /// let mut grant = cons.read().unwrap();
-    /// assert_eq!(grant.buf().len(), 4);
+    /// // Still high, until all bytes are released
+    /// cons.read().unwrap().release(1);
+    /// assert!(LEVEL.load(SeqCst));
+    /// cons.read().unwrap().release(1);
+    /// assert!(!LEVEL.load(SeqCst));
     /// # // bbqueue test shim!
     /// # }
     /// #
@@ -552,32 +1089,482 @@ impl<'a, const N: usize> Consumer<'a, N> {
     /// # bbqtest();
     /// # }
     /// ```
-    pub fn read(&mut self) -> Result<GrantR<'a, N>> {
+    #[cfg(feature = "embedded_hal_1")]
+    pub fn set_data_ready_pin<P>(&mut self, pin: &'a mut P) -> CoreResult<(), &'a mut P>
+    where
+        P: embedded_hal::digital::OutputPin + Send,
+    {
         let inner = unsafe { &self.bbq.as_ref() };
+        inner
+            .data_ready
+            .register(pin, || inner.committed_len() != 0)
+    }
 
-        if atomic::swap(&inner.read_in_progress, true, AcqRel) {
-            return Err(Error::GrantInProgress);
-        }
-
-        let write = inner.write.load(Acquire);
-        let last = inner.last.load(Acquire);
-        let mut read = inner.read.load(Acquire);
+    /// Request a writable, contiguous section of memory of up to
+    /// `desired` bytes, but at least `minimum` bytes.
+    ///
+    /// This behaves like `grant_max_remaining()`, except that an error is
+    /// returned instead of a grant smaller than `minimum`. If fewer than
+    /// `minimum` bytes are available at the end of the buffer, but enough
+    /// are available at the start, the grant wraps around, as with
+    /// `grant_exact()`. If not even `minimum` bytes are available, `desired`
+    /// is added to `Consumer::bytes_lost()`.
+    ///
+    /// As with `grant_exact()`, an empty queue is moved back to the start of
+    /// the buffer when `minimum` bytes would not fit otherwise, but not to
+    /// grant more than the space up to the end of the buffer, see
+    /// `try_compact()`.
+    ///
+    /// A `desired` size smaller than `minimum` can never be granted, so
+    /// `Error::InsufficientSize` is returned, without counting anything as
+    /// lost.
+    ///
+    /// ```
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Fill and release the first four bytes
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// cons.read().unwrap().release(4);
+    ///
+    /// // Two bytes remain at the end of the buffer, which is not enough,
+    /// // so the grant wraps around to the four bytes at the start
+    /// assert_eq!(prod.grant_max_with_minimum(8, 3).unwrap().len(), 4);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn grant_max_with_minimum(
+        &mut self,
+        desired: usize,
+        minimum: usize,
+    ) -> Result<GrantW<'a, N>> {
+        // A bad argument is not data that was dropped
+        if desired < minimum.max(1) {
+            return Err(Error::InsufficientSize);
+        }
+
+        let res = self.grant_max_with_minimum_inner(desired, minimum);
+        self.record_failure(desired, res)
+    }
+
+    /// `grant_max_with_minimum()`, without counting a failure in
+    /// `bytes_lost`, for callers which retry
+    ///
+    /// `desired` is not checked against `minimum`, so a smaller `desired`
+    /// grants at most `desired` bytes, once `minimum` bytes are available.
+    pub(crate) fn grant_max_with_minimum_inner(
+        &mut self,
+        desired: usize,
+        minimum: usize,
+    ) -> Result<GrantW<'a, N>> {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        inner.debug_sanity_check();
+        let minimum = minimum.max(1);
+
+        if inner.consumer_closed.load(Acquire) || inner.producer_closed.load(Acquire) {
+            return Err(Error::Disconnected);
+        }
+        if inner.poisoned.load(Acquire) {
+            return Err(Error::Poisoned);
+        }
+
+        // Only the `Producer` sets this flag, so it is checked and set with a
+        // load and a store, see `atomic`
+        if inner.write_in_progress.load(Acquire) {
+            return Err(Error::GrantInProgress);
+        }
+        inner.write_in_progress.store(true, Release);
+
+        // Writer component. Must never write to `read`,
+        // be careful writing to `load`
+        let write_idx = inner.write.load(Acquire);
+        let read_idx = inner.read.load(Acquire);
+        let (write, read) = (position(write_idx), position(read_idx));
+        let max = N;
+
+        let already_inverted = is_inverted(write_idx, read_idx);
+
+        let (start, sz) = if already_inverted {
+            // In inverted case, read is always >= write
+            let remain = read - write;
+
+            if remain >= minimum {
+                (write, min(remain, desired))
+            } else {
+                // Inverted, not enough room is available
+                inner.write_in_progress.store(false, Release);
+                return Err(Error::InsufficientSize);
+            }
+        } else if max - write >= minimum {
+            // Enough room remaining in un-inverted case
+            (write, min(max - write, desired))
+        } else {
+            // Not inverted, but need to go inverted. The lap flag
+            // tells a full buffer apart from an empty one, so the
+            // grant may reach all the way up to read
+            if read >= minimum {
+                (0, min(read, desired))
+            } else if minimum <= max && inner.compact() {
+                // The queue was empty, and has been moved back to
+                // the start of the buffer
+                (0, min(max, desired))
+            } else {
+                // Not invertible, no space
+                inner.write_in_progress.store(false, Release);
+                return Err(Error::InsufficientSize);
+            }
+        };
+
+        // Safe write, only viewed by this task. Wrapping around to the
+        // start of the buffer begins the next lap
+        let lap = if start < write {
+            (write_idx & LAP) ^ LAP
+        } else {
+            write_idx & LAP
+        };
+        inner.reserve.store(lap | (start + sz), Release);
+        inner.debug_sanity_check();
+
+        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
+        // are all `#[repr(Transparent)]
+        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
+        let grant_slice = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(start), sz) };
+
+        Ok(GrantW {
+            buf: grant_slice,
+            bbq: self.bbq,
+            to_commit: 0,
+            write_pos: 0,
+            generation: inner.generation.load(Acquire),
+        })
+    }
+
+    /// Write a `header` followed by a `body` into the buffer as one
+    /// contiguous region, and commit it.
+    ///
+    /// A single grant of `header.len() + body.len()` bytes is requested, as
+    /// with `grant_exact()`. If that much space is not available, an error
+    /// will be returned, and nothing is written.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Write a two byte header and a three byte body
+    /// prod.write_frame(&[0xAA, 3], &[1, 2, 3]).unwrap();
+    ///
+    /// // There is no space for another one
+    /// assert!(prod.write_frame(&[0xAA, 3], &[1, 2, 3]).is_err());
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, &[0xAA, 3, 1, 2, 3]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn write_frame(&mut self, header: &[u8], body: &[u8]) -> Result<()> {
+        let total_len = header.len() + body.len();
+        let mut wgr = self.grant_exact(total_len)?;
+
+        let (hdr, bdy) = wgr.buf().split_at_mut(header.len());
+        hdr.copy_from_slice(header);
+        bdy.copy_from_slice(body);
+
+        wgr.commit(total_len);
+        Ok(())
+    }
+
+    /// Move the queue back to the start of the buffer, if it is empty, so
+    /// that the whole buffer is available for one contiguous grant. Returns
+    /// `true` if the queue now starts at the beginning of the buffer.
+    ///
+    /// `grant_exact()` and `grant_max_with_minimum()` do this by themselves
+    /// when they would otherwise fail, but not just to grant more than the
+    /// space up to the end of the buffer, which is all `grant_max_remaining()`
+    /// grants then. Nothing is done while a grant is in progress.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// cons.read().unwrap().release(4);
+    ///
+    /// // The queue is empty, but only two bytes remain at the end
+    /// assert_eq!(prod.grant_max_remaining(6).unwrap().len(), 2);
+    ///
+    /// assert!(prod.try_compact());
+    /// assert_eq!(prod.grant_max_remaining(6).unwrap().len(), 6);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn try_compact(&mut self) -> bool {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        // Only the `Producer` sets this flag
+        if inner.write_in_progress.load(Acquire) {
+            return false;
+        }
+        inner.compact()
+    }
+
+    /// Returns the current write position, as the total number of
+    /// bytes committed to the queue
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// cons.read().unwrap().release(1);
+    ///
+    /// // Three bytes are still in flight
+    /// assert_eq!(prod.cursor() - cons.cursor(), 3);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn cursor(&self) -> Cursor {
+        let inner = unsafe { &self.bbq.as_ref() };
+        Cursor {
+            position: inner.write_total.load(),
+        }
+    }
+
+    /// Signal the `Consumer` that no more data will be written
+    ///
+    /// Data that has already been committed can still be read, after which
+    /// the `Consumer` receives `Error::Disconnected`. Any further grants
+    /// from this `Producer` return `Error::Disconnected`. Dropping the
+    /// `Producer` closes it as well.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.write_frame(&[1, 2], &[]).unwrap();
+    /// prod.close();
+    /// assert!(cons.is_producer_closed());
+    ///
+    /// // The remaining data can be drained
+    /// assert_eq!(cons.skip(6), Ok(2));
+    /// assert_eq!(cons.read().unwrap_err(), Error::Disconnected);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn close(&mut self) {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.producer_closed.store(true, Release);
+        event::signal();
+    }
+
+    /// Has the `Consumer` been closed or dropped?
+    pub fn is_consumer_closed(&self) -> bool {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.consumer_closed.load(Acquire)
+    }
+}
+
+/// A read-only handle to a `BBBuffer`, which can monitor the queue without
+/// consuming data, created by `BBBuffer::observer()`
+///
+/// An `Observer` only has access to the positions of the `Producer` and
+/// `Consumer`, not to the data itself: the `Consumer` may modify unreleased
+/// data in place through its read grant, and the `Producer` may overwrite
+/// it as soon as it is released, so the contents can not be safely shared
+/// with a third party. Copying them and re-checking the positions afterwards,
+/// like a seqlock, does not help either: the copy itself would race with the
+/// plain writes of the `Producer` and the `Consumer`, which is undefined
+/// behavior even if the copy is then discarded. To look at the data without
+/// consuming it, drop a read grant of the `Consumer` without releasing it.
+///
+/// The values returned by an `Observer` may be slightly stale if the queue
+/// is in use concurrently, however the `Consumer` position is never ahead of
+/// a `Producer` position loaded after it.
+#[derive(Debug, Clone, Copy)]
+pub struct Observer<'a, const N: usize> {
+    bbq: &'a BBBuffer<N>,
+}
+
+impl<'a, const N: usize> Observer<'a, N> {
+    /// The number of committed bytes, which have not yet been released
+    pub fn bytes_available(&self) -> usize {
+        self.bbq.committed_len()
+    }
+
+    /// The current position of the `Producer`, see `Producer::cursor()`
+    #[cfg(feature = "stats")]
+    pub fn producer_cursor(&self) -> Cursor {
+        Cursor {
+            position: self.bbq.write_total.load(),
+        }
+    }
+
+    /// The current position of the `Consumer`, see `Consumer::cursor()`
+    #[cfg(feature = "stats")]
+    pub fn consumer_cursor(&self) -> Cursor {
+        Cursor {
+            position: self.bbq.read_total.load(),
+        }
+    }
+}
+
+/// `Consumer` is the primary interface for reading data from a `BBBuffer`.
+pub struct Consumer<'a, const N: usize> {
+    bbq: NonNull<BBBuffer<N>>,
+    pd: PhantomData<&'a ()>,
+
+    /// The read position and length of a header handed out by
+    /// `read_frame_header()`, which has not yet been consumed by `read_body()`
+    pending_header: Option<(usize, usize)>,
+
+    /// The read position and length of the slice handed out by `fill_buf()`,
+    /// of which `consume()` has not released all bytes yet
+    filled: Option<(usize, usize)>,
+}
+
+// SAFETY: See `Producer`, the same holds for reading and releasing
+unsafe impl<'a, const N: usize> Send for Consumer<'a, N> {}
+
+impl<'a, const N: usize> Consumer<'a, N> {
+    /// Obtains a contiguous slice of committed bytes. This slice may not
+    /// contain ALL available bytes, if the writer has wrapped around. The
+    /// remaining bytes will be available after all readable bytes are
+    /// released
+    ///
+    /// Only one read grant (of any kind) may be active at a time. While a
+    /// read grant is active, all other methods obtaining a read grant return
+    /// `Error::GrantInProgress`, so grants can never be released out of order.
+    ///
+    /// If no bytes are available and the `Producer` has been closed or dropped,
+    /// `Error::Disconnected` is returned instead of `Error::Empty`,
+    /// as no more data will ever arrive.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Successfully obtain and commit a grant of four bytes
+    /// let mut grant = prod.grant_max_remaining(4).unwrap();
+    /// assert_eq!(grant.buf().len(), 4);
+    /// grant.commit(4);
+    ///
+    /// // Obtain a read grant
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(grant.len(), 4);
+    /// assert_eq!(&grant[..2], &[0, 0]);
+    ///
+    /// // Only one read grant may be active at a time
+    /// assert_eq!(cons.read().unwrap_err(), Error::GrantInProgress);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read(&mut self) -> Result<GrantR<'a, N>> {
+        self.read_available().map(|(grant, _)| grant)
+    }
+
+    /// `read()`, also returning the number of committed bytes in total, taken
+    /// from the same snapshot of the indices
+    fn read_available(&mut self) -> Result<(GrantR<'a, N>, usize)> {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        inner.debug_sanity_check();
+
+        if inner.poisoned.load(Acquire) {
+            return Err(Error::Poisoned);
+        }
+
+        // Only the `Consumer` sets this flag, so it is checked and set with a
+        // load and a store, see `atomic`
+        if inner.read_in_progress.load(Acquire) {
+            return Err(Error::GrantInProgress);
+        }
+        inner.read_in_progress.store(true, Release);
+
+        // Must be loaded before `write`, so that anything committed before
+        // the `Producer` was dropped is seen below
+        let producer_closed = inner.producer_closed.load(Acquire);
+        let (write_idx, last, mut read_idx) = inner.indices();
 
         // Resolve the inverted case or end of read
-        if (read == last) && (write < read) {
-            read = 0;
+        if is_inverted(write_idx, read_idx) && (position(read_idx) == last) {
+            // Catch up with the lap of the writer
+            read_idx = write_idx & LAP;
             // This has some room for error, the other thread reads this
             // Impact to Grant:
-            //   Grant checks if read < write to see if inverted. If not inverted, but
+            //   Grant compares the lap flags to see if inverted. If not inverted, but
             //     no space left, Grant will initiate an inversion, but will not trigger it
             // Impact to Commit:
             //   Commit does not check read, but if Grant has started an inversion,
             //   grant could move Last to the prior write position
             // MOVING READ BACKWARDS!
-            inner.read.store(0, Release);
+            inner.read.store(read_idx, Release);
         }
 
-        let sz = if write < read {
+        let inverted = is_inverted(write_idx, read_idx);
+        let (write, read) = (position(write_idx), position(read_idx));
+
+        let sz = if inverted {
             // Inverted, only believe last
             last
         } else {
@@ -585,75 +1572,1069 @@ impl<'a, const N: usize> Consumer<'a, N> {
             write
         } - read;
 
-        if sz == 0 {
-            inner.read_in_progress.store(false, Release);
+        // When inverted, the bytes at the start of the buffer follow
+        let available = if inverted { sz + write } else { sz };
+
+        if sz == 0 {
+            inner.read_in_progress.store(false, Release);
+            return Err(if producer_closed {
+                Error::Disconnected
+            } else {
+                Error::Empty
+            });
+        }
+
+        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
+        // are all `#[repr(Transparent)]
+        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
+        let grant_slice = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(read), sz) };
+
+        inner.debug_sanity_check();
+        let grant = GrantR {
+            buf: grant_slice,
+            bbq: self.bbq,
+            to_release: 0,
+            read_pos: 0,
+            start: read_idx,
+            trailer: 0,
+            generation: inner.generation.load(Acquire),
+        };
+        Ok((grant, available))
+    }
+
+    /// Obtain a read grant, as with `read()`, waiting in a low power state with
+    /// `WFE` until data has been committed.
+    ///
+    /// The `Producer` signals with `SEV` whenever it commits data, or is
+    /// closed. On targets other than ARM, this waits by spinning instead.
+    ///
+    /// Any error other than `Error::Empty` is returned immediately,
+    /// such as `Error::Disconnected` once the `Producer` has been closed and
+    /// all of its data has been released.
+    #[cfg(feature = "cortex-m")]
+    pub fn read_wfe(&mut self) -> Result<GrantR<'a, N>> {
+        loop {
+            match self.read() {
+                Err(Error::Empty) => event::wait(),
+                res => return res,
+            }
+        }
+    }
+
+    /// Read `payload_len` bytes of payload, followed by their CRC-32, as
+    /// written by `GrantW::commit_with_crc32()`
+    ///
+    /// If the checksum matches, a grant of only the payload is returned.
+    /// Releasing the last byte of the payload also releases the checksum.
+    ///
+    /// If the checksum does not match, the payload and checksum are released
+    /// to re-synchronize, and `Error::ChecksumMismatch` is returned. If fewer
+    /// than `payload_len + 4` contiguous bytes are available, an error is
+    /// returned, and nothing is released.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 16 elements
+    /// let buffer: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(3 + 4).unwrap();
+    /// grant[..3].copy_from_slice(&[1, 2, 3]);
+    /// grant.commit_with_crc32(3);
+    ///
+    /// let grant = cons.read_and_verify_crc32(3).unwrap();
+    /// assert_eq!(&*grant, &[1, 2, 3]);
+    /// grant.release(3);
+    /// assert!(cons.is_empty());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_and_verify_crc32(&mut self, payload_len: usize) -> Result<GrantR<'a, N>> {
+        let mut grant = self.read()?;
+
+        let crc = match payload_len
+            .checked_add(4)
+            .and_then(|end| grant.buf.get(payload_len..end))
+        {
+            Some(crc) => crc,
+            None => return Err(Error::InsufficientSize),
+        };
+
+        if crc != crc32(&grant.buf[..payload_len]).to_le_bytes() {
+            grant.release(payload_len + 4);
+            return Err(Error::ChecksumMismatch);
+        }
+
+        grant.shrink(payload_len);
+        grant.trailer = 4;
+        Ok(grant)
+    }
+
+    /// View the bytes that `read()` would return, without starting a read
+    ///
+    /// Returns `None` if no bytes are available, or if a read is already in
+    /// progress, as the outstanding grant could otherwise release the bytes
+    /// while they are still borrowed.
+    pub(crate) fn peek(&self) -> Option<&[u8]> {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        if inner.read_in_progress.load(Acquire) {
+            return None;
+        }
+
+        let (write_idx, last, mut read_idx) = inner.indices();
+
+        // Resolve the inverted case or end of read, like `read()`, but
+        // without moving `read`
+        if is_inverted(write_idx, read_idx) && (position(read_idx) == last) {
+            read_idx = write_idx & LAP;
+        }
+
+        let (write, read) = (position(write_idx), position(read_idx));
+        let sz = if is_inverted(write_idx, read_idx) {
+            last
+        } else {
+            write
+        } - read;
+
+        if sz == 0 {
+            return None;
+        }
+
+        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
+        // are all `#[repr(Transparent)]. No grant can release these bytes
+        // while `self` is borrowed.
+        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
+        Some(unsafe { from_raw_parts(start_of_buf_ptr.add(read), sz) })
+    }
+
+    /// Obtains a contiguous slice of at most `max` committed bytes.
+    ///
+    /// This behaves like `read()`, but the returned grant is truncated to
+    /// `max` bytes, which is useful when the data is handed on in chunks of
+    /// a limited size.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(3).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3]);
+    /// grant.commit(3);
+    ///
+    /// // Only two bytes are granted
+    /// let grant = cons.read_max(2).unwrap();
+    /// assert_eq!(&*grant, &[1, 2]);
+    /// grant.release(2);
+    ///
+    /// let grant = cons.read_max(2).unwrap();
+    /// assert_eq!(&*grant, &[3]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_max(&mut self, max: usize) -> Result<GrantR<'a, N>> {
+        let mut grant = self.read()?;
+        let len = min(max, grant.len());
+        grant.shrink(len);
+        Ok(grant)
+    }
+
+    /// Obtains a contiguous slice of exactly `n` committed bytes.
+    ///
+    /// If nothing has been committed, `Error::Empty` is returned, and if fewer
+    /// than `n` bytes have been, `Error::InsufficientSize`. If at least `n`
+    /// bytes have been committed, but they wrap around the end of the buffer,
+    /// `Error::NotContiguous` is returned. The error comes with the number of
+    /// bytes that were available, in total, when it was decided, or zero if
+    /// no grant could be obtained at all, such as with
+    /// `Error::GrantInProgress`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.grant_exact(3).unwrap().commit(3);
+    ///
+    /// // Not enough data yet
+    /// assert_eq!(cons.read_exact(4).unwrap_err(), (Error::InsufficientSize, 3));
+    ///
+    /// let grant = cons.read_exact(2).unwrap();
+    /// assert_eq!(grant.len(), 2);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_exact(&mut self, n: usize) -> CoreResult<GrantR<'a, N>, (Error, usize)> {
+        let (mut grant, available) = self.read_available().map_err(|e| (e, 0))?;
+
+        if grant.len() < n {
+            // Dropping the grant releases nothing
+            drop(grant);
+            let err = if available >= n {
+                Error::NotContiguous
+            } else {
+                Error::InsufficientSize
+            };
+            return Err((err, available));
+        }
+
+        grant.shrink(n);
+        Ok(grant)
+    }
+
+    /// Obtains the current contiguous slice of committed bytes, without a grant.
+    ///
+    /// This provides a `BufRead`-style interface to the queue: repeated calls
+    /// return the same data, until `consume()` is used to advance past it. An
+    /// empty slice is returned when no data is available.
+    ///
+    /// This interface can not be used while a read grant is in progress, and an
+    /// error will be returned.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// assert_eq!(cons.fill_buf().unwrap(), &[]);
+    ///
+    /// let mut grant = prod.grant_exact(3).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3]);
+    /// grant.commit(3);
+    ///
+    /// assert_eq!(cons.fill_buf().unwrap(), &[1, 2, 3]);
+    /// cons.consume(2).unwrap();
+    /// assert_eq!(cons.fill_buf().unwrap(), &[3]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn fill_buf(&mut self) -> Result<&[u8]> {
+        let grant = match self.read() {
+            Ok(grant) => grant,
+            Err(Error::Empty) => return Ok(&[]),
+            Err(e) => return Err(e),
+        };
+
+        let ptr = grant.buf.as_ptr();
+        let len = grant.buf.len();
+
+        // Dropping the grant releases nothing. `read()` may have moved the
+        // read position back to the start of the buffer
+        drop(grant);
+        let inner = unsafe { &self.bbq.as_ref() };
+        self.filled = Some((inner.read.load(Acquire), len));
+
+        // This is sound, as committed bytes can not be modified by the
+        // producer until they are released, which requires `&mut self`
+        Ok(unsafe { core::slice::from_raw_parts(ptr, len) })
+    }
+
+    /// Release `amt` bytes of the slice returned by `fill_buf()`.
+    ///
+    /// If `amt` is larger than the slice returned by `fill_buf()`, only the
+    /// bytes of that slice are released, even if more bytes have been committed
+    /// since. Nothing is released if `fill_buf()` was not called, or if bytes
+    /// were released with a read grant in between. An error is returned if a
+    /// read grant is in progress.
+    pub fn consume(&mut self, amt: usize) -> Result<()> {
+        let grant = match self.read() {
+            Ok(grant) => grant,
+            Err(Error::Empty | Error::Disconnected) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        // Only the bytes that were handed out may be released
+        let filled = match self.filled {
+            Some((pos, len)) if inner.read.load(Acquire) == pos => len,
+            _ => 0,
+        };
+        let amt = min(amt, filled);
+
+        grant.release(amt);
+        self.filled = Some((inner.read.load(Acquire), filled - amt));
+        Ok(())
+    }
+
+    /// Obtains all committed bytes as one or two contiguous regions, without
+    /// a grant, e.g. for building a scatter-gather list.
+    ///
+    /// If the data wraps around the end of the buffer, the region at the end
+    /// of the buffer is yielded first, followed by the region at the start. If
+    /// no data is available, no regions are yielded. The regions can then be
+    /// released with `release_regions()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// assert_eq!(cons.regions().unwrap().count(), 0);
+    ///
+    /// prod.write_frame(&[1, 2, 3, 4, 5], &[]).unwrap();
+    /// cons.skip(4).unwrap();
+    /// prod.write_frame(&[6, 7], &[]).unwrap();
+    ///
+    /// let mut regions = cons.regions().unwrap();
+    /// assert_eq!(regions.next(), Some(&[5][..]));
+    /// assert_eq!(regions.next(), Some(&[6, 7][..]));
+    /// assert_eq!(regions.next(), None);
+    /// drop(regions);
+    ///
+    /// cons.release_regions(3).unwrap();
+    /// assert!(cons.is_empty());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn regions(&mut self) -> Result<impl Iterator<Item = &[u8]>> {
+        let (buf1, buf2): (&[u8], &[u8]) = match self.split_read() {
+            Ok(grant) => {
+                let (buf1, buf2) = grant.bufs();
+                let bufs = (buf1.as_ptr(), buf1.len(), buf2.as_ptr(), buf2.len());
+
+                // Dropping the grant releases nothing
+                drop(grant);
+
+                // This is sound, as committed bytes can not be modified by the
+                // producer until they are released, which requires `&mut self`
+                unsafe {
+                    (
+                        core::slice::from_raw_parts(bufs.0, bufs.1),
+                        core::slice::from_raw_parts(bufs.2, bufs.3),
+                    )
+                }
+            }
+            Err(Error::Empty) => (&[], &[]),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Some(buf1)
+            .into_iter()
+            .chain(Some(buf2))
+            .filter(|region| !region.is_empty()))
+    }
+
+    /// Release `total` bytes of the regions returned by `regions()`,
+    /// continuing into the second region if `total` is larger than the first.
+    ///
+    /// If `total` is larger than both regions combined, only the bytes of the
+    /// regions are released. An error is returned if a read grant is in
+    /// progress.
+    pub fn release_regions(&mut self, total: usize) -> Result<()> {
+        match self.split_read() {
+            Ok(grant) => {
+                grant.release(total);
+                Ok(())
+            }
+            Err(Error::Empty | Error::Disconnected) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Obtain an iterator over committed bytes, which releases the bytes
+    /// as they are yielded.
+    ///
+    /// Bytes are released one region at a time, rather than one byte at a time.
+    /// If the iterator is dropped part way through a region, only the bytes that
+    /// were yielded are released. The iterator continues across the end of the
+    /// buffer, and ends once no more committed bytes are available.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(4).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3, 4]);
+    /// grant.commit(4);
+    ///
+    /// // Take two bytes...
+    /// let sum: u8 = cons.iter().take(2).sum();
+    /// assert_eq!(sum, 3);
+    ///
+    /// // ...leaving the rest in the queue
+    /// assert_eq!(cons.bytes_available(), 2);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn iter(&mut self) -> Drain<'_, 'a, N> {
+        Drain {
+            consumer: self,
+            grant: None,
+            idx: 0,
+        }
+    }
+
+    /// Discard all currently committed bytes, returning the number of bytes
+    /// that were discarded.
+    ///
+    /// Bytes committed concurrently with this call may or may not be discarded.
+    /// An error is returned if a read grant is currently in progress.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.grant_exact(4).unwrap().commit(4);
+    ///
+    /// assert_eq!(cons.flush(), Ok(4));
+    /// assert!(cons.is_empty());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn flush(&mut self) -> Result<usize> {
+        // A split read takes a consistent snapshot of both regions, and
+        // releasing it handles passing through `last`
+        let grant = match self.split_read() {
+            Ok(grant) => grant,
+            Err(Error::Empty | Error::Disconnected) => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let len = grant.combined_len();
+        grant.release(len);
+        Ok(len)
+    }
+
+    /// Obtain a read grant, and pass its contents to `f`. The number of bytes
+    /// returned by `f` are released, and this number is returned.
+    ///
+    /// If `f` returns a number larger than the grant, the full grant will be
+    /// released. If `f` panics, no bytes are released, and the read grant is
+    /// ended, so the `Consumer` remains usable.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(5).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3, 4, 5]);
+    /// grant.commit(5);
+    ///
+    /// // Process two bytes at a time, until fewer than two remain
+    /// let mut sum = 0;
+    /// while let Ok(2) = cons.with_read(|buf| {
+    ///     if buf.len() < 2 {
+    ///         return 0;
+    ///     }
+    ///     sum += buf[0] + buf[1];
+    ///     2
+    /// }) {}
+    ///
+    /// assert_eq!(sum, 10);
+    /// assert_eq!(cons.bytes_available(), 1);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn with_read<F>(&mut self, f: F) -> Result<usize>
+    where
+        F: FnOnce(&[u8]) -> usize,
+    {
+        // If `f` panics, dropping the grant releases nothing
+        let grant = self.read()?;
+        let used = min(f(&grant), grant.len());
+        grant.release(used);
+        Ok(used)
+    }
+
+    /// Copy up to `scratch.len()` committed bytes into `scratch`, and release
+    /// them, returning the number of bytes copied.
+    ///
+    /// If the data wraps around the end of the buffer, both regions are copied,
+    /// so `scratch` will contain the data in order. An error is returned if no
+    /// data is available, or a read grant is already in progress.
+    ///
+    /// See `read_all()` to avoid the copy when the data is contiguous.
+    pub fn read_all_into(&mut self, scratch: &mut [u8]) -> Result<usize> {
+        let grant = self.split_read()?;
+        let used = grant.copy_into(scratch);
+        grant.release(used);
+        Ok(used)
+    }
+
+    /// Obtain all committed bytes as a single contiguous slice.
+    ///
+    /// If all data is contiguous, a read grant is returned, and no copy is
+    /// made. Otherwise, up to `scratch.len()` bytes are copied into `scratch`
+    /// and released, as with `read_all_into()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, ReadAll};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// let mut scratch = [0u8; 6];
+    ///
+    /// prod.write_frame(&[1, 2, 3], &[4]).unwrap();
+    /// cons.skip(3).unwrap();
+    ///
+    /// // The data is contiguous, no copy needed
+    /// match cons.read_all(&mut scratch).unwrap() {
+    ///     ReadAll::Grant(grant) => assert_eq!(&*grant, &[4]),
+    ///     ReadAll::Copied(_) => unreachable!(),
+    /// }
+    ///
+    /// // | 6 | 7 | x | 4 | 5 |
+    /// prod.write_frame(&[5], &[]).unwrap();
+    /// prod.write_frame(&[6], &[7]).unwrap();
+    ///
+    /// // The data is wrapped, and gets copied
+    /// let all = cons.read_all(&mut scratch).unwrap();
+    /// assert!(matches!(all, ReadAll::Copied(_)));
+    /// assert_eq!(&*all, &[4, 5, 6, 7]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_all<'s>(&mut self, scratch: &'s mut [u8]) -> Result<ReadAll<'s, 'a, N>> {
+        let grant = self.split_read()?;
+
+        if grant.buf2.is_empty() {
+            return Ok(ReadAll::Grant(grant.into_first()));
+        }
+
+        let used = grant.copy_into(scratch);
+        grant.release(used);
+        Ok(ReadAll::Copied(&scratch[..used]))
+    }
+
+    /// Write all currently committed bytes into `writer`, releasing them,
+    /// and returning the number of bytes written.
+    ///
+    /// If `writer` returns an error, only the bytes that were actually
+    /// written are released, and the rest remain in the queue. An empty
+    /// queue writes nothing, and returns `Ok(0)`.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&mut self, writer: &mut W) -> std::io::Result<usize> {
+        use std::io::ErrorKind;
+
+        fn write_region<W: std::io::Write>(
+            writer: &mut W,
+            mut buf: &[u8],
+            written: &mut usize,
+        ) -> std::io::Result<()> {
+            // Like `write_all()`, but keeping track of partial progress
+            while !buf.is_empty() {
+                match writer.write(buf) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => {
+                        buf = &buf[n..];
+                        *written += n;
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+
+        let grant = match self.split_read() {
+            Ok(grant) => grant,
+            Err(Error::Empty | Error::Disconnected) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let (buf1, buf2) = grant.bufs();
+        let mut written = 0;
+        let res = write_region(writer, buf1, &mut written)
+            .and_then(|()| write_region(writer, buf2, &mut written));
+
+        grant.release(written);
+        res.map(|()| written)
+    }
+
+    /// Obtains a read grant of all committed bytes up to, and including, the
+    /// first `delim` byte.
+    ///
+    /// If `delim` has not been committed yet, `Ok(None)` is returned, and no
+    /// data is released.
+    ///
+    /// A grant is always contiguous, so if the data before `delim` wraps
+    /// around the end of the buffer, `Error::NotContiguous` is returned, and no
+    /// data is released. In this case, `split_read()` can be used to access
+    /// both parts, or the part before the wrap can be released with `skip()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 16 elements
+    /// let buffer: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.write_frame(b"OK\r\nER", &[]).unwrap();
+    ///
+    /// let line = cons.read_until(b'\n').unwrap().unwrap();
+    /// assert_eq!(&*line, b"OK\r\n");
+    /// line.release(4);
+    ///
+    /// // The next line is not complete yet
+    /// assert!(cons.read_until(b'\n').unwrap().is_none());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_until(&mut self, delim: u8) -> Result<Option<GrantR<'a, N>>> {
+        let grant = match self.split_read() {
+            Ok(grant) => grant,
+            Err(Error::Empty) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if let Some(pos) = grant.buf1.iter().position(|b| *b == delim) {
+            let mut grant = grant.into_first();
+            grant.shrink(pos + 1);
+            return Ok(Some(grant));
+        }
+
+        if grant.buf2.contains(&delim) {
+            return Err(Error::NotContiguous);
+        }
+
+        Ok(None)
+    }
+
+    /// Obtains two disjoint slices, which are each contiguous of committed bytes.
+    /// Combined these contain all previously commited data.
+    pub fn split_read(&mut self) -> Result<SplitGrantR<'a, N>> {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        inner.debug_sanity_check();
+
+        if inner.poisoned.load(Acquire) {
+            return Err(Error::Poisoned);
+        }
+
+        // Only the `Consumer` sets this flag, so it is checked and set with a
+        // load and a store, see `atomic`
+        if inner.read_in_progress.load(Acquire) {
+            return Err(Error::GrantInProgress);
+        }
+        inner.read_in_progress.store(true, Release);
+
+        // Must be loaded before `write`, so that anything committed before
+        // the `Producer` was dropped is seen below
+        let producer_closed = inner.producer_closed.load(Acquire);
+        let (write_idx, last, mut read_idx) = inner.indices();
+
+        // Resolve the inverted case or end of read
+        if is_inverted(write_idx, read_idx) && (position(read_idx) == last) {
+            // Catch up with the lap of the writer
+            read_idx = write_idx & LAP;
+            // This has some room for error, the other thread reads this
+            // Impact to Grant:
+            //   Grant compares the lap flags to see if inverted. If not inverted, but
+            //     no space left, Grant will initiate an inversion, but will not trigger it
+            // Impact to Commit:
+            //   Commit does not check read, but if Grant has started an inversion,
+            //   grant could move Last to the prior write position
+            // MOVING READ BACKWARDS!
+            inner.read.store(read_idx, Release);
+        }
+
+        let inverted = is_inverted(write_idx, read_idx);
+        let (write, read) = (position(write_idx), position(read_idx));
+
+        let (sz1, sz2) = if inverted {
+            // Inverted, only believe last
+            (last - read, write)
+        } else {
+            // Not inverted, only believe write
+            (write - read, 0)
+        };
+
+        if sz1 == 0 {
+            inner.read_in_progress.store(false, Release);
+            return Err(if producer_closed {
+                Error::Disconnected
+            } else {
+                Error::Empty
+            });
+        }
+
+        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
+        // are all `#[repr(Transparent)]
+        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
+        let grant_slice1 = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(read), sz1) };
+        let grant_slice2 = unsafe { from_raw_parts_mut(start_of_buf_ptr, sz2) };
+
+        inner.debug_sanity_check();
+        Ok(SplitGrantR {
+            buf1: grant_slice1,
+            buf2: grant_slice2,
+            bbq: self.bbq,
+            to_release: 0,
+            generation: inner.generation.load(Acquire),
+        })
+    }
+
+    /// Discard up to `n` committed bytes, without obtaining a grant. Returns the
+    /// number of bytes that were actually discarded.
+    ///
+    /// If the committed data wraps around the end of the buffer, both regions
+    /// are discarded from as needed. An error is returned if a read grant is
+    /// currently in progress.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(4).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3, 4]);
+    /// grant.commit(4);
+    ///
+    /// // Skip the first three bytes
+    /// assert_eq!(cons.skip(3), Ok(3));
+    ///
+    /// // Only one byte remains
+    /// assert_eq!(cons.skip(3), Ok(1));
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn skip(&mut self, n: usize) -> Result<usize> {
+        let mut skipped = 0;
+
+        // At most two passes are needed: one for the end of the buffer,
+        // and one for the beginning if the data has wrapped around
+        while skipped < n {
+            let grant = match self.read() {
+                Ok(grant) => grant,
+                Err(Error::Empty | Error::Disconnected) => break,
+                Err(e) => return Err(e),
+            };
+            let len = grant.len();
+            let used = min(n - skipped, len);
+            grant.release(used);
+            skipped += used;
+
+            if used < len {
+                break;
+            }
+        }
+
+        Ok(skipped)
+    }
+
+    /// Obtain a read grant of exactly `header_len` bytes, so that a header
+    /// can be inspected before reading the body that follows it.
+    ///
+    /// If fewer than `header_len` contiguous bytes are available, an error
+    /// will be returned. The header should NOT be released, instead use
+    /// `read_body()` to obtain the body, which releases the header.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.write_frame(&[3], &[1, 2, 3]).unwrap();
+    ///
+    /// // Read the length from the header...
+    /// let header = cons.read_frame_header(1).unwrap();
+    /// let body_len = header[0] as usize;
+    /// drop(header);
+    ///
+    /// // ...then the body
+    /// let body = cons.read_body(body_len).unwrap();
+    /// assert_eq!(&*body, &[1, 2, 3]);
+    /// body.release(body_len);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_frame_header(&mut self, header_len: usize) -> Result<GrantR<'a, N>> {
+        let mut grant = self.read()?;
+
+        if grant.len() < header_len {
             return Err(Error::InsufficientSize);
         }
+        grant.shrink(header_len);
 
-        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
-        // are all `#[repr(Transparent)]
-        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
-        let grant_slice = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(read), sz) };
+        // `read()` may have moved the read position back to the start of the buffer
+        let inner = unsafe { &self.bbq.as_ref() };
+        self.pending_header = Some((inner.read.load(Acquire), header_len));
 
-        Ok(GrantR {
-            buf: grant_slice,
-            bbq: self.bbq,
-            to_release: 0,
-        })
+        Ok(grant)
     }
 
-    /// Obtains two disjoint slices, which are each contiguous of committed bytes.
-    /// Combined these contain all previously commited data.
-    pub fn split_read(&mut self) -> Result<SplitGrantR<'a, N>> {
+    /// Obtain a read grant of exactly `body_len` bytes, following the header
+    /// obtained with `read_frame_header()`.
+    ///
+    /// The header is released once the body is available, so it can not be
+    /// read twice. If the header was already released, or no header was read,
+    /// the body is read from the current read position.
+    ///
+    /// If fewer than `body_len` contiguous bytes are available after the header,
+    /// an error will be returned, and the header remains unreleased.
+    pub fn read_body(&mut self, body_len: usize) -> Result<GrantR<'a, N>> {
         let inner = unsafe { &self.bbq.as_ref() };
 
-        if atomic::swap(&inner.read_in_progress, true, AcqRel) {
-            return Err(Error::GrantInProgress);
-        }
-
-        let write = inner.write.load(Acquire);
-        let last = inner.last.load(Acquire);
-        let mut read = inner.read.load(Acquire);
+        // The header only needs to be skipped if it hasn't been released already
+        let header_len = match self.pending_header {
+            Some((pos, len)) if inner.read.load(Acquire) == pos => len,
+            _ => 0,
+        };
 
-        // Resolve the inverted case or end of read
-        if (read == last) && (write < read) {
-            read = 0;
-            // This has some room for error, the other thread reads this
-            // Impact to Grant:
-            //   Grant checks if read < write to see if inverted. If not inverted, but
-            //     no space left, Grant will initiate an inversion, but will not trigger it
-            // Impact to Commit:
-            //   Commit does not check read, but if Grant has started an inversion,
-            //   grant could move Last to the prior write position
-            // MOVING READ BACKWARDS!
-            inner.read.store(0, Release);
-        }
+        let grant = self.split_read()?;
+        let (buf1, buf2) = grant.bufs();
 
-        let (sz1, sz2) = if write < read {
-            // Inverted, only believe last
-            (last - read, write)
+        // The body may start at the beginning of the buffer, if the
+        // header ends exactly at the end of the first region
+        let available = if header_len < buf1.len() {
+            buf1.len() - header_len
         } else {
-            // Not inverted, only believe write
-            (write - read, 0)
+            buf2.len()
         };
 
-        if sz1 == 0 {
-            inner.read_in_progress.store(false, Release);
+        if available < body_len {
             return Err(Error::InsufficientSize);
         }
 
-        // This is sound, as UnsafeCell, MaybeUninit, and GenericArray
-        // are all `#[repr(Transparent)]
-        let start_of_buf_ptr = inner.buf.get().cast::<u8>();
-        let grant_slice1 = unsafe { from_raw_parts_mut(start_of_buf_ptr.add(read), sz1) };
-        let grant_slice2 = unsafe { from_raw_parts_mut(start_of_buf_ptr, sz2) };
+        grant.release(header_len);
+        self.pending_header = None;
 
-        Ok(SplitGrantR {
-            buf1: grant_slice1,
-            buf2: grant_slice2,
-            bbq: self.bbq,
-            to_release: 0,
-        })
+        let mut grant = self.read()?;
+        grant.shrink(body_len);
+        Ok(grant)
+    }
+
+    /// Returns the total number of committed bytes that have not yet been
+    /// released. If the data has wrapped around the end of the buffer, this
+    /// includes both regions, and may be larger than a single `read()` grant.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// assert!(cons.is_empty());
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// assert_eq!(cons.bytes_available(), 4);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn bytes_available(&self) -> usize {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.committed_len()
+    }
+
+    /// Returns `true` if there are no committed bytes available to read
+    pub fn is_empty(&self) -> bool {
+        self.bytes_available() == 0
+    }
+
+    /// The number of bytes the `Producer` has dropped for lack of space. A
+    /// grant that fails with `Error::InsufficientSize` adds the requested
+    /// size, and `Producer::record_lost()` adds any other bytes. Waiting
+    /// grants, such as `Producer::grant_timeout()`, only count once they give
+    /// up. The count wraps around on overflow.
+    #[cfg(feature = "stats")]
+    pub fn bytes_lost(&self) -> usize {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.bytes_lost.load(Acquire)
+    }
+
+    /// Returns the current read position, as the total number of
+    /// bytes released from the queue
+    #[cfg(feature = "stats")]
+    pub fn cursor(&self) -> Cursor {
+        let inner = unsafe { &self.bbq.as_ref() };
+        Cursor {
+            position: inner.read_total.load(),
+        }
+    }
+
+    /// Signal the `Producer` that no more data will be read
+    ///
+    /// Any further grants from the `Producer` return `Error::Disconnected`.
+    /// Data that has already been committed can still be read. Dropping the
+    /// `Consumer` closes it as well.
+    pub fn close(&mut self) {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.consumer_closed.store(true, Release);
+        event::signal();
+    }
+
+    /// Has the `Producer` been closed or dropped?
+    ///
+    /// Data committed before closing may still be available to read.
+    pub fn is_producer_closed(&self) -> bool {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.producer_closed.load(Acquire)
+    }
+
+    /// Wake `waker` once at least `min_bytes` committed bytes are available
+    ///
+    /// The waker is woken once, from within the `commit()` that reaches the
+    /// threshold, or immediately if enough bytes are already available.
+    /// Only one waker can be subscribed, subscribing again replaces it. The
+    /// waker may occasionally be woken early, if a new subscription races
+    /// with a commit.
+    ///
+    /// This does not require an async executor, any source of `Waker`s can
+    /// be used.
+    ///
+    /// This requires the `subscribe` feature, as every `commit()` has to pay
+    /// for a full memory barrier with it, to tell whether a waker has been
+    /// subscribed meanwhile.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    /// use std::{
+    ///     sync::{
+    ///         atomic::{AtomicBool, Ordering},
+    ///         Arc,
+    ///     },
+    ///     task::Wake,
+    /// };
+    ///
+    /// struct Flag(AtomicBool);
+    ///
+    /// impl Wake for Flag {
+    ///     fn wake(self: Arc<Self>) {
+    ///         self.0.store(true, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let flag = Arc::new(Flag(AtomicBool::new(false)));
+    /// cons.subscribe(4, flag.clone().into());
+    ///
+    /// prod.grant_exact(3).unwrap().commit(3);
+    /// assert!(!flag.0.load(Ordering::Relaxed));
+    ///
+    /// prod.grant_exact(1).unwrap().commit(1);
+    /// assert!(flag.0.load(Ordering::Relaxed));
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "subscribe")]
+    pub fn subscribe(&mut self, min_bytes: usize, waker: Waker) {
+        let inner = unsafe { &self.bbq.as_ref() };
+        inner.subscriber.register(min_bytes, waker);
+
+        // Make sure either the `Producer` sees this subscription, or this
+        // sees the latest commit
+        fence(SeqCst);
+        inner.subscriber.wake_if(|| inner.committed_len());
     }
 }
 
@@ -681,6 +2662,446 @@ impl<const N: usize> BBBuffer<N> {
     pub const fn capacity(&self) -> usize {
         N
     }
+
+    /// Returns `true` if a grant committed or released bytes while panicking,
+    /// e.g. when it was dropped while unwinding after `GrantW::to_commit()`
+    ///
+    /// The data in the queue may then be incomplete, so both halves return
+    /// `Error::Poisoned` from any further grants, until `clear_poison()` is
+    /// called. A grant dropped while unwinding without committing or
+    /// releasing anything leaves the queue as it was, and does not poison it.
+    /// Panics can only be detected with the `std` feature. Releasing a stale
+    /// read grant, one which no longer starts at the read position, also
+    /// poisons the queue, without releasing any bytes. `GrantR::release_checked()`
+    /// returns `Error::StaleGrant` for it instead.
+    ///
+    /// A grant that is leaked, e.g. with `core::mem::forget()`, is never
+    /// committed or released, so it can not be detected and does not poison
+    /// the queue. Its half instead returns `Error::GrantInProgress` from every
+    /// further grant, and `try_release()` fails, for as long as the buffer
+    /// exists. `clear_poison()` does not recover from this, so grants must not
+    /// be leaked.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let res = catch_unwind(AssertUnwindSafe(|| {
+    ///     let mut grant = prod.grant_exact(4).unwrap();
+    ///     grant.to_commit(4);
+    ///     grant[0] = 1;
+    ///     panic!("interrupted while writing");
+    /// }));
+    /// assert!(res.is_err());
+    ///
+    /// assert!(buffer.is_poisoned());
+    /// assert_eq!(cons.read().unwrap_err(), Error::Poisoned);
+    ///
+    /// // Discard the partially written data, and continue
+    /// buffer.clear_poison();
+    /// cons.read().unwrap().release(4);
+    /// assert_eq!(cons.read().unwrap_err(), Error::Empty);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(all(feature = "std", not(feature = "thumbv6")))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Acquire)
+    }
+
+    /// Clear the poisoned state, see `is_poisoned()`
+    ///
+    /// This should only be done if the data in the queue is known to be
+    /// consistent, or will be discarded. It does not end a grant that was
+    /// leaked rather than dropped. Unlike `try_release()`, it leaves the
+    /// indices of the queue as they are, so grants held across it remain
+    /// valid, rather than stale.
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Release);
+    }
+
+    /// Sample the fill level of the queue, the committed bytes as a fraction
+    /// of the capacity, and return its exponentially weighted moving average
+    /// in the range `[0.0, 1.0]`.
+    ///
+    /// Each call moves the average towards the current fill level by the
+    /// weight `alpha`, which is clamped to `[0.0, 1.0]`. A larger `alpha`
+    /// follows changes more quickly, while a smaller one smooths out short
+    /// bursts. The average starts out at zero, and can be sampled from
+    /// either half, or a monitoring task.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let buffer: BBBuffer<8> = BBBuffer::new();
+    /// let (mut prod, cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(8).unwrap().commit(8);
+    ///
+    /// // Halfway from empty to full
+    /// assert_eq!(buffer.fill_level_ewma(0.5), 0.5);
+    /// assert_eq!(buffer.fill_level_ewma(0.5), 0.75);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn fill_level_ewma(&self, alpha: f32) -> f32 {
+        let alpha = if alpha.is_nan() {
+            0.0
+        } else {
+            alpha.clamp(0.0, 1.0)
+        };
+        let sample = if N == 0 {
+            0.0
+        } else {
+            self.committed_len() as f32 / N as f32
+        };
+
+        let mut prev = self.fill_ewma.load(Acquire);
+        loop {
+            let avg = prev as f32 / FILL_ONE as f32;
+            let avg = avg + alpha * (sample - avg);
+            let next = min((avg * FILL_ONE as f32 + 0.5) as usize, FILL_ONE);
+
+            // Concurrent samples are all applied, in any order
+            match atomic::compare_exchange(&self.fill_ewma, prev, next, AcqRel) {
+                Ok(_) => return next as f32 / FILL_ONE as f32,
+                Err(actual) => prev = actual,
+            }
+        }
+    }
+
+    /// Register a `callback` to be called when a commit causes the number of
+    /// committed (but not yet released) bytes to rise to `threshold` or above.
+    ///
+    /// This can be used by a producer to know when to stop writing. Only one
+    /// high watermark can be registered, registering another one replaces it.
+    /// It stays registered when the buffer is released and split again.
+    ///
+    /// NOTE: The callback is called from within `commit()`, in whatever
+    /// context the producer is running (e.g. an interrupt), and should
+    /// return quickly.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    /// use core::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static FULL: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn on_full() {
+    ///     FULL.store(true, Ordering::Relaxed);
+    /// }
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// buffer.register_high_watermark(4, on_full);
+    /// let (mut prod, cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(3).unwrap().commit(3);
+    /// assert!(!FULL.load(Ordering::Relaxed));
+    ///
+    /// prod.grant_exact(1).unwrap().commit(1);
+    /// assert!(FULL.load(Ordering::Relaxed));
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "watermarks")]
+    pub fn register_high_watermark(&self, threshold: usize, callback: fn()) {
+        // Disable the callback while the threshold is changed
+        self.high_watermark_cb.store(null_mut(), Release);
+        self.high_watermark.store(threshold, Release);
+        self.high_watermark_cb
+            .store(callback as *const () as *mut (), Release);
+    }
+
+    /// Register a `callback` to be called when a release causes the number of
+    /// committed (but not yet released) bytes to fall to `threshold` or below.
+    ///
+    /// This can be used to know when a producer may resume writing. Only one
+    /// low watermark can be registered, registering another one replaces it.
+    /// It stays registered when the buffer is released and split again.
+    ///
+    /// NOTE: The callback is called from within `release()`, in whatever
+    /// context the consumer is running (e.g. an interrupt), and should
+    /// return quickly.
+    #[cfg(feature = "watermarks")]
+    pub fn register_low_watermark(&self, threshold: usize, callback: fn()) {
+        // Disable the callback while the threshold is changed
+        self.low_watermark_cb.store(null_mut(), Release);
+        self.low_watermark.store(threshold, Release);
+        self.low_watermark_cb
+            .store(callback as *const () as *mut (), Release);
+    }
+
+    /// Release `used` bytes from the current read position, ending the read
+    /// grant in progress. The bytes must not cross the end of the region.
+    fn release_contiguous(&self, used: usize) {
+        self.debug_sanity_check();
+
+        // This should be fine, purely incrementing. Only the `Consumer`
+        // writes `read`, so this does not need a read-modify-write.
+        let read = self.read.load(Acquire);
+
+        // Never move `read` past the committed data, which would leave the
+        // indices in a state the queue can not recover from
+        let used = min(used, self.contiguous_len(read));
+        let _guard = PoisonGuard::new(&self.poisoned, used);
+        self.read.store(read + used, Release);
+        #[cfg(feature = "stats")]
+        self.read_total.add(used);
+
+        self.read_in_progress.store(false, Release);
+        event::signal();
+        self.debug_sanity_check();
+
+        #[cfg(feature = "watermarks")]
+        self.check_low_watermark(used);
+        self.update_data_ready();
+    }
+
+    /// The number of committed bytes from `read` up to the end of its region,
+    /// which is `last` when inverted
+    fn contiguous_len(&self, read: usize) -> usize {
+        // Write must be loaded BEFORE last, as `commit` moves last
+        // before write when inverting
+        let write = self.write.load(Acquire);
+        let last = self.last.load(Acquire);
+
+        let limit = if is_inverted(write, read) {
+            last
+        } else {
+            position(write)
+        };
+        limit.saturating_sub(position(read))
+    }
+
+    /// Was a grant of this `generation` handed out since the last `try_release()`?
+    /// Older grants must not commit or release anything.
+    fn is_current(&self, generation: usize) -> bool {
+        self.generation.load(Acquire) == generation
+    }
+
+    /// Move `write` and `read` back to the start of the buffer if the queue is
+    /// empty, returning `true` if they are both at the start. Only called by
+    /// the `Producer`, while it has no outstanding write grant.
+    fn compact(&self) -> bool {
+        let write = self.write.load(Acquire);
+        let read = self.read.load(Acquire);
+
+        // The `Consumer` can not obtain a grant while the queue is empty
+        if write != read || self.read_in_progress.load(Acquire) {
+            return false;
+        }
+        if position(write) == 0 {
+            return true;
+        }
+
+        // First wrap around without committing anything, which the `Consumer`
+        // handles like any other inversion, by moving `read` back itself
+        let lap = (write & LAP) ^ LAP;
+        self.last.store(position(write), Release);
+        self.reserve.store(lap, Release);
+        self.write.store(lap, Release);
+
+        // Then move `read` on its behalf, unless it was faster. Both store
+        // the same value, and the `Consumer` can not move `read` any further
+        // until something is committed, which only happens after this
+        // returns, so a plain store can not undo its progress.
+        self.read.store(lap, Release);
+        true
+    }
+
+    /// Load a consistent snapshot of `write`, `last` and `read`
+    ///
+    /// `read` is normally only moved by the `Consumer`, but `compact()` may
+    /// move it back to the start of an empty queue, after `write`. A `read`
+    /// loaded before that, together with a `write` or `last` loaded after,
+    /// would describe a queue holding bytes that were never committed, so the
+    /// snapshot is retried if `read` moved while it was taken.
+    fn indices(&self) -> (usize, usize, usize) {
+        loop {
+            let read = self.read.load(Acquire);
+            // Write must be loaded BEFORE last, as `commit` moves last
+            // before write when inverting
+            let write = self.write.load(Acquire);
+            let last = self.last.load(Acquire);
+            if self.read.load(Acquire) == read {
+                return (write, last, read);
+            }
+        }
+    }
+
+    /// Check the invariants of the indices, panicking with the name of the
+    /// violated invariant and the current indices if any of them does not
+    /// hold. This is also run at the start and end of every grant, commit
+    /// and release in builds with debug assertions.
+    ///
+    /// The invariants are:
+    ///
+    /// * `write`, `read`, `reserve` and `last` are within the buffer
+    /// * When not inverted, `read <= write`: the committed bytes are `read..write`
+    /// * When inverted, `write <= read <= last`: the committed bytes are
+    ///   `read..last`, followed by `0..write`
+    ///
+    /// They hold at any time, from either half, even while the other half is
+    /// in use.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// prod.grant_exact(4).unwrap().commit(4);
+    /// cons.read().unwrap().release(3);
+    /// buffer.sanity_check();
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn sanity_check(&self) {
+        let (write, last, read) = self.indices();
+        let reserve = self.reserve.load(Acquire);
+
+        let (w, r) = (position(write), position(read));
+        let check = |ok: bool, invariant: &str| {
+            assert!(
+                ok,
+                "invariant `{}` violated: write={} read={} last={} reserve={} inverted={}",
+                invariant,
+                w,
+                r,
+                last,
+                position(reserve),
+                is_inverted(write, read),
+            );
+        };
+
+        check(w <= N, "write <= N");
+        check(r <= N, "read <= N");
+        check(last <= N, "last <= N");
+        check(position(reserve) <= N, "reserve <= N");
+
+        if is_inverted(write, read) {
+            check(w <= r, "write <= read when inverted");
+            check(r <= last, "read <= last when inverted");
+        } else {
+            check(r <= w, "read <= write when not inverted");
+        }
+    }
+
+    /// Drive the pin registered with `Producer::set_data_ready_pin()`, if any
+    #[inline(always)]
+    fn update_data_ready(&self) {
+        #[cfg(feature = "embedded_hal_1")]
+        self.data_ready.update(|| self.committed_len() != 0);
+    }
+
+    /// Runs `sanity_check()` in builds with debug assertions
+    #[inline(always)]
+    fn debug_sanity_check(&self) {
+        #[cfg(debug_assertions)]
+        self.sanity_check();
+    }
+
+    /// Check the invariants of `sanity_check()`, and those that only hold
+    /// while neither half is being used concurrently
+    #[cfg(feature = "fuzz")]
+    pub(crate) fn check_invariants(&self) {
+        self.sanity_check();
+
+        if !self.write_in_progress.load(Acquire) {
+            let (write, reserve) = (self.write.load(Acquire), self.reserve.load(Acquire));
+            assert_eq!(reserve, write, "reserve moved without a write grant");
+        }
+
+        #[cfg(feature = "stats")]
+        {
+            let committed = self.write_total.load() - self.read_total.load();
+            assert_eq!(committed, self.committed_len() as u64, "bytes were lost");
+        }
+    }
+
+    /// The number of committed bytes that have not yet been released, in both
+    /// regions if the data has wrapped around the end of the buffer
+    fn committed_len(&self) -> usize {
+        let (write, last, read) = self.indices();
+
+        if is_inverted(write, read) {
+            // Inverted, the end region is bounded by last
+            (last - position(read)) + position(write)
+        } else {
+            position(write) - position(read)
+        }
+    }
+
+    /// Call the high watermark callback, if `committed` bytes were just
+    /// committed, and this crossed the registered threshold
+    #[cfg(feature = "watermarks")]
+    fn check_high_watermark(&self, committed: usize) {
+        let cb = self.high_watermark_cb.load(Acquire);
+        if cb.is_null() || committed == 0 {
+            return;
+        }
+
+        let threshold = self.high_watermark.load(Acquire);
+        let after = self.committed_len();
+        let before = after.saturating_sub(committed);
+
+        if before < threshold && after >= threshold {
+            // This is sound, as the pointer was created from a `fn()`
+            let cb = unsafe { transmute::<*mut (), fn()>(cb) };
+            cb();
+        }
+    }
+
+    /// Call the low watermark callback, if `released` bytes were just
+    /// released, and this crossed the registered threshold
+    #[cfg(feature = "watermarks")]
+    fn check_low_watermark(&self, released: usize) {
+        let cb = self.low_watermark_cb.load(Acquire);
+        if cb.is_null() || released == 0 {
+            return;
+        }
+
+        let threshold = self.low_watermark.load(Acquire);
+        let after = self.committed_len();
+        let before = after + released;
+
+        if before > threshold && after <= threshold {
+            // This is sound, as the pointer was created from a `fn()`
+            let cb = unsafe { transmute::<*mut (), fn()>(cb) };
+            cb();
+        }
+    }
 }
 
 /// A structure representing a contiguous region of memory that
@@ -691,15 +3112,67 @@ impl<const N: usize> BBBuffer<N> {
 /// automatically be committed with `to_commit()`, then no bytes
 /// will be comitted for writing.
 ///
-/// If the `thumbv6` feature is selected, dropping the grant
-/// without committing it takes a short critical section,
+/// The contents of the grant are accessed through `DerefMut` or
+/// `AsMut<[u8]>`, and the resulting slice borrows the grant, so it can not be
+/// written after the grant has been committed, while the consumer may be
+/// reading the same bytes:
+///
+/// ```rust,compile_fail
+/// use bbqueue::BBBuffer;
+///
+/// let buffer: BBBuffer<6> = BBBuffer::new();
+/// let (mut prod, mut cons) = buffer.try_split().unwrap();
+///
+/// let mut grant = prod.grant_exact(4).unwrap();
+/// let data: &mut [u8] = &mut grant;
+/// grant.commit(4);
+///
+/// // ERROR: `grant` was moved while still borrowed
+/// data[0] = 1;
+/// ```
+///
+/// Neither can the slice be stored with a longer lifetime than the grant,
+/// such as in a `static`:
+///
+/// ```rust,compile_fail
+/// use bbqueue::BBBuffer;
+///
+/// static BB: BBBuffer<6> = BBBuffer::new();
+/// static mut STASH: &mut [u8] = &mut [];
+///
+/// let (mut prod, mut cons) = BB.try_split().unwrap();
+/// let mut grant = prod.grant_exact(4).unwrap();
+///
+/// // ERROR: `grant` does not live long enough
+/// unsafe { STASH = &mut grant };
+/// grant.commit(4);
+/// ```
+///
+/// A grant is `Send`, so it may be committed by another thread, or by an
+/// interrupt handler, than the one that requested it. It is not `Sync`, as
+/// only one context at a time should be writing to it.
+///
+/// Committing the grant, or dropping it without committing, only updates the
+/// queue with atomic loads and stores, so no critical section is taken with
+/// the `thumbv6` or `critical-section` feature, unless the `embedded_hal_1`
+/// feature is enabled, see `Producer::set_data_ready_pin()`.
 #[derive(Debug, PartialEq)]
 pub struct GrantW<'a, const N: usize> {
     pub(crate) buf: &'a mut [u8],
     bbq: NonNull<BBBuffer<N>>,
     pub(crate) to_commit: usize,
+
+    /// The position of the typed write helpers, such as `write_u8()`
+    write_pos: usize,
+
+    /// `BBBuffer::generation` when the grant was handed out
+    generation: usize,
 }
 
+// SAFETY: The grant has exclusive access to its bytes until it is committed,
+// and the indices it updates are atomics shared with the `Consumer`, so it
+// can be committed from any thread, such as the one of an interrupt that
+// completes a DMA transfer. The `BBBuffer` is `Sync`, and outlives `'a`.
 unsafe impl<'a, const N: usize> Send for GrantW<'a, N> {}
 
 /// A structure representing a contiguous region of memory that
@@ -711,14 +3184,101 @@ unsafe impl<'a, const N: usize> Send for GrantW<'a, N> {}
 /// be released with `to_release()`, then no bytes will be released
 /// as read.
 ///
+/// The contents of the grant are accessed through `Deref` or `AsRef<[u8]>`,
+/// and the resulting slice borrows the grant, so it can not be used after
+/// the grant has been released:
+///
+/// ```rust,compile_fail
+/// use bbqueue::BBBuffer;
+///
+/// let buffer: BBBuffer<6> = BBBuffer::new();
+/// let (mut prod, mut cons) = buffer.try_split().unwrap();
+/// prod.grant_exact(4).unwrap().commit(4);
+///
+/// let grant = cons.read().unwrap();
+/// let data: &[u8] = &grant;
+/// grant.release(4);
 ///
-/// If the `thumbv6` feature is selected, dropping the grant
-/// without releasing it takes a short critical section,
+/// // ERROR: `grant` was moved while still borrowed
+/// assert_eq!(data[0], 0);
+/// ```
+///
+/// A grant is `Send`, so it may be released by another thread, or by an
+/// interrupt handler, than the one that requested it, for example once a DMA
+/// transfer of its bytes has completed. It is not `Sync`.
+///
+/// Releasing the grant, or dropping it without releasing, only updates the
+/// queue with atomic loads and stores, as for a `GrantW`.
 #[derive(Debug, PartialEq)]
 pub struct GrantR<'a, const N: usize> {
     pub(crate) buf: &'a mut [u8],
     bbq: NonNull<BBBuffer<N>>,
     pub(crate) to_release: usize,
+
+    /// The position of the typed read helpers, such as `read_u8()`
+    read_pos: usize,
+
+    /// The value of `read` when the grant was handed out, including its lap
+    /// flag, so that a stale grant can not release bytes
+    start: usize,
+
+    /// Bytes directly following `buf`, which are not part of the grant, but
+    /// are released together with its last byte, such as a checksum
+    pub(crate) trailer: usize,
+
+    /// `BBBuffer::generation` when the grant was handed out
+    generation: usize,
+}
+
+/// All committed data of a `Consumer`, as a single contiguous slice, created
+/// by `Consumer::read_all()`
+#[derive(Debug, PartialEq)]
+pub enum ReadAll<'s, 'a, const N: usize> {
+    /// The data was contiguous, and is available through a read grant
+    Grant(GrantR<'a, N>),
+
+    /// The data wrapped around the end of the buffer, and was copied into
+    /// the scratch buffer. The copied bytes have already been released.
+    Copied(&'s [u8]),
+}
+
+impl<'s, 'a, const N: usize> Deref for ReadAll<'s, 'a, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ReadAll::Grant(grant) => grant,
+            ReadAll::Copied(buf) => buf,
+        }
+    }
+}
+
+/// One part of a read grant, created by `GrantR::split_at()`
+///
+/// NOTE: If a part is dropped without explicitly releasing it, its bytes
+/// are not released. As bytes can only be released from the start of the
+/// original grant, dropping the first part means no bytes are released.
+#[cfg(feature = "split_grants")]
+#[derive(Debug, PartialEq)]
+pub struct GrantRPart<'a, const N: usize> {
+    buf: &'a mut [u8],
+    bbq: NonNull<BBBuffer<N>>,
+    head_len: usize,
+    total_len: usize,
+    released_flag: usize,
+    generation: usize,
+}
+
+// SAFETY: See `GrantR`
+#[cfg(feature = "split_grants")]
+unsafe impl<'a, const N: usize> Send for GrantRPart<'a, N> {}
+
+/// A draining iterator over the committed bytes of a `Consumer`, created
+/// by `Consumer::iter()`
+pub struct Drain<'c, 'a, const N: usize> {
+    consumer: &'c mut Consumer<'a, N>,
+    grant: Option<GrantR<'a, N>>,
+    idx: usize,
 }
 
 /// A structure representing up to two contiguous regions of memory that
@@ -730,10 +3290,14 @@ pub struct SplitGrantR<'a, const N: usize> {
     pub(crate) buf2: &'a mut [u8],
     bbq: NonNull<BBBuffer<N>>,
     pub(crate) to_release: usize,
+    generation: usize,
 }
 
+// SAFETY: As for `GrantW`, the bytes of a read grant are not written by the
+// `Producer` until they are released, which only updates atomics
 unsafe impl<'a, const N: usize> Send for GrantR<'a, N> {}
 
+// SAFETY: See `GrantR`
 unsafe impl<'a, const N: usize> Send for SplitGrantR<'a, N> {}
 
 impl<'a, const N: usize> GrantW<'a, N> {
@@ -744,13 +3308,98 @@ impl<'a, const N: usize> GrantW<'a, N> {
     /// If `used` is larger than the given grant, the maximum amount will
     /// be commited
     ///
-    /// NOTE:  If the `thumbv6` feature is selected, this function takes a short critical
-    /// section while committing.
+    /// NOTE: This takes no critical section with the `thumbv6` or `critical-section` feature,
+    /// see `GrantW`.
     pub fn commit(mut self, used: usize) {
         self.commit_inner(used);
         forget(self);
     }
 
+    /// Finalizes a writable grant, as with `commit()`. This consumes the grant.
+    ///
+    /// Unlike `commit()`, `used` is not saturated. If `used` is larger than
+    /// the given grant, nothing is committed and the grant is returned along
+    /// with `Error::InsufficientSize`. A stale grant, one which was handed out
+    /// before the buffer was released with `BBBuffer::try_release()`, is
+    /// returned along with `Error::StaleGrant` instead. Dropping it does not
+    /// affect the queue.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Committing too much hands the grant back
+    /// let grant = prod.grant_exact(4).unwrap();
+    /// let (err, grant) = grant.commit_checked(5).unwrap_err();
+    /// assert_eq!(err, Error::InsufficientSize);
+    ///
+    /// // Which can then be committed correctly
+    /// assert!(grant.commit_checked(4).is_ok());
+    /// assert_eq!(cons.read().unwrap().len(), 4);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn commit_checked(mut self, used: usize) -> CoreResult<(), (Error, Self)> {
+        let inner = unsafe { &self.bbq.as_ref() };
+        if !inner.is_current(self.generation) {
+            return Err((Error::StaleGrant, self));
+        }
+        if used > self.buf.len() {
+            return Err((Error::InsufficientSize, self));
+        }
+
+        self.commit_inner(used);
+        forget(self);
+        Ok(())
+    }
+
+    /// Commit the first `used` bytes of the grant, followed by their CRC-32
+    ///
+    /// The checksum is written in little endian order to the four bytes
+    /// following the payload, so the grant must have been requested with four
+    /// extra bytes, otherwise this panics. See
+    /// `GrantR::release_and_verify_crc32()` for the reading side.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 16 elements
+    /// let buffer: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(3 + 4).unwrap();
+    /// grant[..3].copy_from_slice(&[1, 2, 3]);
+    /// grant.commit_with_crc32(3);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(grant.len(), 7);
+    /// assert!(grant.release_and_verify_crc32(3).is_ok());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn commit_with_crc32(self, used: usize) {
+        let crc = crc32(&self.buf[..used]);
+        self.buf[used..used + 4].copy_from_slice(&crc.to_le_bytes());
+        self.commit(used + 4);
+    }
+
     /// Obtain access to the inner buffer for writing
     ///
     /// ```rust
@@ -774,7 +3423,312 @@ impl<'a, const N: usize> GrantW<'a, N> {
     /// # bbqtest();
     /// # }
     /// ```
-    pub fn buf(&mut self) -> &mut [u8] {
+    pub fn buf(&mut self) -> &mut [u8] {
+        self.buf
+    }
+
+    /// Fill the entire grant with `value`, returning the grant to allow
+    /// for chaining
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Zero a grant of four bytes, then set the first byte
+    /// let mut grant = prod.grant_exact(4).unwrap();
+    /// grant.fill(0)[0] = 1;
+    /// grant.commit(4);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(grant.buf(), &[1, 0, 0, 0]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn fill(&mut self, value: u8) -> &mut Self {
+        self.buf.fill(value);
+        self
+    }
+
+    /// Copy as much of `src` as fits to the start of the grant, returning the
+    /// number of bytes copied
+    ///
+    /// Unlike `copy_from_slice()`, this does not panic if the lengths differ,
+    /// which suits grants that may be shorter than the data, such as those of
+    /// `Producer::grant_max_remaining()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Only the first four bytes fit
+    /// let data = [1, 2, 3, 4, 5, 6, 7, 8];
+    /// let mut grant = prod.grant_max_remaining(4).unwrap();
+    /// let copied = grant.copy_from_slice_partial(&data);
+    /// assert_eq!(copied, 4);
+    /// grant.commit(copied);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, &[1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn copy_from_slice_partial(&mut self, src: &[u8]) -> usize {
+        let len = min(src.len(), self.buf.len());
+        self.buf[..len].copy_from_slice(&src[..len]);
+        len
+    }
+
+    /// Divide the grant into two mutable slices at `mid`, so that each can be
+    /// filled independently, such as a header and a body. The whole grant is
+    /// still committed at once, with `commit()`.
+    ///
+    /// Panics if `mid` is larger than the grant.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(5).unwrap();
+    /// let (header, body) = grant.split_at_mut(1);
+    /// body.copy_from_slice(&[1, 2, 3, 4]);
+    /// header[0] = body.len() as u8;
+    /// grant.commit(5);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, &[4, 1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn split_at_mut(&mut self, mid: usize) -> (&mut [u8], &mut [u8]) {
+        self.buf.split_at_mut(mid)
+    }
+
+    /// Obtain a cursor for incrementally writing to the grant
+    ///
+    /// The cursor always starts at the beginning of the grant, and its writes
+    /// return `Error::InsufficientSize` instead of panicking. It keeps its own
+    /// position, separate from the chainable typed write helpers on the grant
+    /// such as `write_u8()`, so use one or the other for a given grant.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 8 elements
+    /// let buffer: BBBuffer<8> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(7).unwrap();
+    /// let mut cursor = grant.cursor();
+    /// cursor.write_u8(0xAA).unwrap();
+    /// cursor.write_u32_le(0x04030201).unwrap();
+    ///
+    /// // Only commit what was written
+    /// let written = cursor.written();
+    /// grant.commit(written);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, &[0xAA, 1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn cursor(&mut self) -> WriteCursor<'_> {
+        WriteCursor::new(self.buf)
+    }
+
+    /// Write all of `src` at the current write position, and advance it,
+    /// returning the grant to allow for chaining
+    ///
+    /// This and the other typed write helpers panic if the data does not fit
+    /// in the remainder of the grant. When the size of the data is not known
+    /// to fit up front, use the fallible methods of the same name on the
+    /// `WriteCursor` returned by `cursor()` instead.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 8 elements
+    /// let buffer: BBBuffer<8> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(8).unwrap();
+    /// grant.write_u8(0xAA).write_u16_le(0x0201).write_bytes(&[3, 4]);
+    ///
+    /// let written = grant.written();
+    /// grant.commit(written);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, &[0xAA, 1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn write_bytes(&mut self, src: &[u8]) -> &mut Self {
+        let mut cursor = WriteCursor::new(&mut self.buf[self.write_pos..]);
+        assert!(cursor.write_bytes(src).is_ok(), "write exceeds the grant");
+        self.write_pos += cursor.written();
+        self
+    }
+
+    /// Write a single byte, see `write_bytes()`
+    pub fn write_u8(&mut self, val: u8) -> &mut Self {
+        self.write_bytes(&[val])
+    }
+
+    /// Write a `u16`, in little endian order, see `write_bytes()`
+    pub fn write_u16_le(&mut self, val: u16) -> &mut Self {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u32`, in little endian order, see `write_bytes()`
+    pub fn write_u32_le(&mut self, val: u32) -> &mut Self {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u64`, in little endian order, see `write_bytes()`
+    pub fn write_u64_le(&mut self, val: u64) -> &mut Self {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u16`, in big endian order, see `write_bytes()`
+    pub fn write_u16_be(&mut self, val: u16) -> &mut Self {
+        self.write_bytes(&val.to_be_bytes())
+    }
+
+    /// Write a `u32`, in big endian order, see `write_bytes()`
+    pub fn write_u32_be(&mut self, val: u32) -> &mut Self {
+        self.write_bytes(&val.to_be_bytes())
+    }
+
+    /// Write a `u64`, in big endian order, see `write_bytes()`
+    pub fn write_u64_be(&mut self, val: u64) -> &mut Self {
+        self.write_bytes(&val.to_be_bytes())
+    }
+
+    /// Write the bytes of `val`, see `write_bytes()`
+    #[cfg(feature = "zerocopy_0_7")]
+    pub fn write_as<T: zerocopy::AsBytes + ?Sized>(&mut self, val: &T) -> &mut Self {
+        self.write_bytes(val.as_bytes())
+    }
+
+    /// Write formatted text at the current write position, and advance it.
+    /// This allows for using the `write!()` macro with a grant.
+    ///
+    /// Unlike the typed write helpers, this does not panic, but returns an
+    /// error if the text does not fit in the remainder of the grant. Any
+    /// text that did fit has still been written, see `written()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 16 elements
+    /// let buffer: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(8).unwrap();
+    /// write!(grant, "temp={}", 21).unwrap();
+    /// assert!(write!(grant, "{}", 12345).is_err());
+    ///
+    /// let written = grant.written();
+    /// grant.commit(written);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// assert_eq!(&*grant, b"temp=21");
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        struct Writer<'g, 'a, const N: usize>(&'g mut GrantW<'a, N>);
+
+        impl<'g, 'a, const N: usize> fmt::Write for Writer<'g, 'a, N> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                if self.0.write_pos + s.len() > self.0.buf.len() {
+                    return Err(fmt::Error);
+                }
+                self.0.write_bytes(s.as_bytes());
+                Ok(())
+            }
+        }
+
+        fmt::write(&mut Writer(self), args)
+    }
+
+    /// The number of bytes written by the typed write helpers, such as
+    /// `write_u8()`
+    pub fn written(&self) -> usize {
+        self.write_pos
+    }
+
+    /// The contents of the grant, as possibly uninitialized bytes
+    ///
+    /// When the buffer was split with `BBBuffer::try_split_uninit()`, the bytes
+    /// of a grant are uninitialized until they have been written for the first
+    /// time. Once every byte has been written, use `assume_init_slice()`.
+    pub fn as_uninit_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        let len = self.buf.len();
+        let ptr = self.buf.as_mut_ptr().cast::<MaybeUninit<u8>>();
+
+        // This is sound, as `MaybeUninit<u8>` has the same layout as `u8`,
+        // and the storage of the buffer is a `MaybeUninit` itself
+        unsafe { from_raw_parts_mut(ptr, len) }
+    }
+
+    /// The contents of the grant, after writing them with `as_uninit_mut()`
+    ///
+    /// # Safety
+    ///
+    /// Every byte of the grant must have been initialized, either through
+    /// `as_uninit_mut()`, or by any previous grant over the same bytes.
+    pub unsafe fn assume_init_slice(&mut self) -> &mut [u8] {
         self.buf
     }
 
@@ -799,12 +3753,18 @@ impl<'a, const N: usize> GrantW<'a, N> {
     pub(crate) fn commit_inner(&mut self, used: usize) {
         let inner = unsafe { &self.bbq.as_ref() };
 
+        // The flags and indices of a later split are none of our business
+        if !inner.is_current(self.generation) {
+            return;
+        }
+
         // If there is no grant in progress, return early. This
         // generally means we are dropping the grant within a
         // wrapper structure
         if !inner.write_in_progress.load(Acquire) {
             return;
         }
+        inner.debug_sanity_check();
 
         // Writer component. Must never write to READ,
         // be careful writing to LAST
@@ -812,19 +3772,28 @@ impl<'a, const N: usize> GrantW<'a, N> {
         // Saturate the grant commit
         let len = self.buf.len();
         let used = min(len, used);
+        let _guard = PoisonGuard::new(&inner.poisoned, used);
 
         let write = inner.write.load(Acquire);
-        atomic::fetch_sub(&inner.reserve, len - used, AcqRel);
+        // Only the `Producer` writes `reserve`. Committing nothing leaves
+        // `write` and `last` untouched, even if the grant wrapped around,
+        // so the bytes skipped at the end of the ring are not lost
+        let new_write = if used == 0 {
+            write
+        } else {
+            inner.reserve.load(Acquire) - (len - used)
+        };
+        inner.reserve.store(new_write, Release);
 
         let max = N;
         let last = inner.last.load(Acquire);
-        let new_write = inner.reserve.load(Acquire);
 
-        if (new_write < write) && (write != max) {
-            // We have already wrapped, but we are skipping some bytes at the end of the ring.
-            // Mark `last` where the write pointer used to be to hold the line here
-            inner.last.store(write, Release);
-        } else if new_write > last {
+        if is_inverted(new_write, write) {
+            // The grant wrapped around to the start of the ring, as recorded by the lap
+            // flag of `reserve` when it was issued. Mark `last` where the write pointer
+            // used to be to hold the line here, which may be the end of the ring itself
+            inner.last.store(position(write), Release);
+        } else if position(new_write) > last {
             // We're about to pass the last pointer, which was previously the artificial
             // end of the ring. Now that we've passed it, we can "unlock" the section
             // that was previously skipped.
@@ -840,12 +3809,31 @@ impl<'a, const N: usize> GrantW<'a, N> {
         // * If we write to the start chunk in a wrap, we'll update last when we
         //     move write backwards
 
+        // Count the bytes BEFORE they are visible to the reader, so the
+        // reader's total can never be seen ahead of the writer's
+        #[cfg(feature = "stats")]
+        inner.write_total.add(used);
+
         // Write must be updated AFTER last, otherwise read could think it was
         // time to invert early!
         inner.write.store(new_write, Release);
 
         // Allow subsequent grants
         inner.write_in_progress.store(false, Release);
+        event::signal();
+        inner.debug_sanity_check();
+
+        #[cfg(feature = "watermarks")]
+        inner.check_high_watermark(used);
+        inner.update_data_ready();
+
+        // Make sure either this sees a new subscription, or the `Consumer`
+        // sees this commit when subscribing
+        #[cfg(feature = "subscribe")]
+        {
+            fence(SeqCst);
+            inner.subscriber.wake_if(|| inner.committed_len());
+        }
     }
 
     /// Configures the amount of bytes to be commited on drop.
@@ -861,8 +3849,8 @@ impl<'a, const N: usize> GrantR<'a, N> {
     /// If `used` is larger than the given grant, the full grant will
     /// be released.
     ///
-    /// NOTE:  If the `thumbv6` feature is selected, this function takes a short critical
-    /// section while releasing.
+    /// NOTE: This takes no critical section with the `thumbv6` or `critical-section` feature,
+    /// see `GrantR`.
     pub fn release(mut self, used: usize) {
         // Saturate the grant release
         let used = min(self.buf.len(), used);
@@ -871,6 +3859,84 @@ impl<'a, const N: usize> GrantR<'a, N> {
         forget(self);
     }
 
+    /// Verify and release `used` bytes of payload, followed by their CRC-32,
+    /// as written by `GrantW::commit_with_crc32()`
+    ///
+    /// The payload and checksum are released even if the checksum does not
+    /// match, in which case `Error::ChecksumMismatch` is returned. If the grant
+    /// is shorter than `used + 4` bytes, `Error::InsufficientSize` is
+    /// returned and nothing is released.
+    pub fn release_and_verify_crc32(self, used: usize) -> Result<()> {
+        let crc = match used.checked_add(4).and_then(|end| self.buf.get(used..end)) {
+            Some(crc) => crc,
+            None => return Err(Error::InsufficientSize),
+        };
+        let valid = crc == crc32(&self.buf[..used]).to_le_bytes();
+
+        self.release(used + 4);
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::ChecksumMismatch)
+        }
+    }
+
+    /// Release a sequence of bytes from the buffer, allowing the space
+    /// to be used by later writes. This consumes the grant.
+    ///
+    /// Unlike `release()`, `used` is not saturated. If `used` is larger than
+    /// the given grant, or would move the read position past the committed
+    /// data, nothing is released and the grant is returned along with
+    /// `Error::InsufficientSize`. A stale grant, one which no longer starts at
+    /// the read position or was handed out before the buffer was released with
+    /// `BBBuffer::try_release()`, is returned along with `Error::StaleGrant`
+    /// instead.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.grant_exact(4).unwrap().commit(4);
+    ///
+    /// // Releasing too much hands the grant back
+    /// let grant = cons.read().unwrap();
+    /// let (err, grant) = grant.release_checked(5).unwrap_err();
+    /// assert_eq!(err, Error::InsufficientSize);
+    ///
+    /// // Which can then be released correctly
+    /// assert!(grant.release_checked(4).is_ok());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn release_checked(mut self, used: usize) -> CoreResult<(), (Error, Self)> {
+        if used > self.buf.len() {
+            return Err((Error::InsufficientSize, self));
+        }
+
+        let inner = unsafe { &self.bbq.as_ref() };
+        let read = inner.read.load(Acquire);
+        if !inner.is_current(self.generation) || read != self.start {
+            return Err((Error::StaleGrant, self));
+        }
+        if used > inner.contiguous_len(read) {
+            return Err((Error::InsufficientSize, self));
+        }
+
+        self.release_inner(used);
+        forget(self);
+        Ok(())
+    }
+
     pub(crate) fn shrink(&mut self, len: usize) {
         let mut new_buf: &mut [u8] = &mut [];
         core::mem::swap(&mut self.buf, &mut new_buf);
@@ -878,9 +3944,345 @@ impl<'a, const N: usize> GrantR<'a, N> {
         self.buf = new;
     }
 
-    /// Obtain access to the inner buffer for reading
+    /// Obtain access to the inner buffer for reading
+    ///
+    /// ```
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    ///
+    /// // Successfully obtain and commit a grant of four bytes
+    /// let mut grant = prod.grant_max_remaining(4).unwrap();
+    /// grant.buf().copy_from_slice(&[1, 2, 3, 4]);
+    /// grant.commit(4);
+    ///
+    /// // Obtain a read grant, and copy to a buffer
+    /// let mut grant = cons.read().unwrap();
+    /// let mut buf = [0u8; 4];
+    /// buf.copy_from_slice(grant.buf());
+    /// assert_eq!(&buf, &[1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn buf(&self) -> &[u8] {
+        self.buf
+    }
+
+    /// The number of bytes contained in the read grant
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Is the read grant empty?
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Iterate over the bytes contained in the read grant
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.buf.iter()
+    }
+
+    /// Obtain a cursor for incrementally reading from the grant
+    ///
+    /// The cursor always starts at the beginning of the grant. It keeps its
+    /// own position, separate from the typed read helpers on the grant such as
+    /// `read_u8()`, so use one or the other for a given grant.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 8 elements
+    /// let buffer: BBBuffer<8> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.write_frame(&[0xAA, 1, 2, 3, 4], &[5]).unwrap();
+    ///
+    /// let grant = cons.read().unwrap();
+    /// let mut cursor = grant.cursor();
+    /// assert_eq!(cursor.read_u8(), Ok(0xAA));
+    /// assert_eq!(cursor.read_u32_le(), Ok(0x04030201));
+    ///
+    /// // Only release what was read
+    /// let consumed = cursor.consumed();
+    /// grant.release(consumed);
+    ///
+    /// assert_eq!(&*cons.read().unwrap(), &[5]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn cursor(&self) -> ReadCursor<'_> {
+        ReadCursor::new(self.buf)
+    }
+
+    /// Fill all of `dest` from the current read position, and advance it
+    ///
+    /// If not enough bytes remain in the grant, `Error::InsufficientSize` is
+    /// returned, and the read position is not changed. The same applies to
+    /// the other typed read helpers, which behave like the methods of the
+    /// same name on the `ReadCursor` returned by `cursor()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{BBBuffer, Error};
+    ///
+    /// // Create and split a new buffer of 8 elements
+    /// let buffer: BBBuffer<8> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.write_frame(&[2, 0xAA, 0xBB], &[]).unwrap();
+    ///
+    /// // A length prefixed message
+    /// let mut grant = cons.read().unwrap();
+    /// let len = grant.read_u8().unwrap();
+    /// let mut msg = [0u8; 2];
+    /// grant.read_bytes(&mut msg[..len.into()]).unwrap();
+    /// assert_eq!(msg, [0xAA, 0xBB]);
+    /// assert_eq!(grant.read_u16_le(), Err(Error::InsufficientSize));
+    ///
+    /// let consumed = grant.consumed();
+    /// grant.release(consumed);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn read_bytes(&mut self, dest: &mut [u8]) -> Result<()> {
+        let mut cursor = ReadCursor::new(&self.buf[self.read_pos..]);
+        cursor.read_bytes(dest)?;
+        self.read_pos += cursor.consumed();
+        Ok(())
+    }
+
+    fn read_array<const M: usize>(&mut self) -> Result<[u8; M]> {
+        let mut bytes = [0u8; M];
+        self.read_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Read a single byte, see `read_bytes()`
+    pub fn read_u8(&mut self) -> Result<u8> {
+        self.read_array().map(u8::from_le_bytes)
+    }
+
+    /// Read a `u16`, in little endian order, see `read_bytes()`
+    pub fn read_u16_le(&mut self) -> Result<u16> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    /// Read a `u32`, in little endian order, see `read_bytes()`
+    pub fn read_u32_le(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    /// Read a `u64`, in little endian order, see `read_bytes()`
+    pub fn read_u64_le(&mut self) -> Result<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    /// Read a `u16`, in big endian order, see `read_bytes()`
+    pub fn read_u16_be(&mut self) -> Result<u16> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    /// Read a `u32`, in big endian order, see `read_bytes()`
+    pub fn read_u32_be(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    /// Read a `u64`, in big endian order, see `read_bytes()`
+    pub fn read_u64_be(&mut self) -> Result<u64> {
+        self.read_array().map(u64::from_be_bytes)
+    }
+
+    /// Borrow a `T` directly from the grant at the current read position,
+    /// and advance it past the `T`
+    ///
+    /// If the remainder of the grant is too short, or is not suitably aligned
+    /// for a `T`, `None` is returned and the read position is not changed.
+    #[cfg(feature = "zerocopy_0_7")]
+    pub fn read_as<T: zerocopy::FromBytes>(&mut self) -> Option<&T> {
+        let (val, _) = zerocopy::Ref::<_, T>::new_from_prefix(&self.buf[self.read_pos..])?;
+        self.read_pos += core::mem::size_of::<T>();
+        Some(val.into_ref())
+    }
+
+    /// Copy the start of the grant into a `heapless::Vec`, truncating it to the
+    /// capacity `M` of the `Vec` if the grant is longer
+    ///
+    /// Nothing is released, and the read position is not changed.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let bb: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split().unwrap();
+    ///
+    /// let mut grant = prod.grant_exact(4).unwrap();
+    /// grant.copy_from_slice(&[1, 2, 3, 4]);
+    /// grant.commit(4);
+    ///
+    /// let grant = cons.read().unwrap();
+    /// let vec = grant.copy_to_heapless_vec::<3>();
+    /// assert_eq!(&vec[..], &[1, 2, 3]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    #[cfg(feature = "heapless")]
+    pub fn copy_to_heapless_vec<const M: usize>(&self) -> heapless::Vec<u8, M> {
+        let len = min(M, self.buf.len());
+        let mut vec = heapless::Vec::new();
+        // This can not fail, as at most `M` bytes are copied
+        let _ = vec.extend_from_slice(&self.buf[..len]);
+        vec
+    }
+
+    /// The number of bytes read by the typed read helpers, such as
+    /// `read_u8()`
+    pub fn consumed(&self) -> usize {
+        self.read_pos
+    }
+
+    /// Obtain mutable access to the read grant
+    ///
+    /// This is useful if you are performing in-place operations
+    /// on an incoming packet, such as decryption
+    pub fn buf_mut(&mut self) -> &mut [u8] {
+        self.buf
+    }
+
+    /// Sometimes, it's not possible for the lifetimes to check out. For example,
+    /// if you need to hand this buffer to a function that expects to receive a
+    /// `&'static [u8]`, it is not possible for the inner reference to outlive the
+    /// grant itself.
+    ///
+    /// # Safety
+    ///
+    /// You MUST guarantee that in no cases, the reference that is returned here outlives
+    /// the grant itself. Once the grant has been released, referencing the data contained
+    /// WILL cause undefined behavior.
+    ///
+    /// Additionally, you must ensure that a separate reference to this data is not created
+    /// to this data, e.g. using `Deref` or the `buf()` method of this grant.
+    pub unsafe fn as_static_buf(&self) -> &'static [u8] {
+        transmute::<&[u8], &'static [u8]>(self.buf)
+    }
+
+    #[inline(always)]
+    pub(crate) fn release_inner(&mut self, used: usize) {
+        let inner = unsafe { &self.bbq.as_ref() };
+
+        // The flags and indices of a later split are none of our business
+        if !inner.is_current(self.generation) {
+            return;
+        }
+
+        // If there is no grant in progress, return early. This
+        // generally means we are dropping the grant within a
+        // wrapper structure
+        if !inner.read_in_progress.load(Acquire) {
+            return;
+        }
+
+        // This should always be checked by the public interfaces
+        debug_assert!(used <= self.buf.len());
+
+        // A grant that does not start at the read position is stale, and
+        // must not release the bytes of another grant. It can only exist if
+        // the grants were misused, so the queue is poisoned, but the read
+        // is still ended, so the `Consumer` can recover after
+        // `clear_poison()`
+        if inner.read.load(Acquire) != self.start {
+            inner.poisoned.store(true, Release);
+            inner.read_in_progress.store(false, Release);
+            return;
+        }
+
+        let used = if used == self.buf.len() {
+            used + self.trailer
+        } else {
+            used
+        };
+        inner.release_contiguous(used);
+    }
+
+    /// Configures the amount of bytes to be released on drop.
+    pub fn to_release(&mut self, amt: usize) {
+        self.to_release = self.buf.len().min(amt);
+    }
+
+    /// Divide the grant into two slices at `mid`, so that each can be parsed
+    /// independently, such as a header and a body. Unlike `split_at()`, this
+    /// borrows the grant, which is still released at once, with `release()`.
+    ///
+    /// Panics if `mid` is larger than the grant.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// // Create and split a new buffer of 6 elements
+    /// let buffer: BBBuffer<6> = BBBuffer::new();
+    /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.write_frame(&[0xAA], &[1, 2, 3]).unwrap();
+    ///
+    /// let grant = cons.read().unwrap();
+    /// let (header, payload) = grant.split_at_ref(1);
+    /// assert_eq!(header, &[0xAA]);
+    /// assert_eq!(payload, &[1, 2, 3]);
+    /// grant.release(4);
+    /// assert!(cons.is_empty());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn split_at_ref(&self, mid: usize) -> (&[u8], &[u8]) {
+        self.buf.split_at(mid)
+    }
+
+    /// Split the read grant into two parts at `mid`, so that they can be
+    /// handed to different users of the data. This consumes the grant.
     ///
-    /// ```
+    /// The first part contains the bytes `[0, mid)`, and the second part
+    /// contains the bytes `[mid, len)`. If `mid` is larger than the grant,
+    /// the second part will be empty.
+    ///
+    /// The read grant remains in progress until both parts have been released
+    /// or dropped, in either order. Only bytes from the start of the grant are
+    /// released: the second part is only released if the first part was also
+    /// released.
+    ///
+    /// ```rust
     /// # // bbqueue test shim!
     /// # fn bbqtest() {
     /// use bbqueue::BBBuffer;
@@ -888,17 +4290,20 @@ impl<'a, const N: usize> GrantR<'a, N> {
     /// // Create and split a new buffer of 6 elements
     /// let buffer: BBBuffer<6> = BBBuffer::new();
     /// let (mut prod, mut cons) = buffer.try_split().unwrap();
+    /// prod.write_frame(&[0xAA], &[1, 2, 3]).unwrap();
     ///
-    /// // Successfully obtain and commit a grant of four bytes
-    /// let mut grant = prod.grant_max_remaining(4).unwrap();
-    /// grant.buf().copy_from_slice(&[1, 2, 3, 4]);
-    /// grant.commit(4);
+    /// // Keep the header, and hand off the payload
+    /// let (header, payload) = cons.read().unwrap().split_at(1);
+    /// assert_eq!(&*header, &[0xAA]);
+    /// assert_eq!(&*payload, &[1, 2, 3]);
     ///
-    /// // Obtain a read grant, and copy to a buffer
-    /// let mut grant = cons.read().unwrap();
-    /// let mut buf = [0u8; 4];
-    /// buf.copy_from_slice(grant.buf());
-    /// assert_eq!(&buf, &[1, 2, 3, 4]);
+    /// payload.release();
+    /// assert!(cons.read().is_err());
+    /// header.release();
+    ///
+    /// // All four bytes are now released
+    /// assert!(cons.read().is_err());
+    /// assert!(cons.is_empty());
     /// # // bbqueue test shim!
     /// # }
     /// #
@@ -907,58 +4312,146 @@ impl<'a, const N: usize> GrantR<'a, N> {
     /// # bbqtest();
     /// # }
     /// ```
-    pub fn buf(&self) -> &[u8] {
-        self.buf
+    #[cfg(feature = "split_grants")]
+    pub fn split_at(mut self, mid: usize) -> (GrantRPart<'a, N>, GrantRPart<'a, N>) {
+        let buf = core::mem::take(&mut self.buf);
+        let bbq = self.bbq;
+        let trailer = self.trailer;
+        let generation = self.generation;
+        forget(self);
+
+        let inner = unsafe { bbq.as_ref() };
+        inner.split_state.store(0, Release);
+
+        let mid = min(mid, buf.len());
+        // The trailer is released along with the tail
+        let total = buf.len() + trailer;
+        let (head, tail) = buf.split_at_mut(mid);
+
+        (
+            GrantRPart {
+                buf: head,
+                bbq,
+                head_len: mid,
+                total_len: total,
+                released_flag: SPLIT_HEAD_RELEASED,
+                generation,
+            },
+            GrantRPart {
+                buf: tail,
+                bbq,
+                head_len: mid,
+                total_len: total,
+                released_flag: SPLIT_TAIL_RELEASED,
+                generation,
+            },
+        )
     }
+}
 
-    /// Obtain mutable access to the read grant
+/// Poisons the buffer if it is dropped while panicking, see
+/// `BBBuffer::is_poisoned()`
+///
+/// A grant dropped while unwinding from an unrelated panic only poisons the
+/// buffer if it moves `used` bytes, as committing or releasing nothing leaves
+/// the queue as it was. A panic that starts while the indices are being
+/// updated always poisons it.
+struct PoisonGuard<'b> {
+    poisoned: &'b AtomicBool,
+    armed: bool,
+}
+
+impl<'b> PoisonGuard<'b> {
+    #[inline(always)]
+    fn new(poisoned: &'b AtomicBool, used: usize) -> Self {
+        Self {
+            poisoned,
+            armed: used != 0 || !panicking(),
+        }
+    }
+}
+
+impl Drop for PoisonGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed && panicking() {
+            self.poisoned.store(true, Release);
+        }
+    }
+}
+
+/// Is the current thread unwinding? Panics can not be detected without `std`
+#[inline(always)]
+fn panicking() -> bool {
+    #[cfg(feature = "std")]
+    return std::thread::panicking();
+
+    #[cfg(not(feature = "std"))]
+    return false;
+}
+
+/// The fixed point value of a full buffer in `BBBuffer::fill_ewma`
+#[cfg(feature = "stats")]
+const FILL_ONE: usize = 1 << 16;
+
+/// Bits of `BBBuffer::split_state`. The lowest two bits count the parts
+/// that have been released or dropped, and the flags record which of the
+/// parts were released, rather than dropped.
+#[cfg(feature = "split_grants")]
+const SPLIT_COUNT_MASK: usize = 0b0011;
+#[cfg(feature = "split_grants")]
+const SPLIT_HEAD_RELEASED: usize = 0b0100;
+#[cfg(feature = "split_grants")]
+const SPLIT_TAIL_RELEASED: usize = 0b1000;
+
+#[cfg(feature = "split_grants")]
+impl<'a, const N: usize> GrantRPart<'a, N> {
+    /// Release this part of the read grant. This consumes the part.
     ///
-    /// This is useful if you are performing in-place operations
-    /// on an incoming packet, such as decryption
-    pub fn buf_mut(&mut self) -> &mut [u8] {
+    /// The bytes are released from the queue once both parts of the read
+    /// grant have been released or dropped.
+    pub fn release(mut self) {
+        self.resolve(true);
+        forget(self);
+    }
+
+    /// Obtain access to this part of the read grant
+    pub fn buf(&self) -> &[u8] {
         self.buf
     }
 
-    /// Sometimes, it's not possible for the lifetimes to check out. For example,
-    /// if you need to hand this buffer to a function that expects to receive a
-    /// `&'static [u8]`, it is not possible for the inner reference to outlive the
-    /// grant itself.
-    ///
-    /// # Safety
-    ///
-    /// You MUST guarantee that in no cases, the reference that is returned here outlives
-    /// the grant itself. Once the grant has been released, referencing the data contained
-    /// WILL cause undefined behavior.
-    ///
-    /// Additionally, you must ensure that a separate reference to this data is not created
-    /// to this data, e.g. using `Deref` or the `buf()` method of this grant.
-    pub unsafe fn as_static_buf(&self) -> &'static [u8] {
-        transmute::<&[u8], &'static [u8]>(self.buf)
+    /// Obtain mutable access to this part of the read grant
+    pub fn buf_mut(&mut self) -> &mut [u8] {
+        self.buf
     }
 
-    #[inline(always)]
-    pub(crate) fn release_inner(&mut self, used: usize) {
-        let inner = unsafe { &self.bbq.as_ref() };
+    fn resolve(&mut self, released: bool) {
+        let inner = unsafe { self.bbq.as_ref() };
 
-        // If there is no grant in progress, return early. This
-        // generally means we are dropping the grant within a
-        // wrapper structure
-        if !inner.read_in_progress.load(Acquire) {
+        // The flags and indices of a later split are none of our business
+        if !inner.is_current(self.generation) {
             return;
         }
 
-        // This should always be checked by the public interfaces
-        debug_assert!(used <= self.buf.len());
+        let mine = 1 + if released { self.released_flag } else { 0 };
+        let state = atomic::fetch_add(&inner.split_state, mine, AcqRel) + mine;
 
-        // This should be fine, purely incrementing
-        let _ = atomic::fetch_add(&inner.read, used, Release);
+        if state & SPLIT_COUNT_MASK != 2 {
+            // The other part is still outstanding
+            return;
+        }
 
-        inner.read_in_progress.store(false, Release);
-    }
+        // We are the last part, release the bytes from the start of
+        // the original grant
+        let used = match (
+            state & SPLIT_HEAD_RELEASED != 0,
+            state & SPLIT_TAIL_RELEASED != 0,
+        ) {
+            (true, true) => self.total_len,
+            (true, false) => self.head_len,
+            _ => 0,
+        };
 
-    /// Configures the amount of bytes to be released on drop.
-    pub fn to_release(&mut self, amt: usize) {
-        self.to_release = self.buf.len().min(amt);
+        inner.release_contiguous(used);
     }
 }
 
@@ -969,8 +4462,8 @@ impl<'a, const N: usize> SplitGrantR<'a, N> {
     /// If `used` is larger than the given grant, the full grant will
     /// be released.
     ///
-    /// NOTE:  If the `thumbv6` feature is selected, this function takes a short critical
-    /// section while releasing.
+    /// NOTE: This takes no critical section with the `thumbv6` or `critical-section` feature,
+    /// see `GrantR`.
     pub fn release(mut self, used: usize) {
         // Saturate the grant release
         let used = min(self.combined_len(), used);
@@ -1024,6 +4517,11 @@ impl<'a, const N: usize> SplitGrantR<'a, N> {
     pub(crate) fn release_inner(&mut self, used: usize) {
         let inner = unsafe { &self.bbq.as_ref() };
 
+        // The flags and indices of a later split are none of our business
+        if !inner.is_current(self.generation) {
+            return;
+        }
+
         // If there is no grant in progress, return early. This
         // generally means we are dropping the grant within a
         // wrapper structure
@@ -1031,18 +4529,33 @@ impl<'a, const N: usize> SplitGrantR<'a, N> {
             return;
         }
 
+        let _guard = PoisonGuard::new(&inner.poisoned, used);
+        inner.debug_sanity_check();
+
         // This should always be checked by the public interfaces
         debug_assert!(used <= self.combined_len());
 
         if used <= self.buf1.len() {
-            // This should be fine, purely incrementing
-            let _ = atomic::fetch_add(&inner.read, used, Release);
+            // This should be fine, purely incrementing. Only the `Consumer`
+            // writes `read`, so this does not need a read-modify-write.
+            let read = inner.read.load(Acquire);
+            inner.read.store(read + used, Release);
         } else {
-            // Also release parts of the second buffer
-            inner.read.store(used - self.buf1.len(), Release);
+            // Also release parts of the second buffer, which starts
+            // the next lap
+            let lap = (inner.read.load(Acquire) & LAP) ^ LAP;
+            inner.read.store(lap | (used - self.buf1.len()), Release);
         }
+        #[cfg(feature = "stats")]
+        inner.read_total.add(used);
 
         inner.read_in_progress.store(false, Release);
+        event::signal();
+        inner.debug_sanity_check();
+
+        #[cfg(feature = "watermarks")]
+        inner.check_low_watermark(used);
+        inner.update_data_ready();
     }
 
     /// Configures the amount of bytes to be released on drop.
@@ -1054,6 +4567,124 @@ impl<'a, const N: usize> SplitGrantR<'a, N> {
     pub fn combined_len(&self) -> usize {
         self.buf1.len() + self.buf2.len()
     }
+
+    /// Convert into a regular read grant of the first buffer
+    pub(crate) fn into_first(mut self) -> GrantR<'a, N> {
+        let buf = core::mem::take(&mut self.buf1);
+        let bbq = self.bbq;
+        let generation = self.generation;
+        forget(self);
+
+        // `read` does not move while the grant is in progress
+        let start = unsafe { bbq.as_ref() }.read.load(Acquire);
+        GrantR {
+            buf,
+            bbq,
+            to_release: 0,
+            read_pos: 0,
+            start,
+            trailer: 0,
+            generation,
+        }
+    }
+
+    /// Copy as much of both buffers as fits into `dest`, in order,
+    /// returning the number of bytes copied
+    fn copy_into(&self, dest: &mut [u8]) -> usize {
+        let len1 = min(self.buf1.len(), dest.len());
+        dest[..len1].copy_from_slice(&self.buf1[..len1]);
+
+        let len2 = min(self.buf2.len(), dest.len() - len1);
+        dest[len1..][..len2].copy_from_slice(&self.buf2[..len2]);
+
+        len1 + len2
+    }
+}
+
+impl<'c, 'a, const N: usize> Iterator for Drain<'c, 'a, N> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            if let Some(grant) = self.grant.as_mut() {
+                if let Some(&byte) = grant.buf.get(self.idx) {
+                    self.idx += 1;
+
+                    // Only the yielded bytes are released if we are dropped
+                    grant.to_release(self.idx);
+                    return Some(byte);
+                }
+            }
+
+            // Release the exhausted region (if any), and move on to the
+            // next one, which may be at the start of the buffer
+            self.grant = None;
+            self.idx = 0;
+            self.grant = Some(self.consumer.read().ok()?);
+        }
+    }
+}
+
+/// Writes as many bytes as currently fit in a single contiguous grant.
+///
+/// If the queue is full, an error of kind `WouldBlock` is returned, which is
+/// not counted in `Consumer::bytes_lost()`, and if the `Consumer` has been
+/// closed, an error of kind `BrokenPipe`.
+#[cfg(feature = "std")]
+impl<'a, const N: usize> std::io::Write for Producer<'a, N> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // `WouldBlock` asks the caller to try again, so nothing is lost yet
+        let mut grant = self.grant_max_with_minimum_inner(buf.len(), 1)?;
+        let len = grant.len();
+        grant.copy_from_slice(&buf[..len]);
+        grant.commit(len);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads as many bytes as are currently available in a single contiguous
+/// region.
+///
+/// If the queue is empty, an error of kind `WouldBlock` is returned. Once the
+/// `Producer` has been closed and all data has been read, `Ok(0)` is returned
+/// to signal the end of the stream.
+#[cfg(feature = "std")]
+impl<'a, const N: usize> std::io::Read for Consumer<'a, N> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let grant = match Consumer::read(self) {
+            Ok(grant) => grant,
+            Err(Error::Disconnected) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let len = min(grant.len(), buf.len());
+        buf[..len].copy_from_slice(&grant[..len]);
+        grant.release(len);
+        Ok(len)
+    }
+}
+
+impl<'a, const N: usize> Drop for Producer<'a, N> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<'a, const N: usize> Drop for Consumer<'a, N> {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
 
 impl<'a, const N: usize> Drop for GrantW<'a, N> {
@@ -1068,6 +4699,13 @@ impl<'a, const N: usize> Drop for GrantR<'a, N> {
     }
 }
 
+#[cfg(feature = "split_grants")]
+impl<'a, const N: usize> Drop for GrantRPart<'a, N> {
+    fn drop(&mut self) {
+        self.resolve(false)
+    }
+}
+
 impl<'a, const N: usize> Drop for SplitGrantR<'a, N> {
     fn drop(&mut self) {
         self.release_inner(self.to_release)
@@ -1102,14 +4740,313 @@ impl<'a, const N: usize> DerefMut for GrantR<'a, N> {
     }
 }
 
-#[cfg(feature = "thumbv6")]
+#[cfg(feature = "split_grants")]
+impl<'a, const N: usize> Deref for GrantRPart<'a, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.buf
+    }
+}
+
+#[cfg(feature = "split_grants")]
+impl<'a, const N: usize> DerefMut for GrantRPart<'a, N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.buf
+    }
+}
+
+impl<'a, const N: usize> AsRef<[u8]> for GrantR<'a, N> {
+    fn as_ref(&self) -> &[u8] {
+        self.buf
+    }
+}
+
+/// A 64-bit byte counter, made of two `AtomicUsize`s so that it is also
+/// available on targets without 64-bit atomics
+///
+/// Each counter only has a single writer. The high word is incremented twice
+/// for every carry out of the low word, so it is odd while a carry is in
+/// progress, which allows a reader to detect that it raced with a carry.
+#[cfg(feature = "stats")]
+#[derive(Debug)]
+struct Total {
+    lo: AtomicUsize,
+    hi: AtomicUsize,
+}
+
+#[cfg(feature = "stats")]
+impl Total {
+    const_fn! {
+        fn new() -> Self {
+            Self {
+                lo: AtomicUsize::new(0),
+                hi: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    /// Add `val` to the counter. Must only be called by the owner.
+    fn add(&self, val: usize) {
+        let (lo, carry) = self.lo.load(Acquire).overflowing_add(val);
+
+        if carry {
+            let hi = self.hi.load(Acquire);
+            self.hi.store(hi.wrapping_add(1), Release);
+            self.lo.store(lo, Release);
+            self.hi.store(hi.wrapping_add(2), Release);
+        } else {
+            self.lo.store(lo, Release);
+        }
+    }
+
+    /// Reset the counter to zero, while neither half exists
+    fn clear(&self) {
+        self.hi.store(0, Release);
+        self.lo.store(0, Release);
+    }
+
+    fn load(&self) -> u64 {
+        let before = self.hi.load(Acquire);
+        let lo = self.lo.load(Acquire);
+        let after = self.hi.load(Acquire);
+
+        // If a carry raced with this load, `lo` is either just below
+        // the wrap, or just past it
+        let mut hi = before / 2;
+        if (before != after || before % 2 == 1) && lo <= usize::MAX / 2 {
+            hi += 1;
+        }
+
+        // The high word is always zero on 64-bit targets
+        (hi as u64).checked_shl(usize::BITS).unwrap_or(0) | lo as u64
+    }
+}
+
+/// A single waker, which is woken once the number of committed bytes reaches
+/// a threshold, see `Consumer::subscribe()`
+///
+/// Access to the waker is guarded by `state`, which is `REGISTERING` while
+/// the `Consumer` stores a new waker, and `WAKING` while the `Producer` takes
+/// it. If both happen at once, the `Consumer` wakes the waker instead.
+#[cfg(feature = "subscribe")]
+#[derive(Debug)]
+struct Subscriber {
+    state: AtomicUsize,
+    threshold: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+#[cfg(feature = "subscribe")]
+const WAITING: usize = 0;
+#[cfg(feature = "subscribe")]
+const REGISTERING: usize = 1;
+#[cfg(feature = "subscribe")]
+const WAKING: usize = 2;
+
+#[cfg(feature = "subscribe")]
+impl Subscriber {
+    const_fn! {
+        fn new() -> Self {
+            Self {
+                state: AtomicUsize::new(WAITING),
+                // Never reached, as nothing is subscribed
+                threshold: AtomicUsize::new(usize::MAX),
+                waker: UnsafeCell::new(None),
+            }
+        }
+    }
+
+    /// Forget the waker. Only called while the buffer is not split.
+    fn clear(&self) {
+        self.threshold.store(usize::MAX, Release);
+        self.waker.with_mut(|waker| unsafe { *waker = None });
+    }
+
+    /// Store `waker`, replacing any previous one. Only called by the `Consumer`.
+    fn register(&self, threshold: usize, waker: Waker) {
+        if atomic::compare_exchange(&self.state, WAITING, REGISTERING, Acquire).is_err() {
+            // The previous waker is being woken, so the new one may as well be
+            waker.wake();
+            return;
+        }
+
+        self.waker.with_mut(|cell| unsafe { *cell = Some(waker) });
+        self.threshold.store(threshold, Release);
+
+        if atomic::compare_exchange(&self.state, REGISTERING, WAITING, AcqRel).is_err() {
+            // The `Producer` tried to wake the waker while it was being stored
+            self.wake();
+        }
+    }
+
+    /// Wake the waker, if the threshold has been reached
+    fn wake_if(&self, committed: impl FnOnce() -> usize) {
+        let threshold = self.threshold.load(Acquire);
+        if threshold == usize::MAX || committed() < threshold {
+            return;
+        }
+
+        if atomic::fetch_or(&self.state, WAKING, AcqRel) == WAITING {
+            self.wake();
+        }
+    }
+
+    /// Take and wake the waker, while holding either the `REGISTERING` or
+    /// `WAKING` state
+    fn wake(&self) {
+        self.threshold.store(usize::MAX, Release);
+        let waker = self.waker.with_mut(|cell| unsafe { (*cell).take() });
+        self.state.store(WAITING, Release);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The pin registered with `Producer::set_data_ready_pin()`
+///
+/// The pin is driven by both the `Producer` and the `Consumer`, so access to
+/// it is guarded by `pending`, the number of updates requested since the pin
+/// was last driven. Whoever raises it from zero drives the pin, until no more
+/// updates were requested in the meantime. Anyone else leaves their update
+/// to it, rather than waiting.
+#[cfg(feature = "embedded_hal_1")]
+#[derive(Debug)]
+struct DataReady {
+    pending: AtomicUsize,
+    /// The registered pin, or null
+    pin: AtomicPtr<()>,
+    /// A `fn(*mut (), bool)` driving `pin`, see `drive_pin()`
+    drive: AtomicPtr<()>,
+}
+
+#[cfg(feature = "embedded_hal_1")]
+impl DataReady {
+    const_fn! {
+        fn new() -> Self {
+            Self {
+                pending: AtomicUsize::new(0),
+                pin: AtomicPtr::new(null_mut()),
+                drive: AtomicPtr::new(null_mut()),
+            }
+        }
+    }
+
+    /// Replace the pin, and drive it to the current level, or hand it back if
+    /// the previous pin is being driven
+    fn register<'a, P: embedded_hal::digital::OutputPin>(
+        &self,
+        pin: &'a mut P,
+        ready: impl Fn() -> bool,
+    ) -> CoreResult<(), &'a mut P> {
+        // An update of the previous pin can not be left to the other side, as
+        // it does not know about the new pin
+        if atomic::compare_exchange(&self.pending, 0, 1, Acquire).is_err() {
+            return Err(pin);
+        }
+
+        let drive: fn(*mut (), bool) = drive_pin::<P>;
+        self.pin.store(pin as *mut P as *mut (), Release);
+        self.drive.store(drive as *const () as *mut (), Release);
+        self.drive_pending(ready);
+        Ok(())
+    }
+
+    /// Forget the pin. Only called while the buffer is not split.
+    fn clear(&self) {
+        self.pin.store(null_mut(), Release);
+        self.drive.store(null_mut(), Release);
+    }
+
+    /// Drive the pin to the current level, or leave it to whoever is already
+    /// doing so
+    fn update(&self, ready: impl Fn() -> bool) {
+        // Always counted, even without a pin, so that this can not miss a
+        // pin that is being registered
+        if atomic::fetch_add(&self.pending, 1, AcqRel) == 0 {
+            self.drive_pending(ready);
+        }
+    }
+
+    /// Drive the pin until no more updates were requested, while `pending`
+    /// is raised
+    fn drive_pending(&self, ready: impl Fn() -> bool) {
+        loop {
+            let pending = self.pending.load(Acquire);
+
+            let pin = self.pin.load(Acquire);
+            let drive = self.drive.load(Acquire);
+            if !pin.is_null() {
+                // This is sound, as the pointer was created from a
+                // `fn(*mut (), bool)`, which expects this pin
+                let drive = unsafe { transmute::<*mut (), fn(*mut (), bool)>(drive) };
+                drive(pin, ready());
+            }
+
+            if atomic::compare_exchange(&self.pending, pending, 0, AcqRel).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+/// Drive `pin`, a `P`, to `high`
+#[cfg(feature = "embedded_hal_1")]
+fn drive_pin<P: embedded_hal::digital::OutputPin>(pin: *mut (), high: bool) {
+    // This is sound, as only the holder of `DataReady::pending` accesses the
+    // pin, which is borrowed for as long as the buffer is split
+    let pin = unsafe { &mut *(pin as *mut P) };
+    let _ = if high { pin.set_high() } else { pin.set_low() };
+}
+
+// Read-modify-write operations, which armv6-m does not have. Without optional
+// features, these are only used by `try_split()`, where both halves may race.
+// The `split_grants`, `stats`, `subscribe` and `embedded_hal_1` features use
+// them as well. Values with a single writer, such as `read`, `reserve`, and
+// the grant flags, are updated with a load and a store instead, so that
+// granting, committing, reading and releasing never need one. The `Producer`
+// only moves `read` of an empty queue, which the `Consumer` can not release
+// from.
+//
+// With the `thumbv6` feature, these are emulated with short critical sections,
+// which disable interrupts. With the `critical-section` feature, the critical
+// sections of the `critical-section` crate are used instead, so that any
+// single core target can provide its own implementation. If both features are
+// enabled, such as by `--all-features`, `thumbv6` takes precedence. Without
+// either, the native operations are used, so a target without them, such as
+// armv6-m, needs one of these features, or `portable-atomic`, to build.
+#[cfg(all(
+    not(any(loom, target_has_atomic = "ptr")),
+    not(any(
+        feature = "thumbv6",
+        feature = "critical-section",
+        feature = "portable-atomic"
+    ))
+))]
+compile_error!(
+    "This target has no atomic read-modify-write operations, enable the \
+     `thumbv6`, `critical-section` or `portable-atomic` feature of bbqueue"
+);
+
+#[cfg(any(feature = "thumbv6", feature = "critical-section"))]
 mod atomic {
-    use core::sync::atomic::{
-        AtomicBool, AtomicUsize,
-        Ordering::{self, Acquire, Release},
-    };
+    use crate::sync::AtomicBool;
+    #[cfg(any(
+        feature = "split_grants",
+        feature = "stats",
+        feature = "subscribe",
+        feature = "embedded_hal_1"
+    ))]
+    use crate::sync::AtomicUsize;
+    use core::sync::atomic::Ordering::{self, Acquire, Release};
+    #[cfg(feature = "thumbv6")]
     use cortex_m::interrupt::free;
+    #[cfg(not(feature = "thumbv6"))]
+    use critical_section::with as free;
 
+    #[cfg(any(feature = "split_grants", feature = "embedded_hal_1"))]
     #[inline(always)]
     pub fn fetch_add(atomic: &AtomicUsize, val: usize, _order: Ordering) -> usize {
         free(|_| {
@@ -1120,40 +5057,81 @@ mod atomic {
     }
 
     #[inline(always)]
-    pub fn fetch_sub(atomic: &AtomicUsize, val: usize, _order: Ordering) -> usize {
+    pub fn swap(atomic: &AtomicBool, val: bool, _order: Ordering) -> bool {
         free(|_| {
             let prev = atomic.load(Acquire);
-            atomic.store(prev.wrapping_sub(val), Release);
+            atomic.store(val, Release);
             prev
         })
     }
 
+    #[cfg(feature = "subscribe")]
     #[inline(always)]
-    pub fn swap(atomic: &AtomicBool, val: bool, _order: Ordering) -> bool {
+    pub fn fetch_or(atomic: &AtomicUsize, val: usize, _order: Ordering) -> usize {
         free(|_| {
             let prev = atomic.load(Acquire);
-            atomic.store(val, Release);
+            atomic.store(prev | val, Release);
             prev
         })
     }
+
+    #[cfg(any(feature = "stats", feature = "subscribe", feature = "embedded_hal_1"))]
+    #[inline(always)]
+    pub fn compare_exchange(
+        atomic: &AtomicUsize,
+        current: usize,
+        new: usize,
+        _order: Ordering,
+    ) -> Result<usize, usize> {
+        free(|_| {
+            let prev = atomic.load(Acquire);
+            if prev == current {
+                atomic.store(new, Release);
+                Ok(prev)
+            } else {
+                Err(prev)
+            }
+        })
+    }
 }
 
-#[cfg(not(feature = "thumbv6"))]
+#[cfg(not(any(feature = "thumbv6", feature = "critical-section")))]
 mod atomic {
-    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-
+    use crate::sync::AtomicBool;
+    #[cfg(any(
+        feature = "split_grants",
+        feature = "stats",
+        feature = "subscribe",
+        feature = "embedded_hal_1"
+    ))]
+    use crate::sync::AtomicUsize;
+    use core::sync::atomic::Ordering;
+
+    #[cfg(any(feature = "split_grants", feature = "embedded_hal_1"))]
     #[inline(always)]
     pub fn fetch_add(atomic: &AtomicUsize, val: usize, order: Ordering) -> usize {
         atomic.fetch_add(val, order)
     }
 
     #[inline(always)]
-    pub fn fetch_sub(atomic: &AtomicUsize, val: usize, order: Ordering) -> usize {
-        atomic.fetch_sub(val, order)
+    pub fn swap(atomic: &AtomicBool, val: bool, order: Ordering) -> bool {
+        atomic.swap(val, order)
     }
 
+    #[cfg(feature = "subscribe")]
     #[inline(always)]
-    pub fn swap(atomic: &AtomicBool, val: bool, order: Ordering) -> bool {
-        atomic.swap(val, order)
+    pub fn fetch_or(atomic: &AtomicUsize, val: usize, order: Ordering) -> usize {
+        atomic.fetch_or(val, order)
+    }
+
+    #[cfg(any(feature = "stats", feature = "subscribe", feature = "embedded_hal_1"))]
+    #[inline(always)]
+    pub fn compare_exchange(
+        atomic: &AtomicUsize,
+        current: usize,
+        new: usize,
+        order: Ordering,
+    ) -> Result<usize, usize> {
+        atomic.compare_exchange(current, new, order, Ordering::Acquire)
     }
 }
@@ -0,0 +1,308 @@
+//! A COBS flavor of BBQueue, useful for serial protocols
+//!
+//! This module provides wrappers around a `Producer` and `Consumer` which
+//! use [Consistent Overhead Byte Stuffing] to delimit messages. Each message
+//! is COBS encoded and terminated with a `0x00` byte, which means that the
+//! contents of the queue can be sent out over a serial port as-is, and the
+//! receiving side can re-synchronize on the next delimiter.
+//!
+//! [Consistent Overhead Byte Stuffing]: https://en.wikipedia.org/wiki/Consistent_Overhead_Byte_Stuffing
+//!
+//! ## Example
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::{
+//!     cobs::{CobsDecoder, CobsProducer},
+//!     BBBuffer,
+//! };
+//!
+//! let bb: BBBuffer<64> = BBBuffer::new();
+//! let (prod, cons) = bb.try_split().unwrap();
+//! let mut prod = CobsProducer::new(prod);
+//! let mut cons: CobsDecoder<64, 16> = CobsDecoder::new(cons);
+//!
+//! // One message in, one message out
+//! prod.send(&[1, 0, 2, 3]).unwrap();
+//! assert_eq!(cons.recv().unwrap(), &[1, 0, 2, 3]);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
+//!
+//! ## Encoding overhead
+//!
+//! COBS adds at most one byte for every 254 bytes of message, plus one byte
+//! for the start of the message and one byte for the trailing delimiter. A
+//! write grant of this worst case size is requested when sending, and only
+//! the actually used portion is committed.
+//!
+//! ## Encoding in place
+//!
+//! Instead of copying a message with `CobsProducer::send()`, a `CobsGrantW`
+//! can be requested with `CobsProducer::grant()`. The message is written
+//! directly into the grant, and encoded in place when committed, so no
+//! separate buffer is needed for the message.
+//!
+//! Similarly, `CobsDecoder::recv_raw()` hands out a still encoded message,
+//! for example to forward it over a serial port as-is.
+
+use crate::{Consumer, Error, GrantR, GrantW, Producer, Result};
+use core::{
+    cmp::min,
+    ops::{Deref, DerefMut},
+};
+
+/// The maximum number of bytes `data_len` bytes will take once encoded,
+/// including the trailing delimiter
+pub const fn max_encoded_len(data_len: usize) -> usize {
+    data_len + (data_len / 254) + 2
+}
+
+/// A producer of COBS encoded messages
+pub struct CobsProducer<'a, const N: usize> {
+    producer: Producer<'a, N>,
+}
+
+impl<'a, const N: usize> CobsProducer<'a, N> {
+    /// Wrap a `Producer` to send COBS encoded messages
+    pub fn new(producer: Producer<'a, N>) -> Self {
+        Self { producer }
+    }
+
+    /// Release the inner `Producer`
+    pub fn into_inner(self) -> Producer<'a, N> {
+        self.producer
+    }
+
+    /// Encode `data`, followed by a `0x00` delimiter, into the queue
+    ///
+    /// If not enough space is available for the worst case encoded size
+    /// of `data`, an error is returned and the queue is not modified.
+    pub fn send(&mut self, data: &[u8]) -> Result<()> {
+        let mut wgr = self.grant(data.len())?;
+        wgr.copy_from_slice(data);
+        wgr.commit(data.len());
+        Ok(())
+    }
+
+    /// Obtain a grant for a message of up to `max_sz` bytes, which is encoded
+    /// in place when committed
+    ///
+    /// If not enough space is available for the worst case encoded size of
+    /// the message, an error is returned.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{
+    ///     cobs::{CobsDecoder, CobsProducer},
+    ///     BBBuffer,
+    /// };
+    ///
+    /// let bb: BBBuffer<64> = BBBuffer::new();
+    /// let (prod, cons) = bb.try_split().unwrap();
+    /// let mut prod = CobsProducer::new(prod);
+    /// let mut cons: CobsDecoder<64, 16> = CobsDecoder::new(cons);
+    ///
+    /// let mut wgr = prod.grant(16).unwrap();
+    /// wgr[..3].copy_from_slice(&[1, 0, 2]);
+    /// wgr.commit(3);
+    ///
+    /// assert_eq!(cons.recv().unwrap(), &[1, 0, 2]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn grant(&mut self, max_sz: usize) -> Result<CobsGrantW<'a, N>> {
+        Ok(CobsGrantW {
+            grant_w: self.producer.grant_exact(max_encoded_len(max_sz))?,
+            max_sz,
+        })
+    }
+}
+
+/// A write grant for a single COBS encoded message
+///
+/// The message is written unencoded, and encoded in place by `commit()`.
+/// If the grant is dropped without committing, no message is sent.
+#[derive(Debug, PartialEq)]
+pub struct CobsGrantW<'a, const N: usize> {
+    grant_w: GrantW<'a, N>,
+    max_sz: usize,
+}
+
+impl<'a, const N: usize> CobsGrantW<'a, N> {
+    /// Encode the first `used` bytes of the message, followed by a `0x00`
+    /// delimiter, and commit them
+    ///
+    /// If `used` is larger than the grant, the whole grant is committed.
+    pub fn commit(mut self, used: usize) {
+        let used = min(used, self.max_sz);
+        let start = message_offset(self.max_sz);
+        let len = encode_in_place(&mut self.grant_w, start, used);
+        self.grant_w[len] = 0;
+        self.grant_w.commit(len + 1);
+    }
+}
+
+impl<'a, const N: usize> Deref for CobsGrantW<'a, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        let start = message_offset(self.max_sz);
+        &self.grant_w.buf[start..][..self.max_sz]
+    }
+}
+
+impl<'a, const N: usize> DerefMut for CobsGrantW<'a, N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let start = message_offset(self.max_sz);
+        &mut self.grant_w.buf[start..][..self.max_sz]
+    }
+}
+
+/// A consumer of COBS encoded messages
+///
+/// Messages are decoded into an internal scratch buffer of `M` bytes,
+/// as the encoded message may be wrapped around the end of the queue.
+pub struct CobsDecoder<'a, const N: usize, const M: usize> {
+    consumer: Consumer<'a, N>,
+    scratch: [u8; M],
+}
+
+impl<'a, const N: usize, const M: usize> CobsDecoder<'a, N, M> {
+    /// Wrap a `Consumer` to receive COBS encoded messages
+    pub fn new(consumer: Consumer<'a, N>) -> Self {
+        Self {
+            consumer,
+            scratch: [0u8; M],
+        }
+    }
+
+    /// Release the inner `Consumer`
+    pub fn into_inner(self) -> Consumer<'a, N> {
+        self.consumer
+    }
+
+    /// Obtain the next complete message, if any
+    ///
+    /// If no delimiter has been committed yet, `Error::Empty` is returned and
+    /// no data is consumed.
+    ///
+    /// If the message can not be decoded, or the decoded message does not fit
+    /// in the scratch buffer, the message (and its delimiter) are discarded
+    /// from the queue, and `Error::InvalidFrame` or `Error::InsufficientSize`
+    /// is returned respectively.
+    pub fn recv(&mut self) -> Result<&[u8]> {
+        let rgr = self.consumer.split_read()?;
+        let (buf1, buf2) = rgr.bufs();
+
+        let end = match buf1.iter().chain(buf2.iter()).position(|b| *b == 0) {
+            Some(end) => end,
+            None => return Err(Error::Empty),
+        };
+
+        let res = decode(buf1.iter().chain(buf2.iter()).take(end), &mut self.scratch);
+
+        // Discard the frame and its delimiter, even if it could not be decoded
+        rgr.release(end + 1);
+
+        res.map(move |len| &self.scratch[..len])
+    }
+
+    /// Obtain the next complete message, if any, without decoding it
+    ///
+    /// The grant contains the encoded message, including its delimiter. If
+    /// no delimiter has been committed yet, `Error::Empty` is returned. A
+    /// message that wraps around the end of the queue can not be handed out
+    /// as a single grant, and `Error::NotContiguous` is returned, in which
+    /// case `recv()` can be used instead.
+    pub fn recv_raw(&mut self) -> Result<GrantR<'a, N>> {
+        self.consumer.read_until(0)?.ok_or(Error::Empty)
+    }
+}
+
+/// The offset of the unencoded message in a `CobsGrantW`
+///
+/// This is the worst case overhead of encoding, which guarantees that the
+/// encoded output never overtakes the unencoded input in `encode_in_place()`.
+const fn message_offset(max_sz: usize) -> usize {
+    max_sz / 254 + 1
+}
+
+/// Encode the `len` bytes at `buf[start..]` into the start of `buf`,
+/// returning the number of bytes used. The trailing delimiter is NOT written.
+///
+/// `start` must be at least `message_offset(len)`.
+fn encode_in_place(buf: &mut [u8], start: usize, len: usize) -> usize {
+    let mut code_idx = 0;
+    let mut out = 1;
+    let mut code = 1u8;
+
+    for idx in start..start + len {
+        // `out` never passes `idx`, so each input byte is read before
+        // it can be overwritten
+        let byte = buf[idx];
+
+        if byte != 0 {
+            buf[out] = byte;
+            out += 1;
+            code += 1;
+        }
+
+        if byte == 0 || code == 0xFF {
+            buf[code_idx] = code;
+            code_idx = out;
+            out += 1;
+            code = 1;
+        }
+    }
+
+    buf[code_idx] = code;
+    out
+}
+
+/// Decode the bytes of a single frame (without the delimiter) into `dst`,
+/// returning the number of decoded bytes
+fn decode<'b>(src: impl Iterator<Item = &'b u8>, dst: &mut [u8]) -> Result<usize> {
+    let mut out = 0;
+    let mut remaining = 0u8;
+    let mut code = 0xFFu8;
+
+    for &byte in src {
+        if remaining == 0 {
+            // Start of a new block. The end of every block that was not
+            // a maximum length block stands for a zero byte
+            if byte == 0 {
+                return Err(Error::InvalidFrame);
+            }
+            if code != 0xFF {
+                *dst.get_mut(out).ok_or(Error::InsufficientSize)? = 0;
+                out += 1;
+            }
+            code = byte;
+            remaining = byte - 1;
+        } else {
+            *dst.get_mut(out).ok_or(Error::InsufficientSize)? = byte;
+            out += 1;
+            remaining -= 1;
+        }
+    }
+
+    if remaining != 0 || out == 0 && code == 0xFF {
+        // Truncated block, or an empty frame
+        return Err(Error::InvalidFrame);
+    }
+
+    Ok(out)
+}
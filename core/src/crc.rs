@@ -0,0 +1,79 @@
+//! Checksums
+//!
+//! A table driven CRC-32, as used by Ethernet and zlib (reflected, with the
+//! polynomial `0x04C11DB7`, an initial value and final XOR of `0xFFFFFFFF`).
+//! The check value, the checksum of `b"123456789"`, is `0xCBF43926`.
+//!
+//! A table driven CRC-16/CCITT-FALSE (not reflected, with the polynomial
+//! `0x1021`, an initial value of `0xFFFF` and no final XOR). The check value
+//! is `0x29B1`.
+
+/// The reflected CRC-32 polynomial
+const CRC32_POLY: u32 = 0xEDB8_8320;
+
+/// The lookup table for each possible byte, generated at compile time
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculate the CRC-32 of `data`
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF;
+    for &byte in data {
+        crc = (crc >> 8) ^ CRC32_TABLE[usize::from((crc as u8) ^ byte)];
+    }
+    !crc
+}
+
+/// The CRC-16/CCITT polynomial
+const CRC16_POLY: u16 = 0x1021;
+
+/// The lookup table for each possible byte, generated at compile time
+static CRC16_TABLE: [u16; 256] = crc16_table();
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ CRC16_POLY
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculate the CRC-16/CCITT-FALSE of `data`
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF;
+    for &byte in data {
+        crc = (crc << 8) ^ CRC16_TABLE[usize::from(((crc >> 8) as u8) ^ byte)];
+    }
+    crc
+}
@@ -0,0 +1,196 @@
+use crate::{Error, Result};
+#[cfg(feature = "stats")]
+use core::ops::Sub;
+
+/// A monotonic position in the stream of bytes passing through a `BBBuffer`
+///
+/// Unlike the indices used internally by the queue, a `Cursor` does not wrap
+/// around at the end of the buffer, but counts the total number of bytes
+/// that have been committed (for a `Producer`) or released (for a `Consumer`).
+/// This allows two threads to agree on progress without sharing a reference
+/// to the queue.
+///
+/// Subtracting two cursors gives the number of bytes between them, e.g.
+/// `producer.cursor() - consumer.cursor()` is the number of bytes in flight.
+///
+/// NOTE: The position is a 64-bit count on all targets, so will not wrap
+/// around in practice. Subtracting two cursors gives a `usize`, so the two
+/// cursors must be less than `usize::MAX` bytes apart.
+#[cfg(feature = "stats")]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt_0_3", derive(defmt::Format))]
+pub struct Cursor {
+    /// The total number of bytes that have passed this position
+    pub position: u64,
+}
+
+#[cfg(feature = "stats")]
+impl Sub for Cursor {
+    type Output = usize;
+
+    fn sub(self, rhs: Self) -> usize {
+        (self.position as usize).wrapping_sub(rhs.position as usize)
+    }
+}
+
+/// A cursor for incrementally writing into a write grant, created by
+/// `GrantW::cursor()`
+///
+/// The cursor tracks how many bytes have been written, which can then be
+/// passed to `GrantW::commit()`. Each write either fits completely, or
+/// returns `Error::InsufficientSize` without writing anything.
+///
+/// This is the fallible counterpart of the chainable typed write helpers on
+/// `GrantW`, such as `GrantW::write_u8()`, which panic instead. The cursor
+/// does not share its position with those helpers.
+#[derive(Debug, PartialEq)]
+pub struct WriteCursor<'g> {
+    buf: &'g mut [u8],
+    written: usize,
+}
+
+impl<'g> WriteCursor<'g> {
+    pub(crate) fn new(buf: &'g mut [u8]) -> Self {
+        Self { buf, written: 0 }
+    }
+
+    /// The number of bytes written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// The number of bytes that can still be written
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.written
+    }
+
+    /// Write all of `src`
+    pub fn write_bytes(&mut self, src: &[u8]) -> Result<()> {
+        let dest = self
+            .buf
+            .get_mut(self.written..self.written + src.len())
+            .ok_or(Error::InsufficientSize)?;
+        dest.copy_from_slice(src);
+        self.written += src.len();
+        Ok(())
+    }
+
+    /// Write a single byte
+    pub fn write_u8(&mut self, val: u8) -> Result<()> {
+        self.write_bytes(&[val])
+    }
+
+    /// Write a `u16`, in little endian order
+    pub fn write_u16_le(&mut self, val: u16) -> Result<()> {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u32`, in little endian order
+    pub fn write_u32_le(&mut self, val: u32) -> Result<()> {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u64`, in little endian order
+    pub fn write_u64_le(&mut self, val: u64) -> Result<()> {
+        self.write_bytes(&val.to_le_bytes())
+    }
+
+    /// Write a `u16`, in big endian order
+    pub fn write_u16_be(&mut self, val: u16) -> Result<()> {
+        self.write_bytes(&val.to_be_bytes())
+    }
+
+    /// Write a `u32`, in big endian order
+    pub fn write_u32_be(&mut self, val: u32) -> Result<()> {
+        self.write_bytes(&val.to_be_bytes())
+    }
+
+    /// Write a `u64`, in big endian order
+    pub fn write_u64_be(&mut self, val: u64) -> Result<()> {
+        self.write_bytes(&val.to_be_bytes())
+    }
+}
+
+/// A cursor for incrementally reading from a read grant, created by
+/// `GrantR::cursor()`
+///
+/// The cursor tracks how many bytes have been read, which can then be
+/// passed to `GrantR::release()`. Each read either succeeds completely, or
+/// returns `Error::InsufficientSize` without consuming anything.
+///
+/// The typed read helpers on `GrantR`, such as `GrantR::read_u8()`, behave
+/// the same way, but track their own position rather than sharing the
+/// cursor's.
+#[derive(Debug, PartialEq)]
+pub struct ReadCursor<'g> {
+    buf: &'g [u8],
+    consumed: usize,
+}
+
+impl<'g> ReadCursor<'g> {
+    pub(crate) fn new(buf: &'g [u8]) -> Self {
+        Self { buf, consumed: 0 }
+    }
+
+    /// The number of bytes read so far
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// The number of bytes that can still be read
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.consumed
+    }
+
+    /// Fill all of `dest`
+    pub fn read_bytes(&mut self, dest: &mut [u8]) -> Result<()> {
+        let src = self
+            .buf
+            .get(self.consumed..self.consumed + dest.len())
+            .ok_or(Error::InsufficientSize)?;
+        dest.copy_from_slice(src);
+        self.consumed += dest.len();
+        Ok(())
+    }
+
+    fn read_array<const M: usize>(&mut self) -> Result<[u8; M]> {
+        let mut bytes = [0u8; M];
+        self.read_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Read a single byte
+    pub fn read_u8(&mut self) -> Result<u8> {
+        self.read_array().map(u8::from_le_bytes)
+    }
+
+    /// Read a `u16`, in little endian order
+    pub fn read_u16_le(&mut self) -> Result<u16> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    /// Read a `u32`, in little endian order
+    pub fn read_u32_le(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    /// Read a `u64`, in little endian order
+    pub fn read_u64_le(&mut self) -> Result<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    /// Read a `u16`, in big endian order
+    pub fn read_u16_be(&mut self) -> Result<u16> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    /// Read a `u32`, in big endian order
+    pub fn read_u32_be(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    /// Read a `u64`, in big endian order
+    pub fn read_u64_be(&mut self) -> Result<u64> {
+        self.read_array().map(u64::from_be_bytes)
+    }
+}
@@ -0,0 +1,31 @@
+//! Events for waiting in a low power state, see `Consumer::read_wfe()`
+//!
+//! With the `cortex-m` feature on an ARM target, the `Producer` and
+//! `Consumer` execute `SEV` after every commit, release, or close, and
+//! the waiting side sleeps with `WFE` until then. On any other target,
+//! waiting falls back to spinning, so that code using these helpers can
+//! be tested on the host.
+
+/// Signal the other side after an index was stored, so that it wakes up
+/// from `wait()`
+#[inline(always)]
+pub(crate) fn signal() {
+    #[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+    {
+        // Complete the preceding `Release` store before signaling, so that
+        // a waiter that wakes up is guaranteed to observe it
+        cortex_m::asm::dsb();
+        cortex_m::asm::sev();
+    }
+}
+
+/// Wait for a `signal()` from the other side, or any other event
+#[cfg(feature = "cortex-m")]
+#[inline(always)]
+pub(crate) fn wait() {
+    #[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+    cortex_m::asm::wfe();
+
+    #[cfg(not(all(feature = "cortex-m", target_arch = "arm")))]
+    core::hint::spin_loop();
+}
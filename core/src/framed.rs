@@ -69,68 +69,572 @@
 //! | (2^49)..(2^56)        | 8                    |
 //! | (2^56)..(2^64)        | 9                    |
 //!
+//! ## Other headers
+//!
+//! If the range of frame sizes is known in advance, a fixed size header can
+//! be used instead, by splitting the buffer with `try_split_framed_with()`.
+//! `u8`, `u16` and `u32` headers are supported, which use one, two or four
+//! bytes for every frame, regardless of the requested grant size. Requesting
+//! a grant larger than the header can represent returns an error.
+//!
+//! Alternatively, a `Leb128Header` uses the minimum number of bytes for the
+//! size that is actually committed, by moving the frame when it is committed.
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::BBBuffer;
+//!
+//! let bb: BBBuffer<1000> = BBBuffer::new();
+//! let (mut prod, mut cons) = bb.try_split_framed_with::<u8>().unwrap();
+//!
+//! // Frames of up to 255 bytes can be granted
+//! assert!(prod.grant(256).is_err());
+//! prod.grant(255).unwrap().commit(4);
+//!
+//! let rgrant = cons.read().unwrap();
+//! assert_eq!(rgrant.len(), 4);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
+//!
+//! ## Checksums
+//!
+//! Wrapping a header in `Crc16` or `Crc32` stores a checksum of the payload
+//! after each frame, which is written on commit and checked on read. The
+//! space for the checksum is reserved in addition to the frame and header.
+//!
+//! A frame with a bad checksum is discarded by `FrameConsumer::read()`, and
+//! reported as `Error::ChecksumMismatch` by `FrameConsumer::try_read()`. See
+//! `FrameConsumer::set_discard_bad_frames()` to inspect bad frames instead.
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::{framed::Crc32, BBBuffer};
+//!
+//! let bb: BBBuffer<1000> = BBBuffer::new();
+//! let (mut prod, mut cons) = bb.try_split_framed_with::<Crc32<u16>>().unwrap();
+//!
+//! // Two bytes of header, four bytes of checksum
+//! prod.grant(128).unwrap().commit(4);
+//!
+//! let rgrant = cons.try_read().unwrap();
+//! assert_eq!(rgrant.len(), 4);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
 
 use crate::{Consumer, GrantR, GrantW, Producer};
 
 use crate::{
+    crc,
     vusize::{decode_usize, decoded_len, encode_usize_to_slice, encoded_len},
-    Result,
+    Error, Result,
 };
 
 use core::{
     cmp::min,
+    marker::PhantomData,
+    mem::size_of,
     ops::{Deref, DerefMut},
 };
 
+/// The size header stored in front of each frame
+pub trait FrameHeader {
+    /// The number of bytes used by the header of a frame with a maximum size
+    /// of `max_sz` bytes, or `None` if the header can not represent this size
+    fn header_len(max_sz: usize) -> Option<usize>;
+
+    /// Write the header for `frame_len` to the start of `buf`, which is as long
+    /// as returned by `header_len()` when the frame was granted, returning the
+    /// number of bytes used. If fewer bytes are used, the frame is moved to
+    /// directly follow the header when it is committed.
+    fn encode(frame_len: usize, buf: &mut [u8]) -> usize;
+
+    /// Read a header from the start of `buf`, returning the frame length and
+    /// the header length
+    fn decode(buf: &[u8]) -> (usize, usize);
+
+    /// The number of bytes stored after the payload of each frame, such as a
+    /// checksum. This is zero by default.
+    const TRAILER_LEN: usize = 0;
+
+    /// Write the trailer for the committed `frame` to `trailer`, which is
+    /// `TRAILER_LEN` bytes long
+    fn encode_trailer(_frame: &[u8], _trailer: &mut [u8]) {}
+
+    /// Check the `trailer` of a `frame`, returning `false` if it was corrupted
+    fn check_trailer(_frame: &[u8], _trailer: &[u8]) -> bool {
+        true
+    }
+}
+
+/// A variable sized header, which is the default. See the module level
+/// documentation for the size of the header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct VarHeader;
+
+impl FrameHeader for VarHeader {
+    fn header_len(max_sz: usize) -> Option<usize> {
+        Some(encoded_len(max_sz))
+    }
+
+    fn encode(frame_len: usize, buf: &mut [u8]) -> usize {
+        encode_usize_to_slice(frame_len, buf.len(), buf);
+        buf.len()
+    }
+
+    fn decode(buf: &[u8]) -> (usize, usize) {
+        (decode_usize(buf), decoded_len(buf[0]))
+    }
+}
+
+macro_rules! fixed_header {
+    ($($ty:ty),*) => {$(
+        impl FrameHeader for $ty {
+            fn header_len(max_sz: usize) -> Option<usize> {
+                if max_sz as u64 <= <$ty>::MAX as u64 {
+                    Some(size_of::<$ty>())
+                } else {
+                    None
+                }
+            }
+
+            fn encode(frame_len: usize, buf: &mut [u8]) -> usize {
+                buf.copy_from_slice(&(frame_len as $ty).to_le_bytes());
+                size_of::<$ty>()
+            }
+
+            fn decode(buf: &[u8]) -> (usize, usize) {
+                let mut bytes = [0u8; size_of::<$ty>()];
+                bytes.copy_from_slice(&buf[..size_of::<$ty>()]);
+                (<$ty>::from_le_bytes(bytes) as usize, size_of::<$ty>())
+            }
+        }
+    )*};
+}
+
+fixed_header!(u8, u16, u32);
+
+/// A [LEB128] encoded header, which always uses the minimum number of bytes
+/// for the committed frame length, at the cost of moving the frame on commit
+/// when less than the granted size was used.
+///
+/// [LEB128]: https://en.wikipedia.org/wiki/LEB128
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Leb128Header;
+
+impl FrameHeader for Leb128Header {
+    fn header_len(max_sz: usize) -> Option<usize> {
+        let mut len = 1;
+        let mut rest = max_sz >> 7;
+        while rest != 0 {
+            len += 1;
+            rest >>= 7;
+        }
+        Some(len)
+    }
+
+    fn encode(frame_len: usize, buf: &mut [u8]) -> usize {
+        let mut rest = frame_len;
+        let mut len = 0;
+        loop {
+            let byte = (rest & 0x7F) as u8;
+            rest >>= 7;
+            if rest == 0 {
+                buf[len] = byte;
+                return len + 1;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+    }
+
+    fn decode(buf: &[u8]) -> (usize, usize) {
+        let mut frame_len = 0;
+        for (i, byte) in buf.iter().enumerate() {
+            frame_len |= usize::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                return (frame_len, i + 1);
+            }
+        }
+        unreachable!("frame headers are always complete")
+    }
+}
+
+macro_rules! crc_header {
+    ($(#[$doc:meta])* $name:ident, $crc:ident, $len:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub struct $name<H = VarHeader>(PhantomData<H>);
+
+        impl<H: FrameHeader> FrameHeader for $name<H> {
+            const TRAILER_LEN: usize = $len;
+
+            fn header_len(max_sz: usize) -> Option<usize> {
+                H::header_len(max_sz)
+            }
+
+            fn encode(frame_len: usize, buf: &mut [u8]) -> usize {
+                H::encode(frame_len, buf)
+            }
+
+            fn decode(buf: &[u8]) -> (usize, usize) {
+                H::decode(buf)
+            }
+
+            fn encode_trailer(frame: &[u8], trailer: &mut [u8]) {
+                trailer.copy_from_slice(&crc::$crc(frame).to_le_bytes());
+            }
+
+            fn check_trailer(frame: &[u8], trailer: &[u8]) -> bool {
+                trailer == crc::$crc(frame).to_le_bytes()
+            }
+        }
+    };
+}
+
+crc_header!(
+    /// The frame header `H`, followed by a little endian CRC-16/CCITT-FALSE
+    /// of the payload after each frame.
+    ///
+    /// See the module level documentation on checksums.
+    Crc16,
+    crc16,
+    2
+);
+
+crc_header!(
+    /// The frame header `H`, followed by a little endian CRC-32 of the
+    /// payload after each frame, as used by Ethernet and zlib.
+    ///
+    /// See the module level documentation on checksums.
+    Crc32,
+    crc32,
+    4
+);
+
 /// A producer of Framed data
-pub struct FrameProducer<'a, const N: usize> {
+pub struct FrameProducer<'a, const N: usize, H = VarHeader> {
     pub(crate) producer: Producer<'a, N>,
+    pub(crate) max_frame_size: usize,
+    pub(crate) pd: PhantomData<H>,
 }
 
-impl<'a, const N: usize> FrameProducer<'a, N> {
+impl<'a, const N: usize, H: FrameHeader> FrameProducer<'a, N, H> {
     /// Receive a grant for a frame with a maximum size of `max_sz` in bytes.
     ///
     /// This size does not include the size of the frame header. The exact size
     /// of the frame can be set on `commit`.
-    pub fn grant(&mut self, max_sz: usize) -> Result<FrameGrantW<'a, N>> {
-        let hdr_len = encoded_len(max_sz);
+    ///
+    /// If `max_sz` is larger than the maximum frame size, or can not be
+    /// represented by the frame header, `Error::FrameTooLarge` is returned.
+    /// See `BBBuffer::try_split_framed_with_max()` for the maximum frame size.
+    pub fn grant(&mut self, max_sz: usize) -> Result<FrameGrantW<'a, N, H>> {
+        if max_sz > self.max_frame_size {
+            return Err(Error::FrameTooLarge);
+        }
+        let hdr_len = H::header_len(max_sz).ok_or(Error::FrameTooLarge)?;
         Ok(FrameGrantW {
-            grant_w: self.producer.grant_exact(max_sz + hdr_len)?,
+            grant_w: self
+                .producer
+                .grant_exact(max_sz + hdr_len + H::TRAILER_LEN)?,
             hdr_len: hdr_len as u8,
+            to_commit: None,
+            pd: PhantomData,
         })
     }
 }
 
+#[cfg(feature = "postcard")]
+impl<'a, const N: usize, H: FrameHeader> FrameProducer<'a, N, H> {
+    /// Serialize `value` with `postcard` directly into a frame, and commit it
+    ///
+    /// A frame of exactly the serialized size is requested, so if not enough
+    /// space is available, `Error::InsufficientSize` or `Error::FrameTooLarge`
+    /// is returned, as with `grant()`. If `value` can not be serialized,
+    /// `Error::InvalidFrame` is returned. In either case, nothing is committed.
+    pub fn send<T: serde::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let size = postcard::serialize_with_flavor(value, SizeFlavor(0))
+            .map_err(|_| Error::InvalidFrame)?;
+
+        let mut wgr = self.grant(size)?;
+        // The grant is dropped without committing if this fails
+        let used = postcard::to_slice(value, &mut wgr)
+            .map_err(|_| Error::InvalidFrame)?
+            .len();
+        wgr.commit(used);
+        Ok(())
+    }
+}
+
+/// A `postcard` flavor that only counts the serialized bytes
+#[cfg(feature = "postcard")]
+struct SizeFlavor(usize);
+
+#[cfg(feature = "postcard")]
+impl postcard::ser_flavors::Flavor for SizeFlavor {
+    type Output = usize;
+
+    fn try_push(&mut self, _data: u8) -> postcard::Result<()> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+        self.0 += data.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<usize> {
+        Ok(self.0)
+    }
+}
+
 /// A consumer of Framed data
-pub struct FrameConsumer<'a, const N: usize> {
+pub struct FrameConsumer<'a, const N: usize, H = VarHeader> {
     pub(crate) consumer: Consumer<'a, N>,
+    pub(crate) auto_release: bool,
+    pub(crate) discard_bad_frames: bool,
+    pub(crate) pd: PhantomData<H>,
 }
 
-impl<'a, const N: usize> FrameConsumer<'a, N> {
+impl<'a, const N: usize, H: FrameHeader> FrameConsumer<'a, N, H> {
     /// Obtain the next available frame, if any
+    ///
+    /// If the frame header has a trailer that does not match the frame, such
+    /// as a `Crc32`, `None` is returned for that frame. See `try_read()`.
     pub fn read(&mut self) -> Option<FrameGrantR<'a, N>> {
+        self.try_read().ok()
+    }
+
+    /// Obtain the next available frame, checking its trailer
+    ///
+    /// If no frame is available, the error from `Consumer::read()` is returned,
+    /// such as `Error::Empty`. If the trailer does not match the frame, `Error::ChecksumMismatch` is returned,
+    /// and the frame is discarded, so that the next call returns the following
+    /// frame. See `set_discard_bad_frames()` to keep the frame instead.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::{framed::Crc16, BBBuffer, Error};
+    ///
+    /// let bb: BBBuffer<32> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split_framed_with::<Crc16>().unwrap();
+    /// assert_eq!(cons.try_read().unwrap_err(), Error::Empty);
+    ///
+    /// let mut wgr = prod.grant(4).unwrap();
+    /// wgr.copy_from_slice(&[1, 2, 3, 4]);
+    /// wgr.commit(4);
+    ///
+    /// let frame = cons.try_read().unwrap();
+    /// assert_eq!(&frame[..], &[1, 2, 3, 4]);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn try_read(&mut self) -> Result<FrameGrantR<'a, N>> {
+        self.read_frame(true)
+    }
+
+    /// Obtain the next available frame, without checking its trailer
+    ///
+    /// This can be used to inspect a frame that `try_read()` rejected, when
+    /// bad frames are not discarded.
+    pub fn read_unchecked(&mut self) -> Option<FrameGrantR<'a, N>> {
+        self.read_frame(false).ok()
+    }
+
+    fn read_frame(&mut self, check: bool) -> Result<FrameGrantR<'a, N>> {
         // Get all available bytes. We never wrap a frame around,
         // so if a header is available, the whole frame will be.
-        let mut grant_r = self.consumer.read().ok()?;
+        let mut grant_r = self.consumer.read()?;
 
         // Additionally, we never commit less than a full frame with
         // a header, so if we have ANY data, we'll have a full header
         // and frame. `Consumer::read` will return an Error when
         // there are 0 bytes available.
 
-        // The header consists of a single usize, encoded in native
-        // endianess order
-        let frame_len = decode_usize(&grant_r);
-        let hdr_len = decoded_len(grant_r[0]);
+        let (frame_len, hdr_len) = H::decode(&grant_r);
         let total_len = frame_len + hdr_len;
-        let hdr_len = hdr_len as u8;
 
-        debug_assert!(grant_r.len() >= total_len);
+        debug_assert!(grant_r.len() >= total_len + H::TRAILER_LEN);
+
+        if check {
+            let (frame, trailer) = grant_r[hdr_len..].split_at(frame_len);
+            if !H::check_trailer(frame, &trailer[..H::TRAILER_LEN]) {
+                if self.discard_bad_frames {
+                    grant_r.release(total_len + H::TRAILER_LEN);
+                }
+                return Err(Error::ChecksumMismatch);
+            }
+        }
 
-        // Reduce the grant down to the size of the frame with a header
+        // Reduce the grant down to the size of the frame with a header,
+        // and release the trailer along with it
         grant_r.shrink(total_len);
+        grant_r.trailer = H::TRAILER_LEN;
+
+        let mut frame = FrameGrantR {
+            grant_r,
+            hdr_len: hdr_len as u8,
+        };
+        frame.auto_release(self.auto_release);
+        Ok(frame)
+    }
+
+    /// View the payload of the next available frame, if any, without
+    /// reading it
+    ///
+    /// The frame stays in the queue, and is returned by the next call to
+    /// `read()`. `None` is also returned while a frame obtained from `read()`
+    /// has not yet been released or dropped.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let bb: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+    /// assert!(cons.peek_frame().is_none());
+    ///
+    /// prod.grant(4).unwrap().commit(4);
+    /// assert_eq!(cons.peek_frame().unwrap().len(), 4);
+    ///
+    /// let frame = cons.read().unwrap();
+    /// assert_eq!(frame.len(), 4);
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn peek_frame(&self) -> Option<&[u8]> {
+        let buf = self.consumer.peek()?;
+        let (frame_len, hdr_len) = H::decode(buf);
+        buf.get(hdr_len..hdr_len + frame_len)
+    }
+
+    /// Set whether frames returned by `read()` should be automatically
+    /// released when dropped. This is disabled by default.
+    ///
+    /// This can be overridden for a single frame with
+    /// `FrameGrantR::auto_release()`.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let bb: BBBuffer<16> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+    /// cons.set_auto_release(true);
+    ///
+    /// prod.grant(4).unwrap().commit(4);
+    /// {
+    ///     let frame = cons.read().unwrap();
+    ///     assert_eq!(frame.len(), 4);
+    ///     // The frame is released when dropped
+    /// }
+    /// assert!(cons.read().is_none());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn set_auto_release(&mut self, is_auto: bool) {
+        self.auto_release = is_auto;
+    }
 
-        Some(FrameGrantR { grant_r, hdr_len })
+    /// Set whether frames with a bad trailer are discarded by `try_read()`
+    /// and `read()`. This is enabled by default.
+    ///
+    /// When disabled, a bad frame stays in the queue, and every call to
+    /// `try_read()` returns `Error::ChecksumMismatch` until it is obtained
+    /// with `read_unchecked()` and released, for example for diagnostics.
+    pub fn set_discard_bad_frames(&mut self, is_discard: bool) {
+        self.discard_bad_frames = is_discard;
+    }
+
+    /// Iterate over all available frames, in order
+    ///
+    /// Each frame is automatically released when dropped, regardless of
+    /// `set_auto_release()`. Frames with a bad trailer are skipped if they
+    /// are discarded. Iteration ends once no complete frame is available,
+    /// if a bad frame is kept, or if the previous frame is still held when
+    /// the next one is requested.
+    ///
+    /// ```rust
+    /// # // bbqueue test shim!
+    /// # fn bbqtest() {
+    /// use bbqueue::BBBuffer;
+    ///
+    /// let bb: BBBuffer<32> = BBBuffer::new();
+    /// let (mut prod, mut cons) = bb.try_split_framed().unwrap();
+    ///
+    /// for i in 1..4 {
+    ///     prod.grant(i).unwrap().commit(i);
+    /// }
+    ///
+    /// let lens: Vec<usize> = cons.frames().map(|frame| frame.len()).collect();
+    /// assert_eq!(lens, [1, 2, 3]);
+    /// assert!(cons.read().is_none());
+    /// # // bbqueue test shim!
+    /// # }
+    /// #
+    /// # fn main() {
+    /// # #[cfg(not(feature = "thumbv6"))]
+    /// # bbqtest();
+    /// # }
+    /// ```
+    pub fn frames(&mut self) -> impl Iterator<Item = FrameGrantR<'a, N>> + '_ {
+        core::iter::from_fn(move || loop {
+            match self.try_read() {
+                Ok(mut frame) => {
+                    frame.auto_release(true);
+                    return Some(frame);
+                }
+                Err(Error::ChecksumMismatch) if self.discard_bad_frames => {}
+                Err(_) => return None,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl<'a, const N: usize, H: FrameHeader> FrameConsumer<'a, N, H> {
+    /// Deserialize the next available frame with `postcard`, and release it
+    ///
+    /// If no frame is available, `Error::Empty` is returned. If the
+    /// frame can not be deserialized as a `T`, it is released anyway, and
+    /// `Error::InvalidFrame` is returned.
+    pub fn recv<T: serde::de::DeserializeOwned>(&mut self) -> Result<T> {
+        let frame = self.read().ok_or(Error::Empty)?;
+        let res = postcard::from_bytes(&frame).map_err(|_| Error::InvalidFrame);
+        frame.release();
+        res
     }
 }
 
@@ -140,9 +644,13 @@ impl<'a, const N: usize> FrameConsumer<'a, N> {
 /// the contents without first calling `to_commit()`, then no
 /// frame will be comitted for writing.
 #[derive(Debug, PartialEq)]
-pub struct FrameGrantW<'a, const N: usize> {
+pub struct FrameGrantW<'a, const N: usize, H: FrameHeader = VarHeader> {
     grant_w: GrantW<'a, N>,
     hdr_len: u8,
+
+    /// The payload size to commit on drop, if any
+    to_commit: Option<usize>,
+    pd: PhantomData<H>,
 }
 
 /// A read grant for a single frame
@@ -155,17 +663,19 @@ pub struct FrameGrantR<'a, const N: usize> {
     hdr_len: u8,
 }
 
-impl<'a, const N: usize> Deref for FrameGrantW<'a, N> {
+impl<'a, const N: usize, H: FrameHeader> Deref for FrameGrantW<'a, N, H> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.grant_w.buf[self.hdr_len.into()..]
+        let end = self.grant_w.buf.len() - H::TRAILER_LEN;
+        &self.grant_w.buf[self.hdr_len.into()..end]
     }
 }
 
-impl<'a, const N: usize> DerefMut for FrameGrantW<'a, N> {
+impl<'a, const N: usize, H: FrameHeader> DerefMut for FrameGrantW<'a, N, H> {
     fn deref_mut(&mut self) -> &mut [u8] {
-        &mut self.grant_w.buf[self.hdr_len.into()..]
+        let end = self.grant_w.buf.len() - H::TRAILER_LEN;
+        &mut self.grant_w.buf[self.hdr_len.into()..end]
     }
 }
 
@@ -183,16 +693,15 @@ impl<'a, const N: usize> DerefMut for FrameGrantR<'a, N> {
     }
 }
 
-impl<'a, const N: usize> FrameGrantW<'a, N> {
+impl<'a, const N: usize, H: FrameHeader> FrameGrantW<'a, N, H> {
     /// Commit a frame to make it available to the Consumer half.
     ///
     /// `used` is the size of the payload, in bytes, not
-    /// including the frame header
+    /// including the frame header. A `used` of zero commits an
+    /// empty frame, which is read as an empty slice.
     pub fn commit(mut self, used: usize) {
-        let total_len = self.set_header(used);
-
-        // Commit the header + frame
-        self.grant_w.commit(total_len);
+        // The header + frame are committed on drop
+        self.to_commit = Some(used);
     }
 
     /// Set the header and return the total size
@@ -200,22 +709,39 @@ impl<'a, const N: usize> FrameGrantW<'a, N> {
         // Saturate the commit size to the available frame size
         let grant_len = self.grant_w.len();
         let hdr_len: usize = self.hdr_len.into();
-        let frame_len = min(used, grant_len - hdr_len);
-        let total_len = frame_len + hdr_len;
+        let frame_len = min(used, grant_len - hdr_len - H::TRAILER_LEN);
 
         // Write the actual frame length to the header
-        encode_usize_to_slice(frame_len, hdr_len, &mut self.grant_w[..hdr_len]);
+        let used_hdr_len = H::encode(frame_len, &mut self.grant_w[..hdr_len]);
+
+        // Move the frame to directly follow a shorter header
+        if used_hdr_len < hdr_len {
+            self.grant_w
+                .copy_within(hdr_len..hdr_len + frame_len, used_hdr_len);
+        }
 
-        total_len
+        // Write the trailer directly after the frame
+        let (frame, trailer) = self.grant_w[used_hdr_len..].split_at_mut(frame_len);
+        H::encode_trailer(frame, &mut trailer[..H::TRAILER_LEN]);
+
+        used_hdr_len + frame_len + H::TRAILER_LEN
     }
 
     /// Configures the amount of bytes to be commited on drop.
+    ///
+    /// Unlike `commit()`, an `amt` of zero means that no frame is committed.
     pub fn to_commit(&mut self, amt: usize) {
-        if amt == 0 {
-            self.grant_w.to_commit(0);
-        } else {
-            let size = self.set_header(amt);
-            self.grant_w.to_commit(size);
+        self.to_commit = if amt == 0 { None } else { Some(amt) };
+    }
+}
+
+impl<'a, const N: usize, H: FrameHeader> Drop for FrameGrantW<'a, N, H> {
+    fn drop(&mut self) {
+        if let Some(used) = self.to_commit.take() {
+            let total_len = self.set_header(used);
+
+            // The inner grant commits the header + frame when dropped
+            self.grant_w.to_commit(total_len);
         }
     }
 }
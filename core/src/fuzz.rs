@@ -0,0 +1,259 @@
+//! An entry point for fuzzing, used by the `cargo fuzz` targets in `fuzz/`
+//!
+//! A fuzzing run is a script of `Op`s, applied to both halves of a
+//! `BBBuffer` with `apply_ops()`, which checks the internal invariants of the
+//! buffer after each of them. Every committed byte holds a running counter,
+//! so that the `Consumer` can also check that no data was lost, duplicated,
+//! or reordered. With the `test-utils` feature, the result of every `Op` is
+//! also compared with that of the `ModelQueue` reference implementation.
+//!
+//! With the `arbitrary` feature, `Op` implements `arbitrary::Arbitrary`, so
+//! that a fuzzer can generate scripts directly. `run()` decodes a script from
+//! raw bytes instead.
+
+#[cfg(feature = "test-utils")]
+use crate::test_utils::{check_equiv, ModelQueue};
+use crate::{BBBuffer, Consumer, GrantR, GrantW, Producer, Result, SplitGrantR};
+use core::cmp::min;
+
+/// The size of the buffer fuzzed by `run()`, small enough to wrap around often
+const N: usize = 16;
+
+/// An operation on either half of a queue
+///
+/// Operations that need a grant to be held are skipped if there is none, and
+/// errors from requesting a grant are expected, so any sequence of them is a
+/// valid script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Op {
+    /// `Producer::grant_exact()`, unless a write grant is held
+    Grant(u8),
+
+    /// `Producer::grant_max_remaining()`, unless a write grant is held
+    GrantMax(u8),
+
+    /// Fill and commit up to this many bytes of the write grant
+    Commit(u8),
+
+    /// `Consumer::read()`, unless a read grant is held
+    Read,
+
+    /// `Consumer::split_read()`, unless a read grant is held
+    SplitRead,
+
+    /// Release up to this many bytes of the read grant
+    Release(u8),
+
+    /// Drop both grants, without committing or releasing
+    Drop,
+}
+
+/// Run the operations encoded in `data` on a `BBBuffer` of 16 bytes,
+/// panicking if anything goes wrong
+///
+/// Each operation takes two bytes, an opcode and an argument. The argument
+/// is a size of up to two bytes larger than the buffer.
+///
+/// | Opcode (modulo 7) | Operation           |
+/// | :---              | :---                |
+/// | 0                 | `Op::Grant(arg)`    |
+/// | 1                 | `Op::GrantMax(arg)` |
+/// | 2                 | `Op::Commit(arg)`   |
+/// | 3                 | `Op::Read`          |
+/// | 4                 | `Op::Release(arg)`  |
+/// | 5                 | `Op::Drop`          |
+/// | 6                 | `Op::SplitRead`     |
+pub fn run(data: &[u8]) {
+    let bb: BBBuffer<N> = BBBuffer::new();
+    let mut script = Script::new(&bb).unwrap();
+
+    for op in data.chunks_exact(2) {
+        let arg = op[1] % (N as u8 + 3);
+
+        script.apply(match op[0] % 7 {
+            0 => Op::Grant(arg),
+            1 => Op::GrantMax(arg),
+            2 => Op::Commit(arg),
+            3 => Op::Read,
+            4 => Op::Release(arg),
+            5 => Op::Drop,
+            _ => Op::SplitRead,
+        });
+    }
+}
+
+/// Run `ops` on both halves of `bbq`, panicking if anything goes wrong
+///
+/// Returns an error if `bbq` can not be split.
+///
+/// ```rust
+/// use bbqueue::{fuzz::{apply_ops, Op}, BBBuffer};
+///
+/// let bb: BBBuffer<8> = BBBuffer::new();
+/// apply_ops(&bb, &[Op::Grant(6), Op::Commit(6), Op::Read, Op::Release(4)]).unwrap();
+///
+/// // Steps that need a grant, such as committing, are skipped without one
+/// let bb: BBBuffer<8> = BBBuffer::new();
+/// apply_ops(&bb, &[Op::Commit(3), Op::Read, Op::Release(3)]).unwrap();
+/// ```
+pub fn apply_ops<const N: usize>(bbq: &BBBuffer<N>, ops: &[Op]) -> Result<()> {
+    let mut script = Script::new(bbq)?;
+    for op in ops {
+        script.apply(*op);
+    }
+    Ok(())
+}
+
+/// The state of a script, between its `Op`s
+struct Script<'a, const N: usize> {
+    bbq: &'a BBBuffer<N>,
+    prod: Producer<'a, N>,
+    cons: Consumer<'a, N>,
+
+    wgr: Option<GrantW<'a, N>>,
+    rgr: Option<GrantR<'a, N>>,
+    split_rgr: Option<SplitGrantR<'a, N>>,
+
+    /// The counter values of the next bytes to be committed and released
+    next_write: u8,
+    next_read: u8,
+
+    #[cfg(feature = "test-utils")]
+    model: ModelQueue<N>,
+}
+
+impl<'a, const N: usize> Script<'a, N> {
+    fn new(bbq: &'a BBBuffer<N>) -> Result<Self> {
+        let (prod, cons) = bbq.try_split()?;
+        Ok(Self {
+            bbq,
+            prod,
+            cons,
+            wgr: None,
+            rgr: None,
+            split_rgr: None,
+            next_write: 0,
+            next_read: 0,
+            #[cfg(feature = "test-utils")]
+            model: ModelQueue::new(),
+        })
+    }
+
+    fn apply(&mut self, op: Op) {
+        let reading = self.rgr.is_some() || self.split_rgr.is_some();
+
+        match op {
+            Op::Grant(sz) if self.wgr.is_none() => {
+                let grant = self.prod.grant_exact(usize::from(sz));
+                #[cfg(feature = "test-utils")]
+                check_equiv(
+                    format_args!("{:?}", op),
+                    outcome(&grant, |grant| grant.len()),
+                    self.model.grant_exact(usize::from(sz)),
+                );
+                self.wgr = grant.ok();
+            }
+            Op::GrantMax(sz) if self.wgr.is_none() => {
+                let grant = self.prod.grant_max_remaining(usize::from(sz));
+                #[cfg(feature = "test-utils")]
+                check_equiv(
+                    format_args!("{:?}", op),
+                    outcome(&grant, |grant| grant.len()),
+                    self.model.grant_max_remaining(usize::from(sz)),
+                );
+                self.wgr = grant.ok();
+            }
+            Op::Commit(used) => {
+                if let Some(mut grant) = self.wgr.take() {
+                    let used = min(usize::from(used), grant.len());
+                    for byte in grant[..used].iter_mut() {
+                        *byte = self.next_write;
+                        self.next_write = self.next_write.wrapping_add(1);
+                    }
+                    #[cfg(feature = "test-utils")]
+                    self.model.commit(&grant[..used]);
+                    grant.commit(used);
+                }
+            }
+            Op::Read if !reading => {
+                let grant = self.cons.read();
+                if let Ok(grant) = &grant {
+                    self.check_data(grant.iter());
+                }
+                #[cfg(feature = "test-utils")]
+                check_equiv(
+                    format_args!("{:?}", op),
+                    outcome(&grant, |grant| &grant[..]),
+                    self.model.read(),
+                );
+                self.rgr = grant.ok();
+            }
+            Op::SplitRead if !reading => {
+                let grant = self.cons.split_read();
+                if let Ok(grant) = &grant {
+                    let (first, second) = grant.bufs();
+                    self.check_data(first.iter().chain(second));
+                }
+                #[cfg(feature = "test-utils")]
+                check_equiv(
+                    format_args!("{:?}", op),
+                    outcome(&grant, |grant| grant.bufs()),
+                    self.model.split_read(),
+                );
+                self.split_rgr = grant.ok();
+            }
+            Op::Release(used) => {
+                let used = usize::from(used);
+                let released = if let Some(grant) = self.rgr.take() {
+                    let used = min(used, grant.len());
+                    grant.release(used);
+                    used
+                } else if let Some(grant) = self.split_rgr.take() {
+                    let used = min(used, grant.combined_len());
+                    grant.release(used);
+                    used
+                } else {
+                    0
+                };
+                self.next_read = self.next_read.wrapping_add(released as u8);
+                #[cfg(feature = "test-utils")]
+                self.model.release(released);
+            }
+            Op::Drop => {
+                self.wgr = None;
+                self.rgr = None;
+                self.split_rgr = None;
+                #[cfg(feature = "test-utils")]
+                {
+                    self.model.commit(&[]);
+                    self.model.release(0);
+                }
+            }
+            _ => {}
+        }
+
+        self.bbq.check_invariants();
+        #[cfg(feature = "test-utils")]
+        check_equiv(
+            format_args!("bytes_available() after {:?}", op),
+            self.cons.bytes_available(),
+            self.model.len(),
+        );
+    }
+
+    /// Check that a read grant starts at the next byte to be released
+    fn check_data<'b>(&self, data: impl Iterator<Item = &'b u8>) {
+        let mut expected = self.next_read;
+        for byte in data {
+            assert_eq!(*byte, expected, "committed data was corrupted");
+            expected = expected.wrapping_add(1);
+        }
+    }
+}
+
+/// The part of the result of an operation that the model can reproduce
+#[cfg(feature = "test-utils")]
+fn outcome<'g, T, U>(result: &'g Result<T>, f: impl FnOnce(&'g T) -> U) -> Result<U> {
+    result.as_ref().map(f).map_err(|e| *e)
+}
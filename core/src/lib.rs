@@ -93,16 +93,125 @@
 //! This crate contains special support for Cortex-M0(+) targets with the `thumbv6` feature. By
 //! enabling the feature, unsupported atomic operations will be replaced with critical sections
 //! implemented by disabling interrupts. The critical sections are very short, a few instructions at
-//! most, so they should make no difference to most applications.
+//! most, so they should make no difference to most applications. Only `BBBuffer::try_split()` needs
+//! one by default, granting, committing, reading and releasing only load and store atomics. The
+//! `split_grants`, `stats`, `subscribe` and `embedded_hal_1` features use a few more, as noted
+//! below.
+//!
+//! The `critical-section` feature emulates the same operations with the critical sections of the
+//! [`critical-section`] crate instead, for other single core targets without read-modify-write
+//! atomics. The target, or the application, must provide a `critical-section` implementation.
+//! If both are enabled, the `thumbv6` feature takes precedence. It also enables the
+//! `mutex::CsProducer`, which shares a `Producer` with interrupts on `no_std` targets.
+//!
+//! [`critical-section`]: https://docs.rs/critical-section
+//!
+//! On a single core target, the `cortex_m_singlecore` feature drops the memory barriers of the
+//! atomic operations, and only keeps the compiler from reordering memory accesses around them,
+//! which is all that is needed to synchronize with interrupt handlers on the same core. It must not
+//! be used on multi-core systems. Peripherals with DMA access to the buffer are not ordered by it
+//! either, so the DMA driver must issue its own barriers before starting and after finishing a
+//! transfer, as most do. It can be combined with any of the features above.
+//!
+//! For targets without native atomics, such as `riscv32i`, AVR, or MSP430, the `portable-atomic`
+//! feature uses the atomics of the [`portable-atomic`] crate instead. Its own features, such as
+//! `critical-section`, select how atomic operations are implemented on those targets.
+//!
+//! [`portable-atomic`]: https://docs.rs/portable-atomic
+//!
+//! The `cortex-m` feature adds `Producer::grant_wfe()` and `Consumer::read_wfe()`, which wait in a
+//! low power state with `WFE` until the other side signals with `SEV`, which it does after every
+//! commit and release. It is also enabled by the `thumbv6` feature. On targets other than ARM,
+//! these helpers spin instead, so that they can be tested on the host.
+//!
+//! The `cobs` feature enables the `cobs` module, which provides COBS encoded
+//! wrappers around the `Producer` and `Consumer`, useful for serial protocols.
+//! The `slip` feature enables the `slip` module, which provides the same
+//! wrappers using SLIP framing instead.
+//!
+//! The `std` feature enables helpers for interacting with `std::io`, such as
+//! `Consumer::write_to()` and implementations of `std::io::Write` for the
+//! `Producer` and `std::io::Read` for the `Consumer`, `Producer::grant_timeout()`, which sleeps
+//! until space is available or a timeout elapses, and the `arc` module, which allows splitting a
+//! `BBBuffer` held in an `Arc`. It also enables the `std_compat` module, which provides a
+//! `VecDeque`-like adapter, and the `mutex` module, which allows multiple threads to share a
+//! `Producer`. With `std`, a grant that commits or releases bytes while panicking poisons the
+//! queue, see `BBBuffer::is_poisoned()`.
+//!
+//! The `zerocopy_0_7` feature adds `GrantW::write_as()` and `GrantR::read_as()`, which write and
+//! borrow values of any type implementing the `zerocopy` traits, without copying them.
+//!
+//! The `postcard` feature adds `FrameProducer::send()` and `FrameConsumer::recv()`, which
+//! serialize and deserialize typed messages directly to and from frames, using `postcard`.
+//!
+//! The `fuzz` feature enables the `fuzz` module, the entry point of the `cargo fuzz` targets in
+//! `fuzz/`, which runs scripts of operations with `fuzz::apply_ops()` and checks the internal
+//! invariants of the buffer after each of them. The `arbitrary` feature lets a fuzzer generate
+//! these scripts, with `arbitrary::Arbitrary`.
+//!
+//! The `heapless` feature adds `GrantR::copy_to_heapless_vec()`, which copies the contents of a
+//! grant into a `heapless::Vec`.
+//!
+//! The `test-utils` feature enables the `test_utils` module, which provides `ModelQueue`, a plain
+//! reference implementation of the queue, to test code using BBQueue against, or BBQueue itself.
+//! It requires `std`.
+//!
+//! The `embedded_hal_1` feature adds `Producer::set_data_ready_pin()`, which drives an
+//! `embedded-hal` 1.0 output pin high while committed bytes are waiting to be read. With it, every
+//! commit and release takes a read-modify-write operation, which is a critical section with the
+//! `thumbv6` or `critical-section` feature.
+//!
+//! The `subscribe` feature adds `Consumer::subscribe()`, which wakes a `Waker` once enough bytes
+//! have been committed. It costs a full memory barrier in every `commit()`, such as a `DMB` on
+//! Cortex-M, so it is not enabled by default.
+//!
+//! The `stats` feature adds diagnostics counters to the `BBBuffer`: the 64-bit totals of
+//! `BBBuffer::total_bytes_written()` and `BBBuffer::total_bytes_read()`, the `Cursor` positions
+//! derived from them, `Consumer::bytes_lost()` and `BBBuffer::fill_level_ewma()`. The
+//! `watermarks` feature adds `BBBuffer::register_high_watermark()` and
+//! `BBBuffer::register_low_watermark()`. Both add a few words to every `BBBuffer`, and some work
+//! to every commit and release, so they are not enabled by default. `BBBuffer::fill_level_ewma()`
+//! takes a read-modify-write operation on every call.
+//!
+//! The `split_grants` feature adds `GrantR::split_at()`, which divides a read grant into two
+//! `GrantRPart`s that are released independently. Releasing or dropping a part takes a
+//! read-modify-write operation.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
 #![deny(warnings)]
 
+#[macro_use]
+mod sync;
+
 mod bbbuffer;
 pub use bbbuffer::*;
 
+mod cursor;
+#[cfg(feature = "stats")]
+pub use cursor::Cursor;
+pub use cursor::{ReadCursor, WriteCursor};
+
+#[cfg(feature = "std")]
+pub mod arc;
+
+#[cfg(feature = "cobs")]
+pub mod cobs;
+mod crc;
+mod event;
 pub mod framed;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub mod mutex;
+pub mod pingpong;
+#[cfg(feature = "slip")]
+pub mod slip;
+pub mod spin;
+#[cfg(feature = "std")]
+pub mod std_compat;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 mod vusize;
 
 use core::result::Result as CoreResult;
@@ -111,10 +220,22 @@ use core::result::Result as CoreResult;
 pub type Result<T> = CoreResult<T, Error>;
 
 /// Error type used by the `BBQueue` interfaces
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+///
+/// Errors are ordered by the order of their variants, so that they can be
+/// sorted, or counted in a `BTreeMap` or `HashMap`.
+///
+/// More variants may be added in the future, so matching on an `Error` needs
+/// a wildcard arm.
+///
+/// `Error` implements `Display` and `core::error::Error`, and with the `std`
+/// feature converts into a `std::io::Error` of the matching `ErrorKind`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 #[cfg_attr(feature = "defmt_0_3", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error {
-    /// The buffer does not contain sufficient size for the requested action
+    /// The buffer does not contain sufficient size for the requested action.
+    /// When reading, this means that some data is available, but less than
+    /// was requested.
     InsufficientSize,
 
     /// Unable to produce another grant, a grant of this type is already in
@@ -123,4 +244,75 @@ pub enum Error {
 
     /// Unable to split the buffer, as it has already been split
     AlreadySplit,
+
+    /// The data read from the buffer could not be decoded
+    InvalidFrame,
+
+    /// Enough bytes are available for the requested action, but they
+    /// are not contiguous, as they wrap around the end of the buffer
+    NotContiguous,
+
+    /// The other half of the queue has been closed or dropped. For the
+    /// `Consumer`, this is only returned once all remaining data has been read
+    Disconnected,
+
+    /// The requested frame is larger than the maximum frame size, and can
+    /// never be granted
+    FrameTooLarge,
+
+    /// The checksum of the data read from the buffer does not match
+    ChecksumMismatch,
+
+    /// A grant committed or released bytes while panicking, or a stale read
+    /// grant was released, so the data in the queue may be incomplete. See
+    /// `BBBuffer::clear_poison()`
+    Poisoned,
+
+    /// There is nothing to read yet: no data, or no complete frame, has been
+    /// committed
+    Empty,
+
+    /// The grant no longer matches the state of the queue, such as a read
+    /// grant that does not start at the read position anymore, or a grant
+    /// handed out before `BBBuffer::try_release()`, so nothing was committed
+    /// or released
+    StaleGrant,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Error::InsufficientSize => "not enough space or data in the queue",
+            Error::GrantInProgress => "a grant is already in progress",
+            Error::AlreadySplit => "the queue has already been split",
+            Error::InvalidFrame => "the data in the queue could not be decoded",
+            Error::NotContiguous => "the requested bytes wrap around the end of the queue",
+            Error::Disconnected => "the other half of the queue has been closed",
+            Error::FrameTooLarge => "the frame is larger than the maximum frame size",
+            Error::ChecksumMismatch => "the checksum of the data does not match",
+            Error::Poisoned => "the queue was poisoned by a panic",
+            Error::Empty => "there is nothing to read",
+            Error::StaleGrant => "the grant no longer matches the queue",
+        })
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        use std::io::ErrorKind;
+
+        let kind = match err {
+            Error::InsufficientSize | Error::Empty => ErrorKind::WouldBlock,
+            Error::Disconnected => ErrorKind::BrokenPipe,
+            Error::FrameTooLarge => ErrorKind::InvalidInput,
+            Error::InvalidFrame | Error::ChecksumMismatch => ErrorKind::InvalidData,
+            _ => ErrorKind::Other,
+        };
+
+        // Keep the original error, so it can be recovered with `into_inner()`
+        std::io::Error::new(kind, err)
+    }
 }
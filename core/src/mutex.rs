@@ -0,0 +1,281 @@
+//! A `Producer` that can be shared by multiple threads or interrupts
+//!
+//! A `Producer` requires exclusive access to obtain a grant, so only one
+//! thread can push data into the queue at a time. The `SyncProducer` wraps a
+//! `Producer` in a `Mutex`, and holds the lock for as long as a grant exists,
+//! so that any number of threads can obtain grants through a shared
+//! reference. Threads wait for the grants of the others to be committed,
+//! rather than seeing `Error::GrantInProgress`. It requires the `std` feature.
+//!
+//! On `no_std` targets, the `critical-section` feature provides the
+//! `CsProducer` instead. It can not block, as the holder of a grant may be
+//! the code it interrupted, so a grant requested while another one exists
+//! fails with `Error::GrantInProgress`.
+//!
+//! The `Consumer` is not shared, and does not take the lock.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # #[cfg(feature = "std")]
+//! # fn bbqtest() {
+//! use bbqueue::{mutex::SyncProducer, BBBuffer};
+//! use std::thread::spawn;
+//!
+//! static BB: BBBuffer<64> = BBBuffer::new();
+//! let (prod, mut cons) = BB.try_split().unwrap();
+//! let prod: &'static SyncProducer<'static, 64> = Box::leak(Box::new(SyncProducer::new(prod)));
+//!
+//! let threads: Vec<_> = (0..4u8)
+//!     .map(|i| {
+//!         spawn(move || {
+//!             let mut grant = prod.grant_exact(2).unwrap();
+//!             grant.copy_from_slice(&[i, i]);
+//!             grant.commit(2);
+//!         })
+//!     })
+//!     .collect();
+//! for thread in threads {
+//!     thread.join().unwrap();
+//! }
+//!
+//! // The grants of each thread are never interleaved
+//! let grant = cons.read().unwrap();
+//! assert_eq!(grant.len(), 8);
+//! assert!(grant.chunks(2).all(|pair| pair[0] == pair[1]));
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "std")]
+//! # bbqtest();
+//! # }
+//! ```
+
+use crate::{GrantW, Producer, Result};
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "critical-section")]
+use {
+    crate::Error,
+    core::cell::{Cell, UnsafeCell},
+};
+
+/// A `Producer` which can be used by multiple threads at once
+#[cfg(feature = "std")]
+pub struct SyncProducer<'a, const N: usize> {
+    producer: Mutex<Producer<'a, N>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, const N: usize> SyncProducer<'a, N> {
+    /// Wrap a `Producer`, to share it between threads
+    pub fn new(producer: Producer<'a, N>) -> Self {
+        Self {
+            producer: Mutex::new(producer),
+        }
+    }
+
+    /// Release the inner `Producer`
+    pub fn into_inner(self) -> Producer<'a, N> {
+        self.producer
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Request a grant of exactly `sz` bytes, as with `Producer::grant_exact()`,
+    /// waiting for the grants of other threads to be committed first.
+    pub fn grant_exact(&self, sz: usize) -> Result<SyncGrantW<'_, 'a, N>> {
+        let mut guard = self.lock();
+        let grant = guard.grant_exact(sz)?;
+        Ok(SyncGrantW {
+            grant,
+            _guard: guard,
+        })
+    }
+
+    /// Request a grant of up to `sz` bytes, as with `Producer::grant_max_remaining()`,
+    /// waiting for the grants of other threads to be committed first.
+    pub fn grant_max_remaining(&self, sz: usize) -> Result<SyncGrantW<'_, 'a, N>> {
+        let mut guard = self.lock();
+        let grant = guard.grant_max_remaining(sz)?;
+        Ok(SyncGrantW {
+            grant,
+            _guard: guard,
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Producer<'a, N>> {
+        // A thread that panicked while holding a grant has dropped it. That
+        // either left the `Producer` in a consistent state, or poisoned the
+        // queue, which the next grant reports as `Error::Poisoned`
+        self.producer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A write grant obtained from a `SyncProducer`, which holds the lock of the
+/// `SyncProducer` until it is committed or dropped
+///
+/// The contents of the grant are accessed through `Deref` and `DerefMut`.
+#[cfg(feature = "std")]
+pub struct SyncGrantW<'s, 'a, const N: usize> {
+    // NOTE: Must be declared before `_guard`, so it is committed before the
+    // lock is released
+    grant: GrantW<'a, N>,
+    _guard: MutexGuard<'s, Producer<'a, N>>,
+}
+
+#[cfg(feature = "std")]
+impl<'s, 'a, const N: usize> SyncGrantW<'s, 'a, N> {
+    /// Commit `used` bytes, and release the lock. See `GrantW::commit()`
+    pub fn commit(self, used: usize) {
+        let Self { grant, _guard } = self;
+        grant.commit(used);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'s, 'a, const N: usize> Deref for SyncGrantW<'s, 'a, N> {
+    type Target = GrantW<'a, N>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grant
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'s, 'a, const N: usize> DerefMut for SyncGrantW<'s, 'a, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.grant
+    }
+}
+
+/// A `Producer` which can be shared with interrupts, or with other threads, on
+/// `no_std` targets, using a critical section from the [`critical-section`]
+/// crate
+///
+/// Only one grant can exist at a time. As waiting for it to be committed could
+/// deadlock, `grant_exact()` and `grant_max_remaining()` return
+/// `Error::GrantInProgress` instead.
+///
+/// [`critical-section`]: https://docs.rs/critical-section
+#[cfg(feature = "critical-section")]
+pub struct CsProducer<'a, const N: usize> {
+    producer: UnsafeCell<Producer<'a, N>>,
+    locked: critical_section::Mutex<Cell<bool>>,
+}
+
+// SAFETY: The `Producer` is only accessed by whoever set `locked`, which is
+// checked and set within a critical section, and the `Producer` itself can be
+// sent to another thread.
+#[cfg(feature = "critical-section")]
+unsafe impl<'a, const N: usize> Sync for CsProducer<'a, N> {}
+
+#[cfg(feature = "critical-section")]
+impl<'a, const N: usize> CsProducer<'a, N> {
+    /// Wrap a `Producer`, to share it with interrupts or other threads
+    pub fn new(producer: Producer<'a, N>) -> Self {
+        Self {
+            producer: UnsafeCell::new(producer),
+            locked: critical_section::Mutex::new(Cell::new(false)),
+        }
+    }
+
+    /// Release the inner `Producer`
+    pub fn into_inner(self) -> Producer<'a, N> {
+        self.producer.into_inner()
+    }
+
+    /// Request a grant of exactly `sz` bytes, as with `Producer::grant_exact()`.
+    /// Fails with `Error::GrantInProgress` if another grant has not been
+    /// committed yet.
+    pub fn grant_exact(&self, sz: usize) -> Result<CsGrantW<'_, 'a, N>> {
+        let guard = self.lock()?;
+        // SAFETY: `guard` gives exclusive access to the `Producer`
+        let grant = unsafe { &mut *self.producer.get() }.grant_exact(sz)?;
+        Ok(CsGrantW {
+            grant,
+            _guard: guard,
+        })
+    }
+
+    /// Request a grant of up to `sz` bytes, as with `Producer::grant_max_remaining()`.
+    /// Fails with `Error::GrantInProgress` if another grant has not been
+    /// committed yet.
+    pub fn grant_max_remaining(&self, sz: usize) -> Result<CsGrantW<'_, 'a, N>> {
+        let guard = self.lock()?;
+        // SAFETY: `guard` gives exclusive access to the `Producer`
+        let grant = unsafe { &mut *self.producer.get() }.grant_max_remaining(sz)?;
+        Ok(CsGrantW {
+            grant,
+            _guard: guard,
+        })
+    }
+
+    fn lock(&self) -> Result<CsGuard<'_>> {
+        critical_section::with(|cs| {
+            let locked = self.locked.borrow(cs);
+            if locked.replace(true) {
+                Err(Error::GrantInProgress)
+            } else {
+                Ok(CsGuard {
+                    locked: &self.locked,
+                })
+            }
+        })
+    }
+}
+
+/// Clears the flag of a `CsProducer` when dropped
+#[cfg(feature = "critical-section")]
+struct CsGuard<'s> {
+    locked: &'s critical_section::Mutex<Cell<bool>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<'s> Drop for CsGuard<'s> {
+    fn drop(&mut self) {
+        critical_section::with(|cs| self.locked.borrow(cs).set(false));
+    }
+}
+
+/// A write grant obtained from a `CsProducer`, which keeps other grants from
+/// being requested until it is committed or dropped
+///
+/// The contents of the grant are accessed through `Deref` and `DerefMut`.
+#[cfg(feature = "critical-section")]
+pub struct CsGrantW<'s, 'a, const N: usize> {
+    // NOTE: Must be declared before `_guard`, so it is committed before the
+    // flag is cleared
+    grant: GrantW<'a, N>,
+    _guard: CsGuard<'s>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<'s, 'a, const N: usize> CsGrantW<'s, 'a, N> {
+    /// Commit `used` bytes, and allow the next grant. See `GrantW::commit()`
+    pub fn commit(self, used: usize) {
+        let Self { grant, _guard } = self;
+        grant.commit(used);
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'s, 'a, const N: usize> Deref for CsGrantW<'s, 'a, N> {
+    type Target = GrantW<'a, N>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grant
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'s, 'a, const N: usize> DerefMut for CsGrantW<'s, 'a, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.grant
+    }
+}
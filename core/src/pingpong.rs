@@ -0,0 +1,172 @@
+//! Double buffering, for DMA transfers
+//!
+//! A `PingPongBuffer` holds two `BBBuffer`s, which take turns: while one is
+//! filled, for example by a DMA transfer, the other one is drained by the
+//! CPU. Each fill is granted a whole buffer, and once it has been committed,
+//! the `PingPongConsumer` swaps the roles of the buffers as soon as it has
+//! drained the other one.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::{pingpong::PingPongBuffer, Error};
+//!
+//! static PP: PingPongBuffer<4> = PingPongBuffer::new();
+//! let (mut prod, mut cons) = PP.try_split().unwrap();
+//!
+//! // Fill the first buffer, which can be drained once it was committed
+//! let mut grant = prod.begin_fill().unwrap();
+//! grant.copy_from_slice(&[1, 2, 3, 4]);
+//! grant.commit(4);
+//!
+//! // Until it is being drained, there is no other buffer to fill
+//! assert_eq!(prod.begin_fill().unwrap_err(), Error::InsufficientSize);
+//!
+//! let drained = cons.begin_drain().unwrap();
+//! assert_eq!(&*drained, &[1, 2, 3, 4]);
+//!
+//! // Meanwhile, fill the second buffer
+//! let mut grant = prod.begin_fill().unwrap();
+//! grant.copy_from_slice(&[5, 6, 7, 8]);
+//! grant.commit(4);
+//! drained.release(4);
+//!
+//! let drained = cons.begin_drain().unwrap();
+//! assert_eq!(&*drained, &[5, 6, 7, 8]);
+//! drained.release(4);
+//!
+//! // Both buffers have been drained
+//! assert_eq!(cons.begin_drain().unwrap_err(), Error::Empty);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
+
+use crate::{sync::AtomicBool, BBBuffer, Consumer, GrantR, GrantW, Producer, Result};
+use core::sync::atomic::Ordering::{Acquire, Release};
+
+/// Two `BBBuffer`s of `N` bytes, one filled while the other is drained
+#[derive(Debug)]
+pub struct PingPongBuffer<const N: usize> {
+    bufs: [BBBuffer<N>; 2],
+
+    /// Is the second buffer the one being drained?
+    active: AtomicBool,
+}
+
+impl<const N: usize> PingPongBuffer<N> {
+    const_fn! {
+        /// Create a new `PingPongBuffer`, which can be placed at static scope
+        pub fn new() -> Self {
+            Self {
+                bufs: [BBBuffer::new(), BBBuffer::new()],
+                active: AtomicBool::new(false),
+            }
+        }
+    }
+}
+
+#[cfg(not(loom))]
+impl<const N: usize> Default for PingPongBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const N: usize> PingPongBuffer<N> {
+    /// Split both buffers into a `PingPongProducer` and a `PingPongConsumer`.
+    /// If either buffer has already been split, an error is returned.
+    pub fn try_split(&'a self) -> Result<(PingPongProducer<'a, N>, PingPongConsumer<'a, N>)> {
+        let (prod0, cons0) = self.bufs[0].try_split()?;
+        let (prod1, cons1) = match self.bufs[1].try_split() {
+            Ok(halves) => halves,
+            Err(e) => {
+                // Leave the first buffer as it was
+                let _ = self.bufs[0].try_release(prod0, cons0);
+                return Err(e);
+            }
+        };
+
+        Ok((
+            PingPongProducer {
+                bbq: self,
+                producers: [prod0, prod1],
+            },
+            PingPongConsumer {
+                bbq: self,
+                consumers: [cons0, cons1],
+            },
+        ))
+    }
+
+    /// The index of the buffer being drained
+    fn active(&self) -> usize {
+        usize::from(self.active.load(Acquire))
+    }
+}
+
+/// The half of a `PingPongBuffer` that fills the buffers
+pub struct PingPongProducer<'a, const N: usize> {
+    bbq: &'a PingPongBuffer<N>,
+    producers: [Producer<'a, N>; 2],
+}
+
+impl<'a, const N: usize> PingPongProducer<'a, N> {
+    /// Request a grant of a whole buffer, to be filled
+    ///
+    /// This is the buffer that is not being drained, which is only available
+    /// once the `PingPongConsumer` has moved on to the previous fill,
+    /// otherwise `Error::InsufficientSize` is returned. A fill that committed
+    /// nothing is not handed over, so its buffer is granted again.
+    ///
+    /// As waiting for the other buffer is expected, this is not counted in
+    /// `Consumer::bytes_lost()`.
+    pub fn begin_fill(&mut self) -> Result<GrantW<'a, N>> {
+        loop {
+            let active = self.bbq.active();
+            let grant = self.producers[active ^ 1].grant_exact_inner(N)?;
+
+            // The `PingPongConsumer` may have swapped the buffers before this
+            // one was granted, in which case it is the one being drained.
+            // Dropping the grant commits nothing.
+            if self.bbq.active() == active {
+                return Ok(grant);
+            }
+        }
+    }
+}
+
+/// The half of a `PingPongBuffer` that drains the buffers
+pub struct PingPongConsumer<'a, const N: usize> {
+    bbq: &'a PingPongBuffer<N>,
+    consumers: [Consumer<'a, N>; 2],
+}
+
+impl<'a, const N: usize> PingPongConsumer<'a, N> {
+    /// Obtain a read grant of the oldest completed fill, which has not been
+    /// released yet
+    ///
+    /// Once the buffer being drained is empty, it is handed back to the
+    /// `PingPongProducer`, and the other buffer is drained instead, if it
+    /// holds a completed fill. If there is none, `Error::Empty` is returned.
+    pub fn begin_drain(&mut self) -> Result<GrantR<'a, N>> {
+        // Only the `PingPongConsumer` swaps the buffers, so `active` is
+        // simply stored. A fill is complete once anything was committed, as
+        // a buffer that is not empty can not be granted as a whole again.
+        let active = self.bbq.active();
+        if self.consumers[active].bytes_available() == 0
+            && self.consumers[active ^ 1].bytes_available() != 0
+        {
+            self.bbq.active.store(active == 0, Release);
+            self.consumers[active ^ 1].read()
+        } else {
+            self.consumers[active].read()
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! A SLIP flavor of BBQueue, useful for serial protocols
+//!
+//! This module provides wrappers around a `Producer` and `Consumer` which
+//! use the [Serial Line Internet Protocol] framing to delimit messages. Each
+//! message is escaped, and surrounded by `0xC0` delimiters, which means that
+//! the contents of the queue can be sent out over a serial port as-is, and
+//! the receiving side can re-synchronize on the next delimiter.
+//!
+//! [Serial Line Internet Protocol]: https://datatracker.ietf.org/doc/html/rfc1055
+//!
+//! ## Example
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::{
+//!     slip::{SlipDecoder, SlipProducer},
+//!     BBBuffer,
+//! };
+//!
+//! let bb: BBBuffer<64> = BBBuffer::new();
+//! let (prod, cons) = bb.try_split().unwrap();
+//! let mut prod = SlipProducer::new(prod);
+//! let mut cons: SlipDecoder<64, 16> = SlipDecoder::new(cons);
+//!
+//! // One message in, one message out
+//! prod.send(&[1, 0xC0, 2, 0xDB]).unwrap();
+//! assert_eq!(cons.recv().unwrap(), &[1, 0xC0, 2, 0xDB]);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
+//!
+//! ## Encoding overhead
+//!
+//! Every `0xC0` and `0xDB` byte in a message is escaped as two bytes, so in
+//! the worst case a message doubles in size, plus the leading and trailing
+//! delimiters. A write grant of this worst case size is requested when
+//! sending, and only the actually used portion is committed.
+//!
+//! The leading delimiter terminates any line noise received before the
+//! message, as recommended by RFC 1055. Empty messages are indistinguishable
+//! from repeated delimiters, and are skipped when receiving.
+//!
+//! ## Encoding in place
+//!
+//! Like the `cobs` module, `SlipProducer::grant()` allows writing a message
+//! directly into the queue, which is escaped in place when committed, and
+//! `SlipDecoder::recv_raw()` hands out a still encoded message.
+
+use crate::{Consumer, Error, GrantR, GrantW, Producer, Result};
+use core::{
+    cmp::min,
+    ops::{Deref, DerefMut},
+};
+
+/// The frame delimiter
+pub const END: u8 = 0xC0;
+/// The escape byte
+pub const ESC: u8 = 0xDB;
+/// An escaped `END` byte, following an `ESC` byte
+pub const ESC_END: u8 = 0xDC;
+/// An escaped `ESC` byte, following an `ESC` byte
+pub const ESC_ESC: u8 = 0xDD;
+
+/// The maximum number of bytes `data_len` bytes will take once encoded,
+/// including the leading and trailing delimiters
+pub const fn max_encoded_len(data_len: usize) -> usize {
+    2 * data_len + 2
+}
+
+/// A producer of SLIP encoded messages
+pub struct SlipProducer<'a, const N: usize> {
+    producer: Producer<'a, N>,
+}
+
+impl<'a, const N: usize> SlipProducer<'a, N> {
+    /// Wrap a `Producer` to send SLIP encoded messages
+    pub fn new(producer: Producer<'a, N>) -> Self {
+        Self { producer }
+    }
+
+    /// Release the inner `Producer`
+    pub fn into_inner(self) -> Producer<'a, N> {
+        self.producer
+    }
+
+    /// Encode `data`, surrounded by `0xC0` delimiters, into the queue
+    ///
+    /// If not enough space is available for the worst case encoded size
+    /// of `data`, an error is returned and the queue is not modified.
+    pub fn send(&mut self, data: &[u8]) -> Result<()> {
+        let mut wgr = self.grant(data.len())?;
+        wgr.copy_from_slice(data);
+        wgr.commit(data.len());
+        Ok(())
+    }
+
+    /// Obtain a grant for a message of up to `max_sz` bytes, which is encoded
+    /// in place when committed
+    ///
+    /// If not enough space is available for the worst case encoded size of
+    /// the message, an error is returned.
+    pub fn grant(&mut self, max_sz: usize) -> Result<SlipGrantW<'a, N>> {
+        Ok(SlipGrantW {
+            grant_w: self.producer.grant_exact(max_encoded_len(max_sz))?,
+            max_sz,
+        })
+    }
+}
+
+/// A write grant for a single SLIP encoded message
+///
+/// The message is written unencoded, and encoded in place by `commit()`.
+/// If the grant is dropped without committing, no message is sent.
+#[derive(Debug, PartialEq)]
+pub struct SlipGrantW<'a, const N: usize> {
+    grant_w: GrantW<'a, N>,
+    max_sz: usize,
+}
+
+impl<'a, const N: usize> SlipGrantW<'a, N> {
+    /// Encode the first `used` bytes of the message, surrounded by `0xC0`
+    /// delimiters, and commit them
+    ///
+    /// If `used` is larger than the grant, the whole grant is committed.
+    pub fn commit(mut self, used: usize) {
+        let used = min(used, self.max_sz);
+        let start = message_offset(self.max_sz);
+        let len = encode_in_place(&mut self.grant_w, start, used);
+        self.grant_w[len] = END;
+        self.grant_w.commit(len + 1);
+    }
+}
+
+impl<'a, const N: usize> Deref for SlipGrantW<'a, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        let start = message_offset(self.max_sz);
+        &self.grant_w.buf[start..][..self.max_sz]
+    }
+}
+
+impl<'a, const N: usize> DerefMut for SlipGrantW<'a, N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let start = message_offset(self.max_sz);
+        &mut self.grant_w.buf[start..][..self.max_sz]
+    }
+}
+
+/// A consumer of SLIP encoded messages
+///
+/// Messages are decoded into an internal scratch buffer of `M` bytes,
+/// as the encoded message may be wrapped around the end of the queue.
+pub struct SlipDecoder<'a, const N: usize, const M: usize> {
+    consumer: Consumer<'a, N>,
+    scratch: [u8; M],
+}
+
+impl<'a, const N: usize, const M: usize> SlipDecoder<'a, N, M> {
+    /// Wrap a `Consumer` to receive SLIP encoded messages
+    pub fn new(consumer: Consumer<'a, N>) -> Self {
+        Self {
+            consumer,
+            scratch: [0u8; M],
+        }
+    }
+
+    /// Release the inner `Consumer`
+    pub fn into_inner(self) -> Consumer<'a, N> {
+        self.consumer
+    }
+
+    /// Obtain the next complete message, if any
+    ///
+    /// If no complete message has been committed yet, `Error::Empty` is
+    /// returned and no data is consumed, other than leading delimiters.
+    ///
+    /// If the message contains an invalid escape sequence, or the decoded
+    /// message does not fit in the scratch buffer, the message (and its
+    /// delimiter) are discarded from the queue, and `Error::InvalidFrame` or
+    /// `Error::InsufficientSize` is returned respectively.
+    pub fn recv(&mut self) -> Result<&[u8]> {
+        let rgr = self.consumer.split_read()?;
+        let (buf1, buf2) = rgr.bufs();
+
+        let (start, end) = match frame_bounds(buf1, buf2) {
+            Ok(bounds) => bounds,
+            Err(skip) => {
+                rgr.release(skip);
+                return Err(Error::Empty);
+            }
+        };
+
+        let res = decode(
+            buf1.iter().chain(buf2.iter()).take(end).skip(start),
+            &mut self.scratch,
+        );
+
+        // Discard the frame and its delimiter, even if it could not be decoded
+        rgr.release(end + 1);
+
+        res.map(move |len| &self.scratch[..len])
+    }
+
+    /// Obtain the next complete message, if any, without decoding it
+    ///
+    /// The grant contains the encoded message, including any leading
+    /// delimiters and its trailing delimiter. If no complete message has been
+    /// committed yet, `Error::Empty` is returned. A message that
+    /// wraps around the end of the queue can not be handed out as a single
+    /// grant, and `Error::NotContiguous` is returned, in which case `recv()`
+    /// can be used instead.
+    pub fn recv_raw(&mut self) -> Result<GrantR<'a, N>> {
+        let rgr = self.consumer.split_read()?;
+        let (buf1, buf2) = rgr.bufs();
+
+        match frame_bounds(buf1, buf2) {
+            Ok((_, end)) if end < buf1.len() => {
+                let mut rgr = rgr.into_first();
+                rgr.shrink(end + 1);
+                Ok(rgr)
+            }
+            Ok(_) => Err(Error::NotContiguous),
+            Err(_) => Err(Error::Empty),
+        }
+    }
+}
+
+/// Find the next non-empty frame, returning the position of its first byte
+/// and of its trailing delimiter
+///
+/// If there is no complete frame, the number of leading delimiters is
+/// returned as an error.
+fn frame_bounds(buf1: &[u8], buf2: &[u8]) -> core::result::Result<(usize, usize), usize> {
+    let mut bytes = buf1.iter().chain(buf2.iter());
+    let start = match bytes.position(|b| *b != END) {
+        Some(start) => start,
+        None => return Err(buf1.len() + buf2.len()),
+    };
+
+    match bytes.position(|b| *b == END) {
+        Some(len) => Ok((start, start + 1 + len)),
+        None => Err(start),
+    }
+}
+
+/// The offset of the unencoded message in a `SlipGrantW`
+///
+/// The message is placed in the upper half of the grant, which guarantees
+/// that the encoded output never overtakes the unencoded input in
+/// `encode_in_place()`.
+const fn message_offset(max_sz: usize) -> usize {
+    max_sz + 1
+}
+
+/// Encode the `len` bytes at `buf[start..]` into the start of `buf`, with a
+/// leading delimiter, returning the number of bytes used. The trailing
+/// delimiter is NOT written.
+///
+/// `start` must be at least `message_offset(len)`.
+fn encode_in_place(buf: &mut [u8], start: usize, len: usize) -> usize {
+    buf[0] = END;
+    let mut out = 1;
+
+    for idx in start..start + len {
+        // `out` never passes `idx`, so each input byte is read before
+        // it can be overwritten
+        let byte = buf[idx];
+
+        match byte {
+            END => {
+                buf[out] = ESC;
+                buf[out + 1] = ESC_END;
+                out += 2;
+            }
+            ESC => {
+                buf[out] = ESC;
+                buf[out + 1] = ESC_ESC;
+                out += 2;
+            }
+            _ => {
+                buf[out] = byte;
+                out += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Decode the bytes of a single frame (without delimiters) into `dst`,
+/// returning the number of decoded bytes
+fn decode<'b>(src: impl Iterator<Item = &'b u8>, dst: &mut [u8]) -> Result<usize> {
+    let mut out = 0;
+    let mut escaped = false;
+
+    for &byte in src {
+        let byte = match (escaped, byte) {
+            (false, ESC) => {
+                escaped = true;
+                continue;
+            }
+            (false, byte) => byte,
+            (true, ESC_END) => END,
+            (true, ESC_ESC) => ESC,
+            (true, _) => return Err(Error::InvalidFrame),
+        };
+        escaped = false;
+
+        *dst.get_mut(out).ok_or(Error::InsufficientSize)? = byte;
+        out += 1;
+    }
+
+    if escaped {
+        // Truncated escape sequence
+        return Err(Error::InvalidFrame);
+    }
+
+    Ok(out)
+}
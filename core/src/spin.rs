@@ -0,0 +1,117 @@
+//! A busy-waiting flavor of the `Producer`
+//!
+//! In environments without an async executor or interrupt driven
+//! notifications, it is sometimes acceptable to simply busy-wait until
+//! space becomes available in the queue. The `SpinProducer` wraps a
+//! `Producer`, retrying grant requests until they succeed, optionally
+//! giving up after a configurable number of attempts.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # // bbqueue test shim!
+//! # fn bbqtest() {
+//! use bbqueue::{spin::SpinProducer, BBBuffer, Error};
+//!
+//! let bb: BBBuffer<6> = BBBuffer::new();
+//! let (prod, mut cons) = bb.try_split().unwrap();
+//!
+//! // Give up after 100 failed attempts
+//! let mut prod = SpinProducer::with_max_spins(prod, 100);
+//!
+//! prod.grant_spin(4).unwrap().commit(4);
+//!
+//! // No consumer is making room, so this gives up
+//! assert_eq!(prod.grant_spin(4).unwrap_err(), Error::InsufficientSize);
+//! # // bbqueue test shim!
+//! # }
+//! #
+//! # fn main() {
+//! # #[cfg(not(feature = "thumbv6"))]
+//! # bbqtest();
+//! # }
+//! ```
+
+use crate::{Error, GrantW, Producer, Result};
+use core::hint::spin_loop;
+
+/// A `Producer` that busy-waits for space to become available
+pub struct SpinProducer<'a, const N: usize> {
+    producer: Producer<'a, N>,
+    max_spins: Option<usize>,
+}
+
+impl<'a, const N: usize> SpinProducer<'a, N> {
+    /// Wrap a `Producer`, spinning forever until grants succeed
+    pub fn new(producer: Producer<'a, N>) -> Self {
+        Self {
+            producer,
+            max_spins: None,
+        }
+    }
+
+    /// Wrap a `Producer`, giving up on a grant after `max_spins` failed
+    /// attempts, to allow for other backoff strategies
+    pub fn with_max_spins(producer: Producer<'a, N>, max_spins: usize) -> Self {
+        Self {
+            producer,
+            max_spins: Some(max_spins),
+        }
+    }
+
+    /// Release the inner `Producer`
+    pub fn into_inner(self) -> Producer<'a, N> {
+        self.producer
+    }
+
+    /// Request a grant of exactly `sz` bytes, as with `Producer::grant_exact()`,
+    /// spinning until it succeeds.
+    ///
+    /// If a grant is already in progress, or `sz` is zero or larger than the
+    /// buffer, an error is returned immediately, as waiting would never
+    /// succeed.
+    pub fn grant_spin(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
+        let Self {
+            producer,
+            max_spins,
+        } = self;
+        if sz == 0 || sz > N {
+            return producer.record_failure(sz, Err(Error::InsufficientSize));
+        }
+        let res = spin(*max_spins, || producer.grant_exact_inner(sz));
+        producer.record_failure(sz, res)
+    }
+
+    /// Request a grant of up to `sz` bytes, as with `Producer::grant_max_remaining()`,
+    /// spinning until it succeeds.
+    ///
+    /// If a grant is already in progress, or `sz` is zero, an error is
+    /// returned immediately, as waiting would never succeed.
+    pub fn grant_max_spin(&mut self, sz: usize) -> Result<GrantW<'a, N>> {
+        let Self {
+            producer,
+            max_spins,
+        } = self;
+        if sz == 0 {
+            return Err(Error::InsufficientSize);
+        }
+        let res = spin(*max_spins, || producer.grant_max_with_minimum_inner(sz, 1));
+        producer.record_failure(sz, res)
+    }
+}
+
+fn spin<T>(max_spins: Option<usize>, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut spins = 0;
+    loop {
+        match f() {
+            Err(Error::InsufficientSize) => {}
+            res => return res,
+        }
+
+        spins += 1;
+        if matches!(max_spins, Some(max) if spins >= max) {
+            return Err(Error::InsufficientSize);
+        }
+        spin_loop();
+    }
+}
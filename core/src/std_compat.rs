@@ -0,0 +1,102 @@
+//! Familiar APIs from `std`, for code migrating to BBQueue
+//!
+//! ## Example
+//!
+//! ```rust
+//! use bbqueue::{std_compat::VecDequeAdapter, BBBuffer};
+//!
+//! let bb: BBBuffer<8> = BBBuffer::new();
+//! let mut queue = VecDequeAdapter::new(&bb).unwrap();
+//!
+//! queue.push_back(1).unwrap();
+//! queue.push_back_slice(&[2, 3]).unwrap();
+//! assert_eq!(queue.len(), 3);
+//!
+//! assert_eq!(queue.pop_front(), Some(1));
+//! assert_eq!(queue.pop_front(), Some(2));
+//! assert_eq!(queue.pop_front(), Some(3));
+//! assert_eq!(queue.pop_front(), None);
+//! ```
+
+use crate::{BBBuffer, Consumer, Producer, Result};
+
+/// A byte queue with the API of a `VecDeque<u8>`, holding both halves of a
+/// `BBBuffer`
+///
+/// Unlike a `VecDeque`, the queue never grows, so pushing returns an error
+/// once it is full. Each call takes a grant of its own, so this is mostly
+/// useful while migrating, or when the producer and consumer are the same
+/// piece of code.
+pub struct VecDequeAdapter<'a, const N: usize> {
+    producer: Producer<'a, N>,
+    consumer: Consumer<'a, N>,
+}
+
+impl<'a, const N: usize> VecDequeAdapter<'a, N> {
+    /// Split `bbq`, and hold on to both halves
+    ///
+    /// As with `BBBuffer::try_split()`, an error is returned if the buffer has
+    /// already been split.
+    pub fn new(bbq: &'a BBBuffer<N>) -> Result<Self> {
+        let (producer, consumer) = bbq.try_split()?;
+        Ok(Self::from_parts(producer, consumer))
+    }
+
+    /// Hold on to an existing `Producer` and `Consumer` of the same buffer
+    pub fn from_parts(producer: Producer<'a, N>, consumer: Consumer<'a, N>) -> Self {
+        Self { producer, consumer }
+    }
+
+    /// Release the `Producer` and `Consumer`
+    pub fn into_parts(self) -> (Producer<'a, N>, Consumer<'a, N>) {
+        (self.producer, self.consumer)
+    }
+
+    /// Append a byte to the back of the queue
+    ///
+    /// If the queue is full, `Error::InsufficientSize` is returned. As the
+    /// byte may be pushed again, it is not counted in
+    /// `Consumer::bytes_lost()`.
+    pub fn push_back(&mut self, byte: u8) -> Result<()> {
+        let mut grant = self.producer.grant_exact_inner(1)?;
+        grant[0] = byte;
+        grant.commit(1);
+        Ok(())
+    }
+
+    /// Append all of `data` to the back of the queue
+    ///
+    /// Either all bytes are pushed, or none are, and `Error::InsufficientSize`
+    /// is returned. As the bytes are pushed contiguously, as with
+    /// `Producer::grant_exact()`, this may fail even if `len()` plus the length
+    /// of `data` is within the capacity. As with `push_back()`, a failure is
+    /// not counted in `Consumer::bytes_lost()`.
+    pub fn push_back_slice(&mut self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut grant = self.producer.grant_exact_inner(data.len())?;
+        grant.copy_from_slice(data);
+        grant.commit(data.len());
+        Ok(())
+    }
+
+    /// Remove the byte at the front of the queue, if any
+    pub fn pop_front(&mut self) -> Option<u8> {
+        let grant = self.consumer.read().ok()?;
+        let byte = grant[0];
+        grant.release(1);
+        Some(byte)
+    }
+
+    /// The number of bytes in the queue
+    pub fn len(&self) -> usize {
+        self.consumer.bytes_available()
+    }
+
+    /// Returns `true` if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.consumer.is_empty()
+    }
+}
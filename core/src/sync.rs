@@ -0,0 +1,225 @@
+//! Synchronization primitives
+//!
+//! With the `portable-atomic` feature, the atomics of the `portable-atomic`
+//! crate are used instead of those of `core`, for targets without native
+//! atomics, or without read-modify-write operations.
+//!
+//! With the `cortex_m_singlecore` feature, the atomics are wrapped so that
+//! every access is `Relaxed`, and the requested ordering is only enforced
+//! with a `compiler_fence`. On a single core, this is enough to order the
+//! accesses of the `Producer` and `Consumer`, whether they run in thread or
+//! interrupt context, without the `DMB` barriers of `Acquire` and `Release`.
+//!
+//! When built with `RUSTFLAGS="--cfg loom"`, these are replaced by the
+//! equivalents of the [loom] model checker, so that the tests in `bbqtest`
+//! can explore every interleaving of the `Producer` and `Consumer`.
+//!
+//! The storage of the buffer itself is always a `core::cell::UnsafeCell`, as
+//! grants hold on to it for longer than `loom` can track an access.
+//!
+//! The `fence` and `UnsafeCell` are only used by subscriptions, see
+//! `Consumer::subscribe()`, and the `AtomicPtr` only by watermark callbacks
+//! and the data ready pin, so they are unused without the `subscribe`,
+//! `watermarks` and `embedded_hal_1` features.
+//!
+//! [loom]: https://docs.rs/loom
+
+#[cfg(not(any(loom, feature = "portable-atomic", feature = "cortex_m_singlecore")))]
+#[allow(unused_imports)]
+pub(crate) use core::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+#[cfg(loom)]
+#[allow(unused_imports)]
+pub(crate) use loom::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+#[cfg(all(
+    not(loom),
+    feature = "portable-atomic",
+    not(feature = "cortex_m_singlecore")
+))]
+#[allow(unused_imports)]
+pub(crate) use portable_atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+#[cfg(all(not(loom), feature = "cortex_m_singlecore"))]
+#[allow(unused_imports)]
+pub(crate) use singlecore::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+
+/// Atomics for a single core, which only order accesses with compiler fences
+#[cfg(all(not(loom), feature = "cortex_m_singlecore"))]
+mod singlecore {
+    #[cfg(not(feature = "portable-atomic"))]
+    use core::sync::atomic as native;
+    use core::sync::atomic::{
+        compiler_fence,
+        Ordering::{self, AcqRel, Acquire, Relaxed, Release, SeqCst},
+    };
+    #[cfg(feature = "portable-atomic")]
+    use portable_atomic as native;
+
+    /// A fence only needs to keep the compiler from reordering accesses
+    #[cfg_attr(not(feature = "subscribe"), allow(dead_code))]
+    #[inline(always)]
+    pub(crate) fn fence(order: Ordering) {
+        compiler_fence(order);
+    }
+
+    /// Order the accesses before a store with `order` before it
+    #[inline(always)]
+    fn before(order: Ordering) {
+        if let Release | AcqRel | SeqCst = order {
+            compiler_fence(Release);
+        }
+    }
+
+    /// Order the accesses after a load with `order` after it
+    #[inline(always)]
+    fn after(order: Ordering) {
+        if let Acquire | AcqRel | SeqCst = order {
+            compiler_fence(Acquire);
+        }
+    }
+
+    macro_rules! singlecore_atomic {
+        ($name:ident, $ty:ty) => {
+            #[derive(Debug)]
+            pub(crate) struct $name(native::$name);
+
+            impl $name {
+                pub(crate) const fn new(val: $ty) -> Self {
+                    Self(native::$name::new(val))
+                }
+
+                #[inline(always)]
+                pub(crate) fn load(&self, order: Ordering) -> $ty {
+                    let val = self.0.load(Relaxed);
+                    after(order);
+                    val
+                }
+
+                #[inline(always)]
+                pub(crate) fn store(&self, val: $ty, order: Ordering) {
+                    before(order);
+                    self.0.store(val, Relaxed);
+                }
+            }
+        };
+    }
+
+    singlecore_atomic!(AtomicBool, bool);
+    singlecore_atomic!(AtomicUsize, usize);
+
+    /// An `AtomicPtr`, which only supports loads and stores
+    #[cfg_attr(
+        not(any(feature = "watermarks", feature = "embedded_hal_1")),
+        allow(dead_code)
+    )]
+    #[derive(Debug)]
+    pub(crate) struct AtomicPtr<T>(native::AtomicPtr<T>);
+
+    #[cfg_attr(
+        not(any(feature = "watermarks", feature = "embedded_hal_1")),
+        allow(dead_code)
+    )]
+    impl<T> AtomicPtr<T> {
+        pub(crate) const fn new(ptr: *mut T) -> Self {
+            Self(native::AtomicPtr::new(ptr))
+        }
+
+        #[inline(always)]
+        pub(crate) fn load(&self, order: Ordering) -> *mut T {
+            let ptr = self.0.load(Relaxed);
+            after(order);
+            ptr
+        }
+
+        #[inline(always)]
+        pub(crate) fn store(&self, ptr: *mut T, order: Ordering) {
+            before(order);
+            self.0.store(ptr, Relaxed);
+        }
+    }
+
+    // Read-modify-write operations, for targets which have them. On armv6-m,
+    // the `thumbv6` or `critical-section` feature emulates them with loads and
+    // stores instead.
+    #[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
+    impl AtomicBool {
+        #[inline(always)]
+        pub(crate) fn swap(&self, val: bool, order: Ordering) -> bool {
+            before(order);
+            let prev = self.0.swap(val, Relaxed);
+            after(order);
+            prev
+        }
+    }
+
+    #[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
+    impl AtomicUsize {
+        #[cfg(any(feature = "split_grants", feature = "embedded_hal_1"))]
+        #[inline(always)]
+        pub(crate) fn fetch_add(&self, val: usize, order: Ordering) -> usize {
+            before(order);
+            let prev = self.0.fetch_add(val, Relaxed);
+            after(order);
+            prev
+        }
+
+        #[cfg(feature = "subscribe")]
+        #[inline(always)]
+        pub(crate) fn fetch_or(&self, val: usize, order: Ordering) -> usize {
+            before(order);
+            let prev = self.0.fetch_or(val, Relaxed);
+            after(order);
+            prev
+        }
+
+        #[cfg(any(feature = "stats", feature = "subscribe", feature = "embedded_hal_1"))]
+        #[inline(always)]
+        pub(crate) fn compare_exchange(
+            &self,
+            current: usize,
+            new: usize,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<usize, usize> {
+            before(success);
+            let res = self.0.compare_exchange(current, new, Relaxed, Relaxed);
+            after(if res.is_ok() { success } else { failure });
+            res
+        }
+    }
+}
+
+#[cfg(loom)]
+#[cfg_attr(not(feature = "subscribe"), allow(unused_imports))]
+pub(crate) use loom::cell::UnsafeCell;
+
+/// An `UnsafeCell` with the closure based API of `loom`
+#[cfg(not(loom))]
+#[cfg_attr(not(feature = "subscribe"), allow(dead_code))]
+#[derive(Debug)]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+#[cfg_attr(not(feature = "subscribe"), allow(dead_code))]
+impl<T> UnsafeCell<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        Self(core::cell::UnsafeCell::new(data))
+    }
+
+    #[inline(always)]
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
+
+/// Define a `const fn`, which is not `const` when built with `--cfg loom`, as
+/// the atomics of `loom` can not be created in a constant context
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])*
+        $vis const fn $($rest)*
+
+        #[cfg(loom)]
+        $(#[$attr])*
+        $vis fn $($rest)*
+    };
+}
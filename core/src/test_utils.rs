@@ -0,0 +1,365 @@
+//! A reference model of the queue, for differential testing
+//!
+//! `ModelQueue` has the same grant, commit, read, and release operations as
+//! the `Producer` and `Consumer`, but is written as plainly as possible: the
+//! committed bytes are held in a `VecDeque`, and the positions of the
+//! BipBuffer are kept only to decide how large each grant is. Running the
+//! same operations on a real queue and on the model, and comparing what
+//! both return with `check_equiv()`, finds bugs without writing out the
+//! expected result of every step by hand. `Differential` does exactly that.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use bbqueue::{test_utils::Differential, BBBuffer};
+//!
+//! let bb: BBBuffer<8> = BBBuffer::new();
+//! let mut queue = Differential::new(&bb).unwrap();
+//!
+//! // Each step panics if the queue and the model disagree
+//! queue.grant_exact(6);
+//! queue.commit(6);
+//! queue.read();
+//! queue.release(4);
+//!
+//! // Wrap around, to the start of the buffer
+//! queue.grant_exact(3);
+//! queue.commit(3);
+//! queue.read();
+//! ```
+
+use crate::{BBBuffer, Consumer, Error, GrantR, GrantW, Producer, Result};
+use core::{cmp::min, fmt};
+use std::collections::VecDeque;
+
+/// A reference implementation of a `BBBuffer<N>`, with the API of both its
+/// `Producer` and its `Consumer`
+///
+/// Dropping a grant is modelled by committing or releasing zero bytes, which
+/// is what the `Drop` impls of the grants do. Closing either half, and
+/// poisoning, are not modelled.
+#[derive(Debug, Clone, Default)]
+pub struct ModelQueue<const N: usize> {
+    /// The bytes that were committed but not yet released, in order
+    committed: VecDeque<u8>,
+
+    /// Where the next byte will be written
+    write: usize,
+
+    /// Where the next byte will be read from
+    read: usize,
+
+    /// The end of the bytes before `write` wrapped around, while inverted
+    last: usize,
+
+    /// Has `write` wrapped around to the start, but `read` not yet?
+    inverted: bool,
+
+    /// The start and length of the write grant in progress
+    write_grant: Option<(usize, usize)>,
+
+    /// The lengths of both parts of the read grant in progress. Only a grant
+    /// of `split_read()` has a second part.
+    read_grant: Option<(usize, usize)>,
+}
+
+impl<const N: usize> ModelQueue<N> {
+    /// Create an empty model
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of committed bytes that have not been released, as with
+    /// `Consumer::bytes_available()`
+    pub fn len(&self) -> usize {
+        self.committed.len()
+    }
+
+    /// Returns `true` if no committed bytes are waiting to be released
+    pub fn is_empty(&self) -> bool {
+        self.committed.is_empty()
+    }
+
+    /// The committed bytes that have not been released, in order
+    pub fn committed(&self) -> &VecDeque<u8> {
+        &self.committed
+    }
+
+    /// Model `Producer::grant_exact()`, returning the length of the grant
+    pub fn grant_exact(&mut self, sz: usize) -> Result<usize> {
+        if self.write_grant.is_some() {
+            return Err(Error::GrantInProgress);
+        }
+
+        let start = if self.inverted {
+            // Only the gap up to `read` is free
+            if self.write + sz <= self.read {
+                self.write
+            } else {
+                return Err(Error::InsufficientSize);
+            }
+        } else if self.write + sz <= N {
+            self.write
+        } else if sz <= self.read {
+            // Wrap around, up to `read`
+            0
+        } else if sz <= N && self.compact() {
+            0
+        } else {
+            return Err(Error::InsufficientSize);
+        };
+
+        self.write_grant = Some((start, sz));
+        Ok(sz)
+    }
+
+    /// Model `Producer::grant_max_remaining()`, returning the length of the
+    /// grant
+    pub fn grant_max_remaining(&mut self, sz: usize) -> Result<usize> {
+        if self.write_grant.is_some() {
+            return Err(Error::GrantInProgress);
+        }
+
+        let (start, len) = if self.inverted {
+            (self.write, self.read - self.write)
+        } else if self.write < N {
+            (self.write, N - self.write)
+        } else {
+            (0, self.read)
+        };
+        if len == 0 {
+            return Err(Error::InsufficientSize);
+        }
+
+        let len = min(len, sz);
+        self.write_grant = Some((start, len));
+        Ok(len)
+    }
+
+    /// Model `GrantW::commit()`, committing the bytes of `data` that fit in
+    /// the grant. Does nothing if no write grant is in progress.
+    pub fn commit(&mut self, data: &[u8]) {
+        let (start, len) = match self.write_grant.take() {
+            Some(grant) => grant,
+            None => return,
+        };
+        let used = min(data.len(), len);
+
+        // Committing nothing does not wrap around
+        if used == 0 {
+            return;
+        }
+        if start < self.write {
+            self.last = self.write;
+            self.inverted = true;
+        }
+        self.write = start + used;
+        self.committed.extend(&data[..used]);
+    }
+
+    /// Model `Consumer::read()`, returning the contents of the grant
+    pub fn read(&mut self) -> Result<&[u8]> {
+        if self.read_grant.is_some() {
+            return Err(Error::GrantInProgress);
+        }
+
+        // Follow `write` back to the start, once the end was reached
+        if self.inverted && self.read == self.last {
+            self.read = 0;
+            self.inverted = false;
+        }
+
+        let end = if self.inverted { self.last } else { self.write };
+        let len = end - self.read;
+        if len == 0 {
+            return Err(Error::Empty);
+        }
+
+        self.read_grant = Some((len, 0));
+        Ok(&self.committed.make_contiguous()[..len])
+    }
+
+    /// Model `Consumer::split_read()`, returning the contents of both parts
+    /// of the grant
+    pub fn split_read(&mut self) -> Result<(&[u8], &[u8])> {
+        let len = self.read()?.len();
+
+        // The bytes after wrapping around, if any
+        let wrapped = if self.inverted { self.write } else { 0 };
+        self.read_grant = Some((len, wrapped));
+
+        let (first, second) = self.committed.make_contiguous().split_at(len);
+        Ok((first, &second[..wrapped]))
+    }
+
+    /// Model `GrantR::release()` and `SplitGrantR::release()`, releasing up
+    /// to `used` bytes of the grant. Does nothing if no read grant is in
+    /// progress.
+    pub fn release(&mut self, used: usize) {
+        if let Some((first, second)) = self.read_grant.take() {
+            let used = min(used, first + second);
+            if used <= first {
+                self.read += used;
+            } else {
+                // Follow `write` back to the start
+                self.read = used - first;
+                self.inverted = false;
+            }
+            self.committed.drain(..used);
+        }
+    }
+
+    /// Move the positions of an empty queue back to the start, as the
+    /// `Producer` does when a grant would not fit otherwise
+    fn compact(&mut self) -> bool {
+        if self.inverted || self.write != self.read || self.read_grant.is_some() {
+            return false;
+        }
+        self.write = 0;
+        self.read = 0;
+        true
+    }
+}
+
+/// Check that a step had the same outcome on a real queue as on the model,
+/// panicking with a description of the step otherwise
+pub fn check_equiv<T: PartialEq + fmt::Debug>(step: fmt::Arguments<'_>, real: T, model: T) {
+    assert!(
+        real == model,
+        "{}: the queue returned {:?}, but the model returned {:?}",
+        step,
+        real,
+        model
+    );
+}
+
+/// Both halves of a real queue, and a `ModelQueue`, which every operation is
+/// run on, checking that they behave the same
+///
+/// Committed bytes hold a running counter, so that every byte can be told
+/// apart from its neighbours.
+pub struct Differential<'a, const N: usize> {
+    write_grant: Option<GrantW<'a, N>>,
+    read_grant: Option<GrantR<'a, N>>,
+    producer: Producer<'a, N>,
+    consumer: Consumer<'a, N>,
+    model: ModelQueue<N>,
+    next: u8,
+}
+
+impl<'a, const N: usize> Differential<'a, N> {
+    /// Split `bbq`, which must be empty
+    pub fn new(bbq: &'a BBBuffer<N>) -> Result<Self> {
+        let (producer, consumer) = bbq.try_split()?;
+        Ok(Self {
+            write_grant: None,
+            read_grant: None,
+            producer,
+            consumer,
+            model: ModelQueue::new(),
+            next: 0,
+        })
+    }
+
+    /// The model, which holds the bytes that should be in the queue
+    pub fn model(&self) -> &ModelQueue<N> {
+        &self.model
+    }
+
+    /// Request a grant with `Producer::grant_exact()`, holding on to it
+    pub fn grant_exact(&mut self, sz: usize) -> Result<usize> {
+        let real = self.producer.grant_exact(sz).map(|grant| {
+            let len = grant.len();
+            self.write_grant = Some(grant);
+            len
+        });
+        let model = self.model.grant_exact(sz);
+        self.check(format_args!("grant_exact({})", sz), real, model)
+    }
+
+    /// Request a grant with `Producer::grant_max_remaining()`, holding on to
+    /// it
+    pub fn grant_max_remaining(&mut self, sz: usize) -> Result<usize> {
+        let real = self.producer.grant_max_remaining(sz).map(|grant| {
+            let len = grant.len();
+            self.write_grant = Some(grant);
+            len
+        });
+        let model = self.model.grant_max_remaining(sz);
+        self.check(format_args!("grant_max_remaining({})", sz), real, model)
+    }
+
+    /// Fill up to `used` bytes of the write grant, and commit them. Does
+    /// nothing if no write grant is held.
+    pub fn commit(&mut self, used: usize) {
+        let mut grant = match self.write_grant.take() {
+            Some(grant) => grant,
+            None => return,
+        };
+
+        let used = min(used, grant.len());
+        for byte in grant[..used].iter_mut() {
+            *byte = self.next;
+            self.next = self.next.wrapping_add(1);
+        }
+        self.model.commit(&grant[..used]);
+        grant.commit(used);
+        self.check_len(format_args!("commit({})", used));
+    }
+
+    /// Request a grant with `Consumer::read()`, holding on to it, and return
+    /// its length
+    pub fn read(&mut self) -> Result<usize> {
+        let real = self.consumer.read();
+        let model = self.model.read();
+        check_equiv(
+            format_args!("read()"),
+            real.as_ref().map(|grant| &grant[..]).map_err(|e| *e),
+            model,
+        );
+        self.check_len(format_args!("read()"));
+
+        real.map(|grant| {
+            let len = grant.len();
+            self.read_grant = Some(grant);
+            len
+        })
+    }
+
+    /// Release up to `used` bytes of the read grant. Does nothing if no read
+    /// grant is held.
+    pub fn release(&mut self, used: usize) {
+        if let Some(grant) = self.read_grant.take() {
+            let used = min(used, grant.len());
+            grant.release(used);
+            self.model.release(used);
+            self.check_len(format_args!("release({})", used));
+        }
+    }
+
+    /// Drop both grants, without committing or releasing anything
+    pub fn drop_grants(&mut self) {
+        self.write_grant = None;
+        self.read_grant = None;
+        self.model.commit(&[]);
+        self.model.release(0);
+        self.check_len(format_args!("drop_grants()"));
+    }
+
+    /// Compare the outcome of a step, and the number of bytes left in the
+    /// queue afterwards
+    fn check<T: PartialEq + fmt::Debug>(&self, step: fmt::Arguments<'_>, real: T, model: T) -> T {
+        check_equiv(step, &real, &model);
+        self.check_len(step);
+        real
+    }
+
+    /// Compare the number of bytes left in the queue after a step
+    fn check_len(&self, step: fmt::Arguments<'_>) {
+        check_equiv(
+            format_args!("bytes_available() after {}", step),
+            self.consumer.bytes_available(),
+            self.model.len(),
+        );
+    }
+}
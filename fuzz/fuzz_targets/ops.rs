@@ -0,0 +1,9 @@
+//! Run with `cargo +nightly fuzz run ops`, from the repository root
+//!
+//! See `bbqueue::fuzz::run()` for how the input is interpreted.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| bbqueue::fuzz::run(data));
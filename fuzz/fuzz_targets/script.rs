@@ -0,0 +1,17 @@
+//! Run with `cargo +nightly fuzz run script`, from the repository root
+//!
+//! The fuzzer generates the `Op`s of `bbqueue::fuzz::apply_ops()` directly,
+//! and every step is compared with the reference model of `test-utils`.
+
+#![no_main]
+
+use bbqueue::{
+    fuzz::{apply_ops, Op},
+    BBBuffer,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|ops: Vec<Op>| {
+    let bb: BBBuffer<16> = BBBuffer::new();
+    apply_ops(&bb, &ops).unwrap();
+});